@@ -55,13 +55,37 @@ fn main() {
         None => return,
     };
 
+    if rustc >= 80 {
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_core_error)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_fmt_arguments_as_str)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_macro_reexport)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_ptr_addr_of)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_track_caller)");
+        println!("cargo:rustc-check-cfg=cfg(backtrace)");
+        println!("cargo:rustc-check-cfg=cfg(doc_cfg)");
+    }
+
     if rustc < 38 {
         println!("cargo:rustc-cfg=anyhow_no_macro_reexport");
     }
 
+    if rustc < 46 {
+        println!("cargo:rustc-cfg=anyhow_no_track_caller");
+    }
+
     if rustc < 51 {
         println!("cargo:rustc-cfg=anyhow_no_ptr_addr_of");
     }
+
+    if rustc < 52 {
+        println!("cargo:rustc-cfg=anyhow_no_fmt_arguments_as_str");
+    }
+
+    if rustc < 81 {
+        // core::error::Error stabilized in Rust 1.81.
+        // https://blog.rust-lang.org/2024/09/05/Rust-1.81.0.html#coreerrorerror
+        println!("cargo:rustc-cfg=anyhow_no_core_error");
+    }
 }
 
 fn compile_probe() -> Option<ExitStatus> {
@@ -5,6 +5,7 @@ pub fn err1() -> anyhow::Result<()> {
     Err(anyhow!("1_1"))
 }
 
+#[cfg(not(feature = "deny-empty-messages"))]
 pub fn err1_2() -> anyhow::Result<()> {
     Err(anyhow!())
 }
@@ -34,11 +35,19 @@ pub fn err4_3() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[cfg(not(feature = "deny-empty-messages"))]
 pub fn err4_4() -> anyhow::Result<()> {
     err4_3().map_err(anyhow_error!())?;
     Ok(())
 }
 
+// With "deny-empty-messages" every level needs a message.
+#[cfg(feature = "deny-empty-messages")]
+pub fn err4_4() -> anyhow::Result<()> {
+    err4_3().map_err(anyhow_error!("4_4"))?;
+    Ok(())
+}
+
 fn main() {
     let ret = err4_4();
     if let Err(e) = ret {
@@ -0,0 +1,488 @@
+use crate::alloc::{Box, String, Vec};
+use crate::error::ErrorImpl;
+use crate::template::tag;
+use crate::Error;
+use core::any::Any;
+use core::fmt::{self, Display};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Limits installed by set_max_attachments and set_max_suppressed, stored plus
+// one so that zero can mean no limit.
+static MAX_ATTACHMENTS: AtomicUsize = AtomicUsize::new(0);
+static MAX_SUPPRESSED: AtomicUsize = AtomicUsize::new(0);
+
+/// Limit how many attachments an error keeps, to bound the memory held by
+/// errors that pass through many layers.
+///
+/// Once an error has `max` attachments, attaching another drops the oldest,
+/// so that lookups with [`Error::attachment`] keep finding the most recent
+/// value of each type. Every kind of attachment counts toward the limit,
+/// including statuses, codes, URLs and printable attachments. There is no
+/// limit until one is set. The setting is process-wide and applies to values
+/// attached from then on.
+pub fn set_max_attachments(max: usize) {
+    MAX_ATTACHMENTS.store(max.saturating_add(1), Ordering::SeqCst);
+}
+
+/// Limit how many suppressed errors an error keeps, to bound the memory held
+/// by errors that pass through many layers.
+///
+/// Once an error has `max` suppressed errors, [`Error::also`] drops the
+/// oldest to make room for the new one. There is no limit until one is set.
+/// The setting is process-wide and applies to errors suppressed from then
+/// on.
+pub fn set_max_suppressed(max: usize) {
+    MAX_SUPPRESSED.store(max.saturating_add(1), Ordering::SeqCst);
+}
+
+// Appends `value`, then drops the oldest values beyond the installed limit.
+fn push_bounded<T>(values: &mut Vec<T>, value: T, limit: &AtomicUsize) {
+    values.push(value);
+    if let Some(max) = limit.load(Ordering::SeqCst).checked_sub(1) {
+        if values.len() > max {
+            let excess = values.len() - max;
+            values.drain(..excess);
+        }
+    }
+}
+
+// Wrapper that keeps statuses apart from ordinary attachments of the same type.
+struct Status<S>(S);
+
+// Wrapper that keeps categories apart from ordinary attachments of the same
+// type.
+struct Category<K>(K);
+
+// The machine-readable code given to Context::context_coded or anyhow_code!.
+pub(crate) struct Code(pub &'static str);
+
+// The error made by anyhow_code!: `msg` tagged with the site and the code,
+// with the code also attached for Error::code.
+#[cold]
+pub fn coded(code: &'static str, site: &str, line: u32, column: u32, msg: &dyn Display) -> Error {
+    let punct = crate::private::format!(" code({})", code);
+    Error::msg(tag(site, line, column, &punct, msg)).attach(Code(code))
+}
+
+// A value given to Error::attach_printable, together with a way to display it.
+pub(crate) struct Printable {
+    value: Box<dyn Any + Send + Sync>,
+    display: fn(&(dyn Any + Send + Sync), &mut fmt::Formatter) -> fmt::Result,
+}
+
+impl Display for Printable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.display)(&*self.value, f)
+    }
+}
+
+fn display<T>(value: &(dyn Any + Send + Sync), f: &mut fmt::Formatter) -> fmt::Result
+where
+    T: Display + 'static,
+{
+    match value.downcast_ref::<T>() {
+        Some(value) => Display::fmt(value, f),
+        None => Ok(()),
+    }
+}
+
+// The remediation URL given to Error::with_url.
+pub(crate) struct Url(pub &'static str);
+
+// The field/value pairs given to Error::from_fields or Error::with_fields.
+struct Fields(Vec<(String, String)>);
+
+impl Error {
+    /// Attach a typed value to this error.
+    ///
+    /// Attachments are not part of the error's message. They belong to the
+    /// error as a whole and stay with it as context is added, and are looked
+    /// up by type with [`get`][Error::get].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// struct RequestId(u64);
+    ///
+    /// let error = anyhow!("upstream timed out").attach(RequestId(7));
+    /// let error = error.context("failed to render page");
+    ///
+    /// assert_eq!(error.get::<RequestId>().unwrap().0, 7);
+    /// ```
+    pub fn attach<T>(mut self, value: T) -> Self
+    where
+        T: Any + Send + Sync,
+    {
+        let meta = unsafe { ErrorImpl::meta_mut(self.inner.by_mut()) };
+        push_bounded(&mut meta.attachments, Box::new(value), &MAX_ATTACHMENTS);
+        self
+    }
+
+    /// Attach a typed value to this error that is also shown when the error
+    /// is printed with `{:?}`.
+    ///
+    /// The value is looked up with [`attachment`][Error::attachment] like any
+    /// other attachment. In addition, every printable attachment is listed,
+    /// oldest first, in an `Attachments:` section following the causes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("upstream timed out").attach_printable("request id: 7");
+    ///
+    /// assert_eq!(error.attachment::<&str>(), Some(&"request id: 7"));
+    /// assert!(format!("{:?}", error).contains("Attachments:\n    request id: 7"));
+    /// ```
+    pub fn attach_printable<T>(self, value: T) -> Self
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        self.attach(Printable {
+            value: Box::new(value),
+            display: display::<T>,
+        })
+    }
+
+    /// The most recently attached value of type `T`, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct RequestId(u64);
+    ///
+    /// let error = anyhow!("upstream timed out").attach(RequestId(7));
+    ///
+    /// assert_eq!(error.get::<RequestId>(), Some(&RequestId(7)));
+    /// assert_eq!(error.get::<u32>(), None);
+    /// ```
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        let meta = unsafe { ErrorImpl::meta(self.inner.by_ref()) }?;
+        meta.attachments.iter().rev().find_map(|attachment| {
+            match attachment.downcast_ref::<Printable>() {
+                Some(printable) => printable.value.downcast_ref::<T>(),
+                None => attachment.downcast_ref::<T>(),
+            }
+        })
+    }
+
+    /// The most recently attached value of type `T`, the same as
+    /// [`get`][Error::get].
+    pub fn attachment<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        self.get()
+    }
+
+    /// Pair this error with a status, such as an HTTP status code, that is
+    /// carried along through `?` and context.
+    ///
+    /// The status is stored as an attachment, separately from any ordinary
+    /// attachment of the same type. A later call replaces an earlier one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// #[derive(Copy, Clone, Debug, PartialEq)]
+    /// struct StatusCode(u16);
+    ///
+    /// fn find_user(id: u64) -> Result<()> {
+    ///     Err(anyhow!("no user {}", id).with_status(StatusCode(404)))
+    /// }
+    ///
+    /// fn handler() -> Result<(), (StatusCode, anyhow::Error)> {
+    ///     find_user(1).context("loading profile").map_err(|error| {
+    ///         let status = error.status().copied().unwrap_or(StatusCode(500));
+    ///         (status, error)
+    ///     })
+    /// }
+    ///
+    /// assert_eq!(handler().unwrap_err().0, StatusCode(404));
+    /// ```
+    pub fn with_status<S>(self, status: S) -> Self
+    where
+        S: Any + Send + Sync,
+    {
+        self.attach(Status(status))
+    }
+
+    /// The status given to [`with_status`][Error::with_status], if any.
+    pub fn status<S>(&self) -> Option<&S>
+    where
+        S: Any,
+    {
+        self.attachment::<Status<S>>().map(|status| &status.0)
+    }
+
+    /// Put this error in a category, such as the subsystem it came from.
+    ///
+    /// The category may be a value of any type, typically an enum defined by
+    /// the application, and is read back with [`category`][Error::category].
+    /// It is stored apart from ordinary attachments and statuses of the same
+    /// type, stays with the error as context is added, and a later call
+    /// replaces an earlier one. The [`categorize!`][crate::categorize] macro
+    /// categorizes every error coming out of a block of code.
+    pub fn with_category<K>(self, category: K) -> Self
+    where
+        K: Any + Send + Sync,
+    {
+        self.attach(Category(category))
+    }
+
+    /// The category given to [`with_category`][Error::with_category], if any.
+    pub fn category<K>(&self) -> Option<&K>
+    where
+        K: Any,
+    {
+        self.attachment::<Category<K>>().map(|category| &category.0)
+    }
+
+    /// The code most recently given to
+    /// [`context_coded`][crate::Context::context_coded] or
+    /// [`anyhow_code!`][crate::anyhow_code], if any.
+    pub fn code(&self) -> Option<&'static str> {
+        self.attachment::<Code>().map(|code| code.0)
+    }
+
+    /// Point readers of this error to a page explaining how to resolve it.
+    ///
+    /// The URL is shown as a `See: ...` line at the end of the `{:?}`
+    /// representation, ahead of any backtrace. Like a status, it belongs to
+    /// the error as a whole and stays with it as context is added, and a
+    /// later call replaces an earlier one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn load() -> Result<()> {
+    ///     Err(anyhow!("unsupported config version"))
+    ///         .map_err(|error| error.with_url("https://example.com/errors/E123"))
+    /// }
+    ///
+    /// let error = load().context("starting up").unwrap_err();
+    /// assert_eq!(error.url(), Some("https://example.com/errors/E123"));
+    /// ```
+    pub fn with_url(self, url: &'static str) -> Self {
+        self.attach(Url(url))
+    }
+
+    /// The URL given to [`with_url`][Error::with_url], if any.
+    pub fn url(&self) -> Option<&'static str> {
+        self.attachment::<Url>().map(|url| url.0)
+    }
+
+    /// Create an error whose substance is structured data, tagged with the
+    /// caller's location.
+    ///
+    /// The message is tagged like [`Error::msg_located`] and the fields are
+    /// attached to the error, to be read back with
+    /// [`fields`][Error::fields]. They are not part of the message.
+    ///
+    /// Requires Rust 1.46 or newer, which is the first to support
+    /// `#[track_caller]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::from_fields(
+    ///     "invalid listener config",
+    ///     vec![
+    ///         ("port".to_owned(), "70000".to_owned()),
+    ///         ("host".to_owned(), String::new()),
+    ///     ],
+    /// );
+    ///
+    /// for (field, value) in error.fields() {
+    ///     eprintln!("  {} = {:?}", field, value);
+    /// }
+    /// assert_eq!(error.fields()[0].0, "port");
+    /// ```
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[cold]
+    #[track_caller]
+    pub fn from_fields<M, I>(message: M, fields: I) -> Self
+    where
+        M: core::fmt::Display,
+        I: IntoIterator<Item = (String, String)>,
+    {
+        Error::msg_located(message).attach(Fields(fields.into_iter().collect()))
+    }
+
+    /// Attach more field/value pairs to this error, such as those known to a
+    /// layer adding context.
+    ///
+    /// [`fields`][Error::fields] then returns these, while
+    /// [`all_fields`][Error::all_fields] combines them with any attached
+    /// before.
+    pub fn with_fields<I>(self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.attach(Fields(fields.into_iter().collect()))
+    }
+
+    /// The field/value pairs given to the most recent
+    /// [`from_fields`][Error::from_fields] or
+    /// [`with_fields`][Error::with_fields], in their original order, or an
+    /// empty slice.
+    pub fn fields(&self) -> &[(String, String)] {
+        match self.attachment::<Fields>() {
+            Some(fields) => &fields.0,
+            None => &[],
+        }
+    }
+
+    /// Every field/value pair attached to this error, by
+    /// [`from_fields`][Error::from_fields] and by each call to
+    /// [`with_fields`][Error::with_fields], for structured logging.
+    ///
+    /// Where the same field was given more than once, the value attached
+    /// last wins, which is normally the one from the outermost layer. The
+    /// fields attached last come first, each call's fields in their original
+    /// order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::from_fields("query failed", vec![("table".to_owned(), "users".to_owned())])
+    ///     .context("loading profile")
+    ///     .with_fields(vec![("user".to_owned(), "7".to_owned())]);
+    ///
+    /// assert_eq!(error.all_fields(), [("user", "7"), ("table", "users")]);
+    /// ```
+    pub fn all_fields(&self) -> Vec<(&str, &str)> {
+        let mut all: Vec<(&str, &str)> = Vec::new();
+        let meta = match unsafe { ErrorImpl::meta(self.inner.by_ref()) } {
+            Some(meta) => meta,
+            None => return all,
+        };
+        let sets = meta
+            .attachments
+            .iter()
+            .rev()
+            .filter_map(|attachment| attachment.downcast_ref::<Fields>());
+        for fields in sets {
+            for (field, value) in &fields.0 {
+                if all.iter().all(|&(seen, _)| seen != field) {
+                    all.push((field, value));
+                }
+            }
+        }
+        all
+    }
+
+    /// Record another, unrelated failure alongside this one.
+    ///
+    /// This is for when a second operation fails while handling the first,
+    /// such as cleanup after a failed write. `self` stays the primary error:
+    /// its message, causes and `Display` output are unchanged, and `other` is
+    /// kept as a suppressed error that [`suppressed`][Error::suppressed]
+    /// returns and the `{:?}` representation lists at the end.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Result};
+    ///
+    /// fn write() -> Result<()> {
+    ///     Err(anyhow!("disk full"))
+    /// }
+    ///
+    /// fn remove_partial_file() -> Result<()> {
+    ///     Err(anyhow!("permission denied"))
+    /// }
+    ///
+    /// fn save() -> Result<()> {
+    ///     write().map_err(|error| match remove_partial_file() {
+    ///         Ok(()) => error,
+    ///         Err(cleanup) => error.also(cleanup),
+    ///     })
+    /// }
+    ///
+    /// let error = save().unwrap_err();
+    /// assert_eq!(error.suppressed().len(), 1);
+    /// ```
+    pub fn also(mut self, other: Error) -> Self {
+        let meta = unsafe { ErrorImpl::meta_mut(self.inner.by_mut()) };
+        push_bounded(&mut meta.suppressed, other, &MAX_SUPPRESSED);
+        self
+    }
+
+    /// The errors recorded by [`also`][Error::also], in the order they were
+    /// added.
+    pub fn suppressed(&self) -> &[Error] {
+        match unsafe { ErrorImpl::meta(self.inner.by_ref()) } {
+            Some(meta) => &meta.suppressed,
+            None => &[],
+        }
+    }
+
+    /// Count one more attempt at the operation that failed with this error.
+    ///
+    /// Meant for retry loops: bump the count each time the error is carried
+    /// into the next attempt, and the final error can report how many were
+    /// made. The count stays with the error as context is added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Result};
+    ///
+    /// fn connect() -> Result<()> {
+    ///     Err(anyhow!("connection refused"))
+    /// }
+    ///
+    /// // Retries up to five times, reporting the first failure.
+    /// fn connect_with_retry() -> Result<()> {
+    ///     let mut error = match connect() {
+    ///         Ok(()) => return Ok(()),
+    ///         Err(error) => error.bump_attempt(),
+    ///     };
+    ///     while error.attempts() < 5 {
+    ///         match connect() {
+    ///             Ok(()) => return Ok(()),
+    ///             Err(_) => error = error.bump_attempt(),
+    ///         }
+    ///     }
+    ///     let attempts = error.attempts();
+    ///     Err(error.context(format!("failed after {} attempts", attempts)))
+    /// }
+    ///
+    /// let error = connect_with_retry().unwrap_err();
+    /// assert_eq!(error.to_string(), "failed after 5 attempts");
+    /// assert_eq!(error.attempts(), 5);
+    /// ```
+    pub fn bump_attempt(mut self) -> Self {
+        let meta = unsafe { ErrorImpl::meta_mut(self.inner.by_mut()) };
+        meta.attempts = meta.attempts.saturating_add(1);
+        self
+    }
+
+    /// The number of times [`bump_attempt`][Error::bump_attempt] was called
+    /// on this error.
+    pub fn attempts(&self) -> u32 {
+        match unsafe { ErrorImpl::meta(self.inner.by_ref()) } {
+            Some(meta) => meta.attempts,
+            None => 0,
+        }
+    }
+}
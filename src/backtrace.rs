@@ -35,6 +35,22 @@ macro_rules! backtrace {
     };
 }
 
+// A backtrace that prints nothing, for an error rebuilt from the rendering
+// of another one, whose backtrace is already part of its message.
+#[cfg(any(backtrace, feature = "backtrace"))]
+macro_rules! no_backtrace {
+    () => {
+        Some(crate::backtrace::Backtrace::disabled())
+    };
+}
+
+#[cfg(not(any(backtrace, feature = "backtrace")))]
+macro_rules! no_backtrace {
+    () => {
+        None
+    };
+}
+
 #[cfg(backtrace)]
 macro_rules! backtrace_if_absent {
     ($err:expr) => {
@@ -198,10 +214,15 @@ mod capture {
             enabled
         }
 
+        pub(crate) fn disabled() -> Backtrace {
+            let inner = Inner::Disabled;
+            Backtrace { inner }
+        }
+
         #[inline(never)] // want to make sure there's a frame here to remove
         pub(crate) fn capture() -> Backtrace {
             if Backtrace::enabled() {
-                Backtrace::create(Backtrace::capture as usize)
+                Backtrace::create(Backtrace::capture as *const () as usize)
             } else {
                 let inner = Inner::Disabled;
                 Backtrace { inner }
@@ -384,7 +405,7 @@ mod capture {
         };
         if print_fmt == PrintFmt::Short && file.is_absolute() {
             if let Some(cwd) = cwd {
-                if let Ok(stripped) = file.strip_prefix(&cwd) {
+                if let Ok(stripped) = file.strip_prefix(cwd) {
                     if let Some(s) = stripped.to_str() {
                         return write!(fmt, ".{}{}", path::MAIN_SEPARATOR, s);
                     }
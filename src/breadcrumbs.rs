@@ -0,0 +1,134 @@
+#[cfg(not(anyhow_no_track_caller))]
+use crate::error::ErrorImpl;
+use crate::location::Location;
+use crate::Error;
+use core::fmt::{self, Display};
+
+/// The sites an error passed through, from where it was created to where it
+/// is now.
+///
+/// Returned by [`Error::breadcrumbs`]. Unlike a backtrace, which is the call stack
+/// at the moment the error was created, this follows the error value itself
+/// on its way out: its origin, then every context and propagation site in the
+/// order they were passed. It displays as
+/// `origin src/a.rs:3 -> src/b.rs:10 -> src/c.rs:22`, or as `origin unknown`
+/// if no sites were recorded.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breadcrumbs {
+    sites: Vec<Location>,
+}
+
+impl Breadcrumbs {
+    /// The sites in the order the error passed them, origin first.
+    pub fn sites(&self) -> &[Location] {
+        &self.sites
+    }
+}
+
+impl Error {
+    /// The path this error took, combining the location of each level of
+    /// the chain with the sites recorded by [`propagated`][Error::propagated].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, anyhow_located, Result};
+    ///
+    /// fn open() -> Result<()> {
+    ///     Err(anyhow_located!("no such file"))
+    /// }
+    ///
+    /// fn load() -> Result<()> {
+    ///     open().map_err(|e| e.propagated())?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let error = load().map_err(anyhow_context!("loading config")).unwrap_err();
+    /// eprintln!("{}", error.breadcrumbs());
+    /// assert_eq!(error.breadcrumbs().sites().len(), 3);
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn breadcrumbs(&self) -> Breadcrumbs {
+        let mut levels: Vec<_> = self.locations().collect();
+        levels.reverse();
+
+        // A site recorded while the chain was n levels long was passed after
+        // the innermost n levels were created and before the next one was.
+        // Rerooting can leave the chain shorter than when a site was recorded;
+        // such sites count as passed after the whole current chain.
+        #[cfg(not(anyhow_no_track_caller))]
+        {
+            let len = levels.len();
+            let propagation: Vec<_> = unsafe { ErrorImpl::header(self.inner.by_ref()) }
+                .propagation()
+                .collect();
+            let mut sites = Vec::new();
+            for (n, level) in levels.into_iter().enumerate() {
+                sites.extend(level);
+                for site in &propagation {
+                    if (site.depth as usize).min(len) == n + 1 {
+                        sites.push(Location::of(site.location));
+                    }
+                }
+            }
+            Breadcrumbs { sites }
+        }
+
+        // Without track_caller no sites are recorded.
+        #[cfg(anyhow_no_track_caller)]
+        Breadcrumbs {
+            sites: levels.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Where this error was created: the first of its
+    /// [breadcrumbs][Error::breadcrumbs].
+    ///
+    /// That is the location of the innermost level of the chain that
+    /// recorded one or, if none did, the first site the error was propagated
+    /// through.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("no such file"))
+    ///     .map_err(anyhow_context!("loading config"))
+    ///     .unwrap_err();
+    ///
+    /// let origin = error.origin_location().unwrap();
+    /// let latest = error.latest_location().unwrap();
+    /// assert!(latest.line() > origin.line());
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn origin_location(&self) -> Option<Location> {
+        self.breadcrumbs().sites.into_iter().next()
+    }
+
+    /// Where this error was most recently handled, by located context or by
+    /// a recorded propagation: the last of its
+    /// [breadcrumbs][Error::breadcrumbs].
+    ///
+    /// For an error that was created and never touched since, this is the
+    /// same as [`origin_location()`][Error::origin_location].
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn latest_location(&self) -> Option<Location> {
+        self.breadcrumbs().sites.pop()
+    }
+}
+
+impl Display for Breadcrumbs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.sites.is_empty() {
+            return f.write_str("origin unknown");
+        }
+        f.write_str("origin")?;
+        for (n, site) in self.sites.iter().enumerate() {
+            let separator = if n == 0 { " " } else { " -> " };
+            write!(f, "{}{}", separator, site)?;
+        }
+        Ok(())
+    }
+}
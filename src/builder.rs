@@ -0,0 +1,81 @@
+use crate::alloc::String;
+use crate::separator::line_separator;
+use crate::template::render;
+use crate::Error;
+use core::fmt::Display;
+
+/// Collects several independent problems into one error, each with a
+/// location tag of its own.
+///
+/// Validation code that finds more than one problem can report them all at
+/// once instead of stopping at the first. The contexts are siblings rather
+/// than a chain of causes: [`build`][ErrorBuilder::build] joins them in the
+/// order they were pushed with the [line separator][crate::line_separator],
+/// each rendered as `[file:line, emsg(msg)]` the way `anyhow_context!`
+/// renders its own, so that every one of them is a frame of the error.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{ErrorBuilder, Result};
+///
+/// fn validate(port: u32, host: &str) -> Result<()> {
+///     let mut problems = ErrorBuilder::new();
+///     if port > 65535 {
+///         problems.push_context(file!(), line!(), format_args!("port {} out of range", port));
+///     }
+///     if host.is_empty() {
+///         problems.push_context(file!(), line!(), "host is empty");
+///     }
+///     if problems.is_empty() {
+///         return Ok(());
+///     }
+///     Err(problems.build())
+/// }
+///
+/// let error = validate(70000, "").unwrap_err();
+/// assert_eq!(error.parsed_frames().count(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ErrorBuilder {
+    message: String,
+    len: usize,
+}
+
+impl ErrorBuilder {
+    /// A builder with no contexts yet.
+    pub fn new() -> Self {
+        ErrorBuilder::default()
+    }
+
+    /// Add `msg`, tagged with `file` and `line`, as the next context.
+    pub fn push_context<M>(&mut self, file: &str, line: u32, msg: M) -> &mut Self
+    where
+        M: Display,
+    {
+        if self.len > 0 {
+            self.message.push_str(line_separator());
+        }
+        self.message.push_str(&render(file, line, None, ",", &msg));
+        self.len += 1;
+        self
+    }
+
+    /// The number of contexts pushed so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no context has been pushed, in which case there is likely no
+    /// error to report.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The error listing every context pushed, one frame each, outermost
+    /// first. With no contexts, the message is empty.
+    #[cold]
+    pub fn build(self) -> Error {
+        Error::msg(self.message)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::Error;
+use std::any::Any;
+use std::panic::{self, UnwindSafe};
+
+/// Run `f`, turning a panic inside it into a located error.
+///
+/// If `f` returns, its value is passed through as `Ok`. If it panics, the
+/// panic is caught with [`std::panic::catch_unwind`] and becomes an error
+/// whose message is the panic message, tagged with the location of the call
+/// to `catch` just as `anyhow_located!` would tag it. Panics with a payload
+/// other than a `&str` or `String` get the message `Box<dyn Any>`, as the
+/// standard library's panic message does.
+///
+/// The panic hook still runs before the panic is caught, so the default hook
+/// still prints the panic to stderr. Panics that abort rather than unwind,
+/// such as under `panic = "abort"`, are not caught.
+///
+/// Requires Rust 1.46 or newer, which is the first to support
+/// `#[track_caller]`.
+///
+/// # Example
+///
+/// ```
+/// let error = anyhow::catch(|| -> u32 { panic!("index out of range") }).unwrap_err();
+/// let line = line!() - 1;
+///
+/// assert_eq!(
+///     error.to_string(),
+///     format!("[{}:{}:13 emsg(index out of range)]", file!(), line),
+/// );
+/// ```
+#[track_caller]
+pub fn catch<F, T>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> T + UnwindSafe,
+{
+    let caller = core::panic::Location::caller();
+    panic::catch_unwind(f).map_err(|payload| {
+        Error::msg(crate::template::tag(
+            caller.file(),
+            caller.line(),
+            caller.column(),
+            "",
+            &message(&*payload),
+        ))
+    })
+}
+
+// The message of a panic payload, as the standard library's hook prints it.
+fn message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
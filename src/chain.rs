@@ -1,5 +1,17 @@
 use self::ChainState::*;
-use crate::StdError;
+use crate::alloc::{Box, String, ToString, Vec};
+use crate::error::ErrorImpl;
+use crate::fmt::Unformatted;
+use crate::location::{extract_one, extract_tag, Location};
+use crate::parens::unbalance;
+use crate::parse::ParsedFrame;
+use crate::separator::{line_separator, separator_id, unescape};
+use crate::{Error, StdError};
+use core::iter;
+use core::mem;
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 #[cfg(feature = "std")]
 use std::vec;
@@ -100,3 +112,276 @@ impl Default for Chain<'_> {
         }
     }
 }
+
+/// Iterator over the frames of an error's `{:?}` representation.
+///
+/// Returned by [`Error::parsed_frames`]. The frames are the same as those
+/// [`parse_frames`][crate::parse_frames] collects. They are parsed the first
+/// time any iterator over them is created, and kept with the error for the
+/// next ones.
+#[derive(Clone)]
+pub struct Frames<'a> {
+    frames: slice::Iter<'a, ParsedFrame>,
+}
+
+// What is parsed out of an error, each part on first use: the frames of its
+// unformatted `{:-?}` representation, its location, and the frames of its
+// levels. Anything that may change the messages clears it.
+pub(crate) struct FrameCache {
+    parsed: Cached<Parsed>,
+    location: Cached<Option<Location>>,
+    all_frames: Cached<Vec<ParsedFrame>>,
+}
+
+pub(crate) struct Parsed {
+    pub frames: Vec<ParsedFrame>,
+    // The key-value pairs of anyhow_kv! in the tags, outermost first.
+    pub kv: Vec<(String, String)>,
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        FrameCache {
+            parsed: Cached::new(),
+            location: Cached::new(),
+            all_frames: Cached::new(),
+        }
+    }
+}
+
+impl FrameCache {
+    pub(crate) fn clear(&mut self) {
+        self.parsed.clear();
+        self.location.clear();
+        self.all_frames.clear();
+    }
+}
+
+// One part of a FrameCache. Messages are split up at the line separator, so
+// a part parsed under a separator that has since been replaced is parsed
+// again. The stale part is kept until the cache is cleared, since references
+// to it may still be out.
+struct Cached<T> {
+    slot: AtomicPtr<Slot<T>>,
+}
+
+struct Slot<T> {
+    separator: usize,
+    value: T,
+    stale: *mut Slot<T>,
+}
+
+impl<T> Cached<T> {
+    fn new() -> Self {
+        Cached {
+            slot: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        let separator = separator_id();
+        let mut current = self.slot.load(Ordering::Acquire);
+        if !current.is_null() && unsafe { (*current).separator } == separator {
+            return unsafe { &(*current).value };
+        }
+        // Two threads may both parse; the loser drops its copy and uses the
+        // one that was stored first.
+        let slot = Box::into_raw(Box::new(Slot {
+            separator,
+            value: init(),
+            stale: current,
+        }));
+        loop {
+            match self
+                .slot
+                .compare_exchange(current, slot, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return unsafe { &(*slot).value },
+                Err(stored) if unsafe { (*stored).separator } == separator => {
+                    drop(unsafe { Box::from_raw(slot) });
+                    return unsafe { &(*stored).value };
+                }
+                Err(stored) => {
+                    unsafe { (*slot).stale = stored };
+                    current = stored;
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        let mut slot = mem::replace(self.slot.get_mut(), ptr::null_mut());
+        while !slot.is_null() {
+            let cached = unsafe { Box::from_raw(slot) };
+            slot = cached.stale;
+        }
+    }
+}
+
+impl<T> Drop for Cached<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl Error {
+    /// An iterator over the frames of this error's `{:?}` representation,
+    /// outermost first.
+    ///
+    /// See [`parse_frames`][crate::parse_frames] for how the representation
+    /// is split up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, anyhow_error};
+    ///
+    /// let error = Err::<(), _>(anyhow!("no such file"))
+    ///     .map_err(anyhow_error!("loading config"))
+    ///     .unwrap_err();
+    ///
+    /// let lines: Vec<u32> = error.parsed_frames().map(|frame| frame.line).collect();
+    /// assert_eq!(lines[..2], [line!() - 4, line!() - 5]);
+    /// ```
+    pub fn parsed_frames(&self) -> Frames<'_> {
+        Frames {
+            frames: self.parsed().frames.iter(),
+        }
+    }
+
+    pub(crate) fn parsed(&self) -> &Parsed {
+        let cache = unsafe { ErrorImpl::frames(self.inner.by_ref()) };
+        cache.parsed.get_or_init(|| {
+            let debug = crate::private::format!("{:-?}", Unformatted(self));
+            let mut offset = 0;
+            let mut pending = None;
+            let frames = iter::from_fn(|| next_frame(&debug, &mut offset, &mut pending)).collect();
+            let kv = debug
+                .split(line_separator())
+                .filter(|segment| segment.starts_with('['))
+                .filter_map(|segment| extract_tag(segment)?.3)
+                .flat_map(crate::kv::decode)
+                .collect();
+            Parsed { frames, kv }
+        })
+    }
+
+    // The location of Error::location, kept for it and for downcasting to
+    // Location.
+    pub(crate) fn cached_location(&self) -> Option<&Location> {
+        let cache = unsafe { ErrorImpl::frames(self.inner.by_ref()) };
+        cache.location.get_or_init(|| self.find_location()).as_ref()
+    }
+
+    /// An iterator over the frames of every level of this error's
+    /// [`chain()`][Error::chain], outermost first.
+    ///
+    /// Unlike [`parsed_frames()`][Error::parsed_frames], which parses the
+    /// whole `{:?}` representation at once, each level is parsed on its own
+    /// from its `Display`. The frames of a level without location tags, such
+    /// as a foreign error in the source chain, come out as one untagged
+    /// frame, and the `Caused by:` list and backtrace of the `{:?}`
+    /// representation never show up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, Error};
+    ///
+    /// let error = Err::<(), _>(Error::msg("no such file"))
+    ///     .map_err(anyhow_context!("loading config"))
+    ///     .unwrap_err();
+    ///
+    /// let messages: Vec<String> = error.all_frames().map(|frame| frame.msg).collect();
+    /// assert_eq!(messages, ["loading config", "no such file"]);
+    /// ```
+    pub fn all_frames(&self) -> impl Iterator<Item = ParsedFrame> + '_ {
+        let cache = unsafe { ErrorImpl::frames(self.inner.by_ref()) };
+        let frames = cache.all_frames.get_or_init(|| {
+            let chain = unsafe { ErrorImpl::chain(self.inner.by_ref()) };
+            chain
+                .flat_map(|level| {
+                    let display = crate::private::format!("{:-}", level);
+                    let mut offset = 0;
+                    let mut pending = None;
+                    iter::from_fn(move || next_frame(&display, &mut offset, &mut pending))
+                })
+                .collect()
+        });
+        frames.iter().cloned()
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = ParsedFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+// The next frame of `text` from `offset` on, which is advanced past the
+// segment the frame came from. A segment with both a tag and an untagged
+// remainder yields the tagged frame and leaves the remainder in `pending`.
+pub(crate) fn next_frame(
+    text: &str,
+    offset: &mut usize,
+    pending: &mut Option<ParsedFrame>,
+) -> Option<ParsedFrame> {
+    if let Some(frame) = pending.take() {
+        return Some(frame);
+    }
+    let separator = line_separator();
+    while *offset <= text.len() {
+        let rest = &text[*offset..];
+        // An empty separator is no separator, which also keeps the offset
+        // moving.
+        let end = if separator.is_empty() {
+            None
+        } else {
+            rest.find(separator)
+        };
+        let (segment, next) = match end {
+            Some(end) => (&rest[..end], *offset + end + separator.len()),
+            // One past the end, so the loop stops after the last segment.
+            None => (rest, text.len() + 1),
+        };
+        *offset = next;
+
+        let mut tagged = None;
+        let mut rest = segment;
+        if rest.starts_with('[') {
+            if let Some((file, line, msg, len)) = extract_one(rest) {
+                tagged = Some(ParsedFrame {
+                    file: file.to_string(),
+                    line,
+                    msg: unescape(&unbalance(msg)).into_owned(),
+                });
+                rest = &rest[len..];
+            }
+        }
+        let rest = rest.trim();
+        let untagged = if rest.is_empty() {
+            None
+        } else {
+            Some(ParsedFrame {
+                file: String::new(),
+                line: 0,
+                msg: unescape(rest).into_owned(),
+            })
+        };
+        match tagged {
+            Some(frame) => {
+                *pending = untagged;
+                return Some(frame);
+            }
+            None if untagged.is_some() => return untagged,
+            None => {}
+        }
+    }
+    None
+}
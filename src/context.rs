@@ -1,3 +1,5 @@
+use crate::alloc::Cow;
+use crate::attach::Code;
 use crate::error::ContextError;
 use crate::{Context, Error, StdError};
 use core::convert::Infallible;
@@ -13,6 +15,8 @@ mod ext {
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static;
+
+        fn ext_into(self) -> Error;
     }
 
     #[cfg(feature = "std")]
@@ -27,6 +31,11 @@ mod ext {
             let backtrace = backtrace_if_absent!(self);
             Error::from_context(context, self, backtrace)
         }
+
+        fn ext_into(self) -> Error {
+            let backtrace = backtrace_if_absent!(self);
+            Error::from_std(self, backtrace)
+        }
     }
 
     impl StdError for Error {
@@ -36,6 +45,10 @@ mod ext {
         {
             self.context(context)
         }
+
+        fn ext_into(self) -> Error {
+            self
+        }
     }
 }
 
@@ -43,20 +56,113 @@ impl<T, E> Context<T, E> for Result<T, E>
 where
     E: ext::StdError + Send + Sync + 'static,
 {
+    // With "with-location", the context is tagged as by
+    // anyhow_context!(move context). Not map_err there: the closure would not
+    // inherit the caller's location.
+    #[cfg_attr(
+        all(feature = "with-location", not(anyhow_no_track_caller)),
+        allow(clippy::incompatible_msrv), // gated by anyhow_no_track_caller
+        track_caller
+    )]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
     {
+        #[cfg(all(feature = "with-location", not(anyhow_no_track_caller)))]
+        return match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(with_location(error, context)),
+        };
+        #[cfg(not(all(feature = "with-location", not(anyhow_no_track_caller))))]
         self.map_err(|error| error.ext_context(context))
     }
 
+    #[cfg_attr(
+        all(feature = "with-location", not(anyhow_no_track_caller)),
+        allow(clippy::incompatible_msrv), // gated by anyhow_no_track_caller
+        track_caller
+    )]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
         F: FnOnce() -> C,
     {
+        #[cfg(all(feature = "with-location", not(anyhow_no_track_caller)))]
+        return match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(with_location(error, context())),
+        };
+        #[cfg(not(all(feature = "with-location", not(anyhow_no_track_caller))))]
         self.map_err(|error| error.ext_context(context()))
     }
+
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.map_err(|error| error.ext_context(context).attach(Code(code)))
+    }
+
+    #[cfg(feature = "std")]
+    fn or_else_context<F>(self, fallback: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error>,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(primary) => fallback().map_err(|error| error.graft(primary.ext_into())),
+        }
+    }
+
+    fn context_if_enabled<C, F>(self, enabled: bool, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|error| {
+            if enabled {
+                error.ext_context(f())
+            } else {
+                error.ext_into()
+            }
+        })
+    }
+
+    fn with_context_args<'a, F>(self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> fmt::Arguments<'a>,
+    {
+        self.map_err(|error| error.ext_context(render(f())))
+    }
+
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn map_context<U, C, F>(self, f: F, context: C) -> Result<U, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Ok(ok) => Ok(f(ok)),
+            Err(error) => Err(error.ext_context(located(context))),
+        }
+    }
+
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn with_location_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        // Not map_err: the closure would not inherit the caller's location.
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.ext_context(located(f()))),
+        }
+    }
 }
 
 /// ```
@@ -94,6 +200,119 @@ impl<T> Context<T, Infallible> for Option<T> {
     {
         self.ok_or_else(|| Error::from_display(context(), backtrace!()))
     }
+
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.ok_or_else(|| Error::from_display(context, backtrace!()).attach(Code(code)))
+    }
+
+    #[cfg(feature = "std")]
+    fn or_else_context<F>(self, fallback: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error>,
+    {
+        match self {
+            Some(some) => Ok(some),
+            None => fallback(),
+        }
+    }
+
+    fn context_if_enabled<C, F>(self, _enabled: bool, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.with_context(f)
+    }
+
+    fn with_context_args<'a, F>(self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> fmt::Arguments<'a>,
+    {
+        self.ok_or_else(|| Error::from_display(render(f()), backtrace!()))
+    }
+
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn map_context<U, C, F>(self, f: F, context: C) -> Result<U, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Some(some) => Ok(f(some)),
+            None => Err(Error::from_display(located(context), backtrace!())),
+        }
+    }
+
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn with_location_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        match self {
+            Some(some) => Ok(some),
+            None => Err(Error::from_display(located(f()), backtrace!())),
+        }
+    }
+}
+
+// Keeps a string with nothing interpolated as it is, without formatting it
+// into a String.
+pub(crate) fn render(args: fmt::Arguments) -> Cow<'static, str> {
+    #[cfg(not(anyhow_no_fmt_arguments_as_str))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_fmt_arguments_as_str
+    {
+        if let Some(message) = args.as_str() {
+            return Cow::Borrowed(message);
+        }
+    }
+    Cow::Owned(crate::private::format!("{}", args))
+}
+
+// anyhow_context!(move context): the context is kept as a level of its own,
+// to be downcast, under a tagged level with its Display as the message, the
+// way anyhow_located!(error) keeps an error.
+#[cold]
+pub fn typed<C>(error: Error, context: C, site: &str, line: u32, column: u32) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    let message = crate::template::tag(site, line, column, ",", &context);
+    error.context(context).context(message)
+}
+
+// The context of Context::context with "with-location": kept as a level of its
+// own, to be downcast, under a level tagged with the caller's location, as
+// typed keeps it.
+#[cfg(all(feature = "with-location", not(anyhow_no_track_caller)))]
+#[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+#[track_caller]
+fn with_location<E, C>(error: E, context: C) -> Error
+where
+    E: ext::StdError,
+    C: Display + Send + Sync + 'static,
+{
+    let message = located(&context);
+    error.ext_context(context).context(message)
+}
+
+// Tags the context with the caller's location, as anyhow_context! does.
+#[cfg(not(anyhow_no_track_caller))]
+#[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+#[track_caller]
+fn located<C>(context: C) -> crate::alloc::String
+where
+    C: Display,
+{
+    let caller = core::panic::Location::caller();
+    crate::template::tag(caller.file(), caller.line(), caller.column(), ",", &context)
 }
 
 impl<C, E> Debug for ContextError<C, E>
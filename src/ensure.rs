@@ -0,0 +1,176 @@
+// Tagged dispatch for printing the operands of a failed `ensure!(a == b)`.
+//
+// The operands are shown only if they implement Debug, and a comparison of
+// operands without a Debug impl must keep compiling. As in kind.rs, this is
+// resolved through autoref: DebugOperand is implemented for Operand only when
+// the operand is Debug, while PlainOperand is implemented for every &Operand
+// and so is picked only when DebugOperand does not apply.
+//
+// The ensure! macro sets up the call in this form:
+//
+//     #[allow(unused_imports)]
+//     use $crate::private::ensure::{DebugOperand, PlainOperand};
+//     (&Operand(left)).anyhow_operand()
+
+use crate::alloc::Box;
+use core::fmt::{self, Debug, Display};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+#[cfg(feature = "std")]
+use std::cell::Cell;
+
+// The installed prefix, or null for the built-in "Condition failed: ". Like
+// the tag format it can be installed only once, and is leaked.
+static CONDITION_PREFIX: AtomicPtr<&'static str> = AtomicPtr::new(ptr::null_mut());
+
+/// Replace the `Condition failed: ` that starts the message of an `ensure!`
+/// without a message of its own, such as to report failed conditions in the
+/// language of the application's users or with a shorter prefix.
+///
+/// The condition follows the prefix in backticks as before, and the message
+/// is tagged with the location of the call as before:
+/// ``[file:line:column emsg(prefix`condition`)]``. The prefix is used by all
+/// of `ensure!`, `ensure_located!`, `ensure_ctx!` and, without a message,
+/// `ensure_eq!` and `ensure_ne!`. With the "compat" feature, `ensure!` keeps
+/// upstream's message.
+///
+/// As with [`set_tag_format`][crate::set_tag_format], the prefix can be set
+/// only once: a second call leaves the installed prefix in place and hands
+/// `prefix` back as the error. To use another prefix on one thread for a
+/// while, see `with_condition_prefix`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{ensure_located, Result};
+///
+/// fn check(depth: u32) -> Result<()> {
+///     ensure_located!(depth < 8);
+///     Ok(())
+/// }
+///
+/// anyhow::set_condition_prefix("Bedingung verletzt: ").unwrap();
+///
+/// let error = check(9).unwrap_err();
+/// assert_eq!(error.emsg(), Some("Bedingung verletzt: `depth < 8`"));
+/// ```
+pub fn set_condition_prefix(prefix: &'static str) -> Result<(), &'static str> {
+    let installed = Box::into_raw(Box::new(prefix));
+    match CONDITION_PREFIX.compare_exchange(
+        ptr::null_mut(),
+        installed,
+        Ordering::SeqCst,
+        Ordering::SeqCst,
+    ) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(*unsafe { Box::from_raw(installed) }),
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    // The prefix installed on this thread by with_condition_prefix, if any.
+    static SCOPED_PREFIX: Cell<Option<&'static str>> = Cell::new(None);
+}
+
+/// Start the messages of failed conditions checked on this thread with
+/// `prefix` for as long as `f` runs, in place of the prefix set by
+/// [`set_condition_prefix`] or the built-in `Condition failed: `.
+///
+/// The previous prefix of the thread is restored when `f` returns or
+/// panics, and scopes can be nested, as with
+/// [`with_tag_format`][crate::with_tag_format].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{ensure_located, Result};
+///
+/// fn check(depth: u32) -> Result<()> {
+///     ensure_located!(depth < 8);
+///     Ok(())
+/// }
+///
+/// let error = anyhow::with_condition_prefix("check: ", || check(9)).unwrap_err();
+/// assert_eq!(error.emsg(), Some("check: `depth < 8`"));
+///
+/// let error = check(9).unwrap_err();
+/// assert_eq!(error.emsg(), Some("Condition failed: `depth < 8`"));
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn with_condition_prefix<F, R>(prefix: &'static str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Restore(Option<&'static str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_PREFIX.with(|scoped| scoped.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SCOPED_PREFIX.with(|scoped| scoped.replace(Some(prefix))));
+    f()
+}
+
+// The prefix of the messages of failed conditions on this thread.
+fn condition_prefix() -> &'static str {
+    #[cfg(feature = "std")]
+    {
+        if let Ok(Some(prefix)) = SCOPED_PREFIX.try_with(Cell::get) {
+            return prefix;
+        }
+    }
+    let prefix = CONDITION_PREFIX.load(Ordering::SeqCst);
+    if prefix.is_null() {
+        "Condition failed: "
+    } else {
+        unsafe { *prefix }
+    }
+}
+
+pub struct Operand<'a, T: ?Sized>(pub &'a T);
+
+pub trait DebugOperand {
+    fn anyhow_operand(&self) -> Option<&dyn Debug>;
+}
+
+impl<T> DebugOperand for Operand<'_, T>
+where
+    T: ?Sized + Debug,
+{
+    #[inline]
+    fn anyhow_operand(&self) -> Option<&dyn Debug> {
+        Some(&self.0)
+    }
+}
+
+pub trait PlainOperand {
+    #[inline]
+    fn anyhow_operand(&self) -> Option<&dyn Debug> {
+        None
+    }
+}
+
+impl<T> PlainOperand for &Operand<'_, T> where T: ?Sized {}
+
+// The message of a failed condition, with the values of the operands of a
+// comparison when both could be shown.
+pub struct Failed<'a> {
+    pub condition: &'static str,
+    pub left: Option<&'a dyn Debug>,
+    pub right: Option<&'a dyn Debug>,
+}
+
+impl Display for Failed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}`{}`", condition_prefix(), self.condition)?;
+        if let (Some(left), Some(right)) = (self.left, self.right) {
+            write!(f, " (left: `{:?}`, right: `{:?}`)", left, right)?;
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,20 @@
-use crate::alloc::Box;
+use crate::alloc::{Box, String, ToString};
 use crate::backtrace::Backtrace;
-use crate::chain::Chain;
-#[cfg(any(feature = "std", anyhow_no_ptr_addr_of))]
-use crate::ptr::Mut;
-use crate::ptr::{Own, Ref};
+use crate::chain::{Chain, FrameCache};
+use crate::location::{split_tag, untag, Location};
+use crate::meta::{Header, Meta};
+use crate::ptr::{Mut, Own, Ref};
+use crate::template::render;
+use crate::version;
 use crate::{Error, StdError};
 use core::any::TypeId;
 use core::fmt::{self, Debug, Display};
-use core::mem::ManuallyDrop;
+use core::mem::{self, ManuallyDrop};
 #[cfg(not(anyhow_no_ptr_addr_of))]
 use core::ptr;
 use core::ptr::NonNull;
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", not(anyhow_no_core_error)))]
 use core::ops::{Deref, DerefMut};
 
 impl Error {
@@ -79,6 +81,46 @@ impl Error {
         Error::from_adhoc(message, backtrace!())
     }
 
+    /// Create an ad-hoc error whose message is tagged with a location, as
+    /// `anyhow!("...")` tags it.
+    ///
+    /// This is for libraries with error-construction helpers of their own.
+    /// The tag is rendered by the same code as that of the macros, including
+    /// any format installed with [`set_tag_format`][crate::set_tag_format],
+    /// so the output cannot drift from theirs. `file` is used as given,
+    /// without the module path the macros put in front of it under the
+    /// "module-path" feature, and the column is that of the call to
+    /// `new_tagged`, or omitted before Rust 1.46, which is the first to
+    /// support `#[track_caller]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_located, Error};
+    ///
+    /// let a = Error::new_tagged(file!(), line!() + 1, "disk full");
+    /// let b = anyhow_located!("disk full");
+    /// assert_eq!(a.location(), b.location());
+    /// assert_eq!(a.emsg(), b.emsg());
+    /// ```
+    #[cold]
+    #[cfg_attr(
+        not(anyhow_no_track_caller),
+        allow(clippy::incompatible_msrv),
+        track_caller
+    )]
+    pub fn new_tagged<M>(file: &str, line: u32, msg: M) -> Self
+    where
+        M: Display,
+    {
+        #[cfg(not(anyhow_no_track_caller))]
+        #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+        let column = Some(core::panic::Location::caller().column());
+        #[cfg(anyhow_no_track_caller)]
+        let column = None;
+        Error::msg(render(file, line, column, "", &msg))
+    }
+
     #[cfg(feature = "std")]
     #[cold]
     pub(crate) fn from_std<E>(error: E, backtrace: Option<Backtrace>) -> Self
@@ -95,12 +137,17 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<E>,
             object_drop_rest: object_drop_front::<E>,
+            object_layer: foreign_layer,
+            object_rewrite: foreign_rewrite,
+            object_split_off: object_split_off::<E>,
+            object_reroot: object_reroot::<E>,
+            object_graft: object_graft::<E>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: passing vtable that operates on the right type E.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, version::stamp()) }
     }
 
     #[cold]
@@ -120,13 +167,20 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
+            object_layer: message_layer,
+            object_rewrite: message_rewrite::<MessageError<M>>,
+            object_split_off: object_split_off::<MessageError<M>>,
+            #[cfg(feature = "std")]
+            object_reroot: object_reroot::<MessageError<M>>,
+            #[cfg(feature = "std")]
+            object_graft: object_graft::<M>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: MessageError is repr(transparent) so it is okay for the
         // vtable to allow casting the MessageError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, version::stamp()) }
     }
 
     #[cold]
@@ -146,13 +200,20 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
+            object_layer: message_layer,
+            object_rewrite: message_rewrite::<DisplayError<M>>,
+            object_split_off: object_split_off::<DisplayError<M>>,
+            #[cfg(feature = "std")]
+            object_reroot: object_reroot::<DisplayError<M>>,
+            #[cfg(feature = "std")]
+            object_graft: object_graft::<M>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: DisplayError is repr(transparent) so it is okay for the
         // vtable to allow casting the DisplayError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, version::stamp()) }
     }
 
     #[cfg(feature = "std")]
@@ -174,12 +235,17 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_downcast_mut::<C, E>,
             object_drop_rest: context_drop_rest::<C, E>,
+            object_layer: context_layer,
+            object_rewrite: context_rewrite::<C, E>,
+            object_split_off: context_split_off::<C, E>,
+            object_reroot: context_reroot::<C, E>,
+            object_graft: context_graft::<C, E>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, version::stamp()) }
     }
 
     #[cfg(feature = "std")]
@@ -200,13 +266,18 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<Box<dyn StdError + Send + Sync>>,
             object_drop_rest: object_drop_front::<Box<dyn StdError + Send + Sync>>,
+            object_layer: foreign_layer,
+            object_rewrite: foreign_rewrite,
+            object_split_off: object_split_off::<BoxedError>,
+            object_reroot: object_reroot::<BoxedError>,
+            object_graft: object_graft::<BoxedError>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: BoxedError is repr(transparent) so it is okay for the vtable
         // to allow casting to Box<dyn StdError + Send + Sync>.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, version::stamp()) }
     }
 
     // Takes backtrace as argument rather than capturing it here so that the
@@ -219,6 +290,7 @@ impl Error {
         error: E,
         vtable: &'static ErrorVTable,
         backtrace: Option<Backtrace>,
+        header: Header,
     ) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -226,6 +298,7 @@ impl Error {
         let inner: Box<ErrorImpl<E>> = Box::new(ErrorImpl {
             vtable,
             backtrace,
+            header,
             _object: error,
         });
         // Erase the concrete type of E from the compile-time type system. This
@@ -244,6 +317,10 @@ impl Error {
     /// [`Context`][crate::Context] extension trait may be more convenient than
     /// this function.
     ///
+    /// The context is stored as the value it was given, so a `&'static str`
+    /// or a `Cow::Borrowed` costs nothing beyond the one allocation every
+    /// level of the chain needs. There is no need to turn it into a `String`.
+    ///
     /// The primary reason to use `error.context(...)` instead of
     /// `result.context(...)` via the `Context` trait would be if the context
     /// needs to depend on some data held by the underlying error:
@@ -293,10 +370,11 @@ impl Error {
     /// }
     /// ```
     #[cold]
-    pub fn context<C>(self, context: C) -> Self
+    pub fn context<C>(mut self, context: C) -> Self
     where
         C: Display + Send + Sync + 'static,
     {
+        let header = unsafe { ErrorImpl::take_header(self.inner.by_mut()) };
         let error: ContextError<C, Error> = ContextError {
             context,
             error: self,
@@ -312,6 +390,13 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_chain_downcast_mut::<C>,
             object_drop_rest: context_chain_drop_rest::<C>,
+            object_layer: context_chain_layer::<C>,
+            object_rewrite: context_chain_rewrite::<C>,
+            object_split_off: context_chain_split_off::<C>,
+            #[cfg(feature = "std")]
+            object_reroot: context_chain_reroot::<C>,
+            #[cfg(feature = "std")]
+            object_graft: context_chain_graft::<C>,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: context_backtrace::<C>,
         };
@@ -319,8 +404,242 @@ impl Error {
         // As the cause is anyhow::Error, we already have a backtrace for it.
         let backtrace = None;
 
-        // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        // Safety: passing vtable that operates on the right type. Error-wide
+        // metadata always lives in the outermost level.
+        unsafe { Error::construct(error, vtable, backtrace, header) }
+    }
+
+    /// Replace the root cause of this error, keeping the context around it.
+    ///
+    /// Every level of context attached through anyhow stays in place, with its
+    /// original type for the purpose of downcasting. What gets replaced is the
+    /// innermost level that anyhow owns: the error that was converted into
+    /// `anyhow::Error` in the first place, or the ad-hoc message the chain was
+    /// started from. If that error has sources of its own, they are discarded
+    /// along with it because anyhow has no way to rewrite a foreign error's
+    /// chain. The result's root cause is therefore `new_root` or, if
+    /// `new_root` has a source, the last error in `new_root`'s own chain.
+    ///
+    /// The backtrace captured for the original error is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Error, Result};
+    /// use std::fmt::{self, Display};
+    /// use std::io;
+    ///
+    /// #[derive(Debug)]
+    /// struct UnknownUser;
+    ///
+    /// impl Display for UnknownUser {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         f.write_str("no such user")
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for UnknownUser {}
+    ///
+    /// fn load_profile() -> Result<()> {
+    ///     let io_error = io::Error::from(io::ErrorKind::NotFound);
+    ///     Err(Error::new(io_error).context("failed to load profile"))
+    /// }
+    ///
+    /// let error = load_profile().unwrap_err();
+    /// let error = match error.root_cause().downcast_ref::<io::Error>() {
+    ///     Some(io_error) if io_error.kind() == io::ErrorKind::NotFound => error.reroot(UnknownUser),
+    ///     _ => error,
+    /// };
+    ///
+    /// assert_eq!(format!("{:#}", error), "failed to load profile: no such user");
+    /// assert!(error.root_cause().is::<UnknownUser>());
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    pub fn reroot<E>(mut self, new_root: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let backtrace = backtrace_if_absent!(new_root);
+        let root = Error::from_std(new_root, backtrace);
+        unsafe {
+            let header = ErrorImpl::take_header(self.inner.by_mut());
+            let outer = ManuallyDrop::new(self);
+            let mut error = (vtable(outer.inner.ptr).object_reroot)(outer.inner, root);
+            ErrorImpl::set_header(error.inner.by_mut(), header);
+            error
+        }
+    }
+
+    // Puts `cause` underneath the innermost level of this chain that anyhow
+    // owns, so that the chain continues into the cause's. A foreign error at
+    // the bottom keeps its place but its own sources give way to the cause.
+    // The backtrace is the cause's; error-wide metadata is this error's.
+    #[cfg(feature = "std")]
+    pub(crate) fn graft(mut self, cause: Error) -> Self {
+        unsafe {
+            let header = ErrorImpl::take_header(self.inner.by_mut());
+            let outer = ManuallyDrop::new(self);
+            let mut error = (vtable(outer.inner.ptr).object_graft)(outer.inner, cause);
+            ErrorImpl::set_header(error.inner.by_mut(), header);
+            error
+        }
+    }
+
+    /// Replace the outermost message if it matches a predicate.
+    ///
+    /// `pred` is given the outermost message, and only if it returns true is
+    /// the message replaced by the one `new` returns. The causes underneath,
+    /// the backtrace and attachments are kept. A foreign error at the top of
+    /// the chain, one that was not created by anyhow, is never replaced; wrap
+    /// it with [`context`][Error::context] instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("password=hunter2 rejected");
+    /// let error = error.replace_message_if(
+    ///     |message| message.contains("password="),
+    ///     || "credentials rejected",
+    /// );
+    ///
+    /// assert_eq!(error.to_string(), "credentials rejected");
+    /// ```
+    pub fn replace_message_if<F, G, M>(self, pred: F, new: G) -> Self
+    where
+        F: FnOnce(&str) -> bool,
+        G: FnOnce() -> M,
+        M: Display,
+    {
+        let mut replace = Some((pred, new));
+        self.rewrite(&mut |message| {
+            let (pred, new) = replace.take()?;
+            if pred(message) {
+                Some(new().to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Transform the message of every level of the chain that anyhow created.
+    ///
+    /// For messages tagged with a location, `f` is given only the text inside
+    /// `emsg(...)` and the tag is put back around what it returns, so
+    /// locations survive the transform. The result has the same structure as
+    /// the original: the same number of levels, the same backtrace and
+    /// attachments, and the same root cause for downcasting.
+    ///
+    /// Foreign errors, including the one at the top of a chain that has no
+    /// context on it, are not anyhow's to rewrite. They and everything under
+    /// them are left as they are, and `f` is never called for them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow_located;
+    ///
+    /// let error = anyhow_located!("password hunter2 rejected")
+    ///     .context("login as admin:hunter2 failed");
+    ///
+    /// let redacted = error.map_chain(|message| message.replace("hunter2", "***"));
+    ///
+    /// assert_eq!(redacted.to_string(), "login as admin:*** failed");
+    /// assert!(redacted.root_cause().to_string().ends_with(" emsg(password *** rejected)]"));
+    /// ```
+    pub fn map_chain<F>(self, f: F) -> Self
+    where
+        F: Fn(&str) -> String,
+    {
+        self.rewrite(&mut |message| match split_tag(message) {
+            Some((_, range)) => Some(
+                [
+                    &message[..range.start],
+                    &f(&message[range.clone()]),
+                    &message[range.end..],
+                ]
+                .concat(),
+            ),
+            None => Some(f(message)),
+        })
+    }
+
+    /// Separate the outermost message from the rest of the chain.
+    ///
+    /// The message is returned with its location tag removed, as
+    /// [`plain`][Error::plain] would show it. The rest is the error this one
+    /// wrapped, which carries on the backtrace and attachments, or `None` if
+    /// the outermost level is an ad-hoc message with nothing underneath. A
+    /// foreign error at the top of the chain also gives `None`, because its
+    /// sources belong to it and cannot be taken out as an `anyhow::Error`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("write failed")
+    ///     .context("could not save your document");
+    ///
+    /// let (headline, details) = error.split_off_context();
+    /// assert_eq!(headline, "could not save your document");
+    /// assert_eq!(format!("{:#}", details.unwrap()), "write failed: disk full");
+    /// ```
+    pub fn split_off_context(mut self) -> (String, Option<Error>) {
+        let message = self.to_string();
+        let message = match untag(&message) {
+            Some((_, message)) => message,
+            None => message,
+        };
+        unsafe {
+            let header = ErrorImpl::take_header(self.inner.by_mut());
+            let outer = ManuallyDrop::new(self);
+            let mut rest = (vtable(outer.inner.ptr).object_split_off)(outer.inner);
+            if let Some(rest) = &mut rest {
+                ErrorImpl::set_header(rest.inner.by_mut(), header);
+            }
+            (message, rest)
+        }
+    }
+
+    /// Wrap this error in `Err`, as a `Result` of any success type.
+    ///
+    /// This reads better than `Err(error)` at the end of a chain of method
+    /// calls, and needs no type annotation where the `Result` is inferred.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Result};
+    ///
+    /// fn port(s: &str) -> Result<u16> {
+    ///     s.parse().or_else(|_| anyhow!("invalid port {:?}", s).into_err())
+    /// }
+    ///
+    /// assert!(port("eighty").is_err());
+    /// ```
+    pub fn into_err<T>(self) -> Result<T, Self> {
+        Err(self)
+    }
+
+    // Rebuilds the levels of the chain that anyhow created, outermost first,
+    // passing each level's message to `f`. Where `f` returns a replacement,
+    // that level's message or context becomes the returned String; the other
+    // levels, foreign errors, the backtrace and error-wide metadata all carry
+    // over untouched.
+    pub(crate) fn rewrite(mut self, f: &mut Rewrite) -> Self {
+        unsafe {
+            let header = ErrorImpl::take_header(self.inner.by_mut());
+            let outer = ManuallyDrop::new(self);
+            let mut error = (vtable(outer.inner.ptr).object_rewrite)(outer.inner, f);
+            ErrorImpl::set_header(error.inner.by_mut(), header);
+            error
+        }
     }
 
     /// Get the backtrace for this Error.
@@ -359,6 +678,19 @@ impl Error {
         unsafe { ErrorImpl::backtrace(self.inner.by_ref()) }
     }
 
+    /// Whether the backtrace of this error was captured at this level, as
+    /// opposed to being the one of an error further down the chain.
+    ///
+    /// A backtrace is captured once, where the chain originates: by the
+    /// constructors and macros that create an error from scratch, or by the
+    /// first conversion of a foreign error that does not provide a backtrace
+    /// of its own. Context added after that refers to the backtrace already
+    /// captured, so `error.context(...)` never has its own backtrace. This is
+    /// always false when backtraces are not supported by the build.
+    pub fn has_own_backtrace(&self) -> bool {
+        unsafe { self.inner.by_ref().deref() }.backtrace.is_some()
+    }
+
     /// An iterator of the chain of source errors contained by this Error.
     ///
     /// This iterator will visit every error in the cause chain of this error
@@ -383,7 +715,7 @@ impl Error {
     #[cfg(feature = "std")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     #[cold]
-    pub fn chain(&self) -> Chain {
+    pub fn chain(&self) -> Chain<'_> {
         unsafe { ErrorImpl::chain(self.inner.by_ref()) }
     }
 
@@ -398,6 +730,56 @@ impl Error {
         self.chain().last().unwrap()
     }
 
+    /// The number of errors in the chain, the same as
+    /// `error.chain().count()`: one for this error and one for each of its
+    /// sources.
+    ///
+    /// The chain is walked to count its links, without allocating.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// fn report(error: &Error) {
+    ///     if error.chain_len() > 1 {
+    ///         eprintln!("{:?}", error);
+    ///     } else {
+    ///         eprintln!("{}", error);
+    ///     }
+    /// }
+    /// ```
+    pub fn chain_len(&self) -> usize {
+        unsafe { ErrorImpl::chain(self.inner.by_ref()) }.len()
+    }
+
+    /// The number of errors of type `E` in the chain.
+    ///
+    /// Each error visited by [`chain()`][Error::chain] that is an `E` counts
+    /// once, so this is nonzero exactly when some cause is an `E`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// fn report(error: &Error) {
+    ///     match error.count_of::<io::Error>() {
+    ///         0 => eprintln!("{:#}", error),
+    ///         n => eprintln!("{:#} ({} I/O errors occurred)", error, n),
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn count_of<E>(&self) -> usize
+    where
+        E: StdError + 'static,
+    {
+        self.chain().filter(|cause| cause.is::<E>()).count()
+    }
+
     /// Returns true if `E` is the type held by this error object.
     ///
     /// For errors with context, this method returns true if `E` matches the
@@ -450,6 +832,25 @@ impl Error {
 
     /// Downcast this error object by reference.
     ///
+    /// An error with a location also downcasts to [`Location`], when nothing
+    /// in its chain is one: `downcast_ref::<Location>()` returns the location
+    /// of [`location()`][Error::location], kept with the parsed frames of the
+    /// error so that it can be borrowed. Downcasting to the type of a value
+    /// in the chain works the same as ever. Only this method and
+    /// [`is`][Error::is] see the location; the other downcasts do not.
+    ///
+    /// ```
+    /// use anyhow::{anyhow_located, Location};
+    /// use std::io;
+    ///
+    /// let error = anyhow_located!(io::Error::new(io::ErrorKind::Other, "disk full"));
+    /// let line = line!() - 1;
+    ///
+    /// let location = error.downcast_ref::<Location>().unwrap();
+    /// assert_eq!((location.file(), location.line()), (file!(), line));
+    /// assert!(error.downcast_ref::<io::Error>().is_some());
+    /// ```
+    ///
     /// # Example
     ///
     /// ```
@@ -492,9 +893,19 @@ impl Error {
         unsafe {
             // Use vtable to find NonNull<()> which points to a value of type E
             // somewhere inside the data structure.
-            let addr = (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?;
-            Some(addr.cast::<E>().deref())
+            if let Some(addr) =
+                (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)
+            {
+                return Some(addr.cast::<E>().deref());
+            }
+        }
+        // The location found in the tags stands in for a Location in the
+        // chain.
+        if target == TypeId::of::<Location>() {
+            let location = self.cached_location()?;
+            return Some(unsafe { &*(location as *const Location as *const E) });
         }
+        None
     }
 
     /// Downcast this error object by mutable reference.
@@ -514,7 +925,51 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             let addr = (vtable(self.inner.ptr).object_downcast_mut)(self.inner.by_mut(), target)?;
 
-            Some(addr.cast::<E>().deref_mut())
+            // The caller may change the error, so the frames parsed from it
+            // go stale.
+            let addr = addr.cast::<E>().extend();
+            ErrorImpl::clear_frames(self.inner.by_mut());
+            Some(addr.deref_mut())
+        }
+    }
+
+    /// Handle the error one way if it can be downcast to `E` and another way
+    /// if not.
+    ///
+    /// `on_match` receives the downcast reference, exactly as from
+    /// [`downcast_ref`][Error::downcast_ref]. Otherwise `on_miss` receives the
+    /// error's `Display` representation, which is what code falling back to a
+    /// generic path usually wants to log.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// # use std::io;
+    /// #
+    /// # let error = anyhow!("...");
+    /// #
+    /// let status = error.downcast_ref_or(
+    ///     |io_error: &io::Error| match io_error.kind() {
+    ///         io::ErrorKind::NotFound => 404,
+    ///         _ => 500,
+    ///     },
+    ///     |message| {
+    ///         eprintln!("unexpected error: {}", message);
+    ///         500
+    ///     },
+    /// );
+    /// # assert_eq!(status, 500);
+    /// ```
+    pub fn downcast_ref_or<E, R, F, G>(&self, on_match: F, on_miss: G) -> R
+    where
+        E: Display + Debug + Send + Sync + 'static,
+        F: FnOnce(&E) -> R,
+        G: FnOnce(&str) -> R,
+    {
+        match self.downcast_ref::<E>() {
+            Some(error) => on_match(error),
+            None => on_miss(&self.to_string()),
         }
     }
 }
@@ -525,15 +980,18 @@ impl<E> From<E> for Error
 where
     E: StdError + Send + Sync + 'static,
 {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     #[cold]
     fn from(error: E) -> Self {
         let backtrace = backtrace_if_absent!(error);
-        Error::from_std(error, backtrace)
+        let error = Error::from_std(error, backtrace);
+        #[cfg(not(anyhow_no_track_caller))]
+        let error = error.propagated();
+        error
     }
 }
 
-#[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[cfg(any(feature = "std", not(anyhow_no_core_error)))]
 impl Deref for Error {
     type Target = dyn StdError + Send + Sync + 'static;
 
@@ -542,8 +1000,7 @@ impl Deref for Error {
     }
 }
 
-#[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[cfg(any(feature = "std", not(anyhow_no_core_error)))]
 impl DerefMut for Error {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { ErrorImpl::error_mut(self.inner.by_mut()) }
@@ -581,6 +1038,13 @@ struct ErrorVTable {
     #[cfg(anyhow_no_ptr_addr_of)]
     object_downcast_mut: unsafe fn(Mut<ErrorImpl>, TypeId) -> Option<Mut<()>>,
     object_drop_rest: unsafe fn(Own<ErrorImpl>, TypeId),
+    object_layer: unsafe fn(Ref<ErrorImpl>) -> Layer,
+    object_rewrite: unsafe fn(Own<ErrorImpl>, &mut Rewrite) -> Error,
+    object_split_off: unsafe fn(Own<ErrorImpl>) -> Option<Error>,
+    #[cfg(feature = "std")]
+    object_reroot: unsafe fn(Own<ErrorImpl>, Error) -> Error,
+    #[cfg(feature = "std")]
+    object_graft: unsafe fn(Own<ErrorImpl>, Error) -> Error,
     #[cfg(all(not(backtrace), feature = "backtrace"))]
     object_backtrace: unsafe fn(Ref<ErrorImpl>) -> Option<&Backtrace>,
 }
@@ -683,8 +1147,75 @@ where
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<E>.
+#[cfg(feature = "std")]
+unsafe fn object_reroot<E>(e: Own<ErrorImpl>, mut root: Error) -> Error {
+    // The whole of E is replaced by the new root. Carry over the backtrace
+    // from where E was created.
+    let unerased = e.cast::<ErrorImpl<E>>().boxed();
+    if let Some(backtrace) = unerased.backtrace {
+        ErrorImpl::clear_frames(root.inner.by_mut());
+        root.inner.by_mut().deref_mut().backtrace = Some(backtrace);
+    }
+    root
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+#[cfg(feature = "std")]
+unsafe fn object_graft<E>(e: Own<ErrorImpl>, cause: Error) -> Error
+where
+    E: Display + Send + Sync + 'static,
+{
+    // The whole of E goes on top of the cause as context.
+    let unerased = e.cast::<ErrorImpl<E>>().boxed();
+    let ErrorImpl { _object: error, .. } = *unerased;
+    cause.context(error)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn message_rewrite<E>(e: Own<ErrorImpl>, f: &mut Rewrite) -> Error {
+    match f(&ErrorImpl::error(e.by_ref()).to_string()) {
+        Some(message) => {
+            let unerased = e.cast::<ErrorImpl<E>>().boxed();
+            let ErrorImpl { backtrace, .. } = *unerased;
+            Error::from_adhoc(message, backtrace)
+        }
+        None => Error { inner: e },
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_split_off<E>(e: Own<ErrorImpl>) -> Option<Error> {
+    // Nothing underneath that anyhow owns.
+    drop(e.cast::<ErrorImpl<E>>().boxed());
+    None
+}
+
+#[cfg(feature = "std")]
+fn foreign_rewrite(e: Own<ErrorImpl>, f: &mut Rewrite) -> Error {
+    let _ = f;
+    Error { inner: e }
+}
+
+fn message_layer(e: Ref<'_, ErrorImpl>) -> Layer<'_> {
+    let _ = e;
+    Layer::Message
+}
+
+#[cfg(feature = "std")]
+fn context_layer(e: Ref<'_, ErrorImpl>) -> Layer<'_> {
+    let _ = e;
+    Layer::Context
+}
+
+#[cfg(feature = "std")]
+fn foreign_layer(e: Ref<'_, ErrorImpl>) -> Layer<'_> {
+    let _ = e;
+    Layer::Foreign
+}
+
 #[cfg(all(not(backtrace), feature = "backtrace"))]
-fn no_backtrace(e: Ref<ErrorImpl>) -> Option<&Backtrace> {
+fn no_backtrace(e: Ref<'_, ErrorImpl>) -> Option<&Backtrace> {
     let _ = e;
     None
 }
@@ -747,6 +1278,80 @@ where
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_reroot<C, E>(e: Own<ErrorImpl>, mut root: Error) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    // Keep the context but replace E. The new root is an anyhow::Error, so
+    // the context goes on top of it as a context chain.
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().boxed();
+    let ErrorImpl {
+        backtrace,
+        _object: ContextError { context, .. },
+        ..
+    } = *unerased;
+    if let Some(backtrace) = backtrace {
+        ErrorImpl::clear_frames(root.inner.by_mut());
+        root.inner.by_mut().deref_mut().backtrace = Some(backtrace);
+    }
+    root.context(context)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_graft<C, E>(e: Own<ErrorImpl>, cause: Error) -> Error
+where
+    C: Display + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    // E and then the context go on top of the cause, E taking the place of
+    // any sources of its own.
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().boxed();
+    let ErrorImpl {
+        _object: ContextError { context, error },
+        ..
+    } = *unerased;
+    cause.context(error).context(context)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_rewrite<C, E>(e: Own<ErrorImpl>, f: &mut Rewrite) -> Error
+where
+    C: Display + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>();
+    match f(&unerased.by_ref().deref()._object.context.to_string()) {
+        Some(message) => {
+            let ErrorImpl {
+                backtrace,
+                _object: ContextError { error, .. },
+                ..
+            } = *unerased.boxed();
+            Error::from_context(message, error, backtrace)
+        }
+        None => Error { inner: e },
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_split_off<C, E>(e: Own<ErrorImpl>) -> Option<Error>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().boxed();
+    let ErrorImpl {
+        backtrace,
+        _object: ContextError { error, .. },
+        ..
+    } = *unerased;
+    Some(Error::from_std(error, backtrace))
+}
+
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
 unsafe fn context_chain_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
@@ -804,10 +1409,80 @@ where
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[cfg(feature = "std")]
+unsafe fn context_chain_reroot<C>(e: Own<ErrorImpl>, root: Error) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    // Recurse down the context chain, then put this level's context back on.
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+    let ErrorImpl {
+        _object: ContextError { context, error },
+        ..
+    } = *unerased;
+    let inner = ManuallyDrop::new(error).inner;
+    (vtable(inner.ptr).object_reroot)(inner, root).context(context)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[cfg(feature = "std")]
+unsafe fn context_chain_graft<C>(e: Own<ErrorImpl>, cause: Error) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    // Recurse down the context chain, then put this level's context back on.
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+    let ErrorImpl {
+        _object: ContextError { context, error },
+        ..
+    } = *unerased;
+    let inner = ManuallyDrop::new(error).inner;
+    (vtable(inner.ptr).object_graft)(inner, cause).context(context)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_rewrite<C>(e: Own<ErrorImpl>, f: &mut Rewrite) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+    let ErrorImpl {
+        _object: ContextError { context, error },
+        ..
+    } = *unerased;
+    let replacement = f(&context.to_string());
+    let inner = ManuallyDrop::new(error).inner;
+    let error = (vtable(inner.ptr).object_rewrite)(inner, f);
+    match replacement {
+        Some(message) => error.context(message),
+        None => error.context(context),
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_split_off<C>(e: Own<ErrorImpl>) -> Option<Error> {
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+    let ErrorImpl {
+        _object: ContextError { error, .. },
+        ..
+    } = *unerased;
+    Some(error)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_layer<C>(e: Ref<'_, ErrorImpl>) -> Layer<'_>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    Layer::ContextChain(unerased._object.error.inner.by_ref())
+}
+
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
 #[cfg(all(not(backtrace), feature = "backtrace"))]
 #[allow(clippy::unnecessary_wraps)]
-unsafe fn context_backtrace<C>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
+unsafe fn context_backtrace<C>(e: Ref<'_, ErrorImpl>) -> Option<&Backtrace>
 where
     C: 'static,
 {
@@ -823,6 +1498,7 @@ where
 pub(crate) struct ErrorImpl<E = ()> {
     vtable: &'static ErrorVTable,
     backtrace: Option<Backtrace>,
+    header: Header,
     // NOTE: Don't use directly. Use only through vtable. Erased type may have
     // different alignment.
     _object: E,
@@ -835,6 +1511,26 @@ unsafe fn vtable(p: NonNull<ErrorImpl>) -> &'static ErrorVTable {
     *(p.as_ptr() as *const &'static ErrorVTable)
 }
 
+// Callback for Error::rewrite. Receives the message of one level and returns
+// its replacement, if any.
+pub(crate) type Rewrite<'a> = dyn FnMut(&str) -> Option<String> + 'a;
+
+// What the front of an ErrorImpl's chain consists of. Used to tell apart the
+// levels that anyhow produced itself from foreign errors underneath them.
+#[derive(Copy, Clone)]
+pub(crate) enum Layer<'a> {
+    // An ad-hoc message or display value. Nothing underneath.
+    Message,
+    // Context on top of a foreign error, which makes up the rest of the chain.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    Context,
+    // Context on top of another anyhow::Error.
+    ContextChain(Ref<'a, ErrorImpl>),
+    // A foreign error. It and all of its sources are foreign.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    Foreign,
+}
+
 // repr C to ensure that ContextError<C, E> has the same layout as
 // ContextError<ManuallyDrop<C>, E> and ContextError<C, ManuallyDrop<E>>.
 #[repr(C)]
@@ -844,7 +1540,7 @@ pub(crate) struct ContextError<C, E> {
 }
 
 impl<E> ErrorImpl<E> {
-    fn erase(&self) -> Ref<ErrorImpl> {
+    fn erase(&self) -> Ref<'_, ErrorImpl> {
         // Erase the concrete type of E but preserve the vtable in self.vtable
         // for manipulating the resulting thin pointer. This is analogous to an
         // unsize coercion.
@@ -853,16 +1549,20 @@ impl<E> ErrorImpl<E> {
 }
 
 impl ErrorImpl {
-    pub(crate) unsafe fn error(this: Ref<Self>) -> &(dyn StdError + Send + Sync + 'static) {
+    pub(crate) unsafe fn error(this: Ref<'_, Self>) -> &(dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
         // original type E.
         (vtable(this.ptr).object_ref)(this).deref()
     }
 
-    #[cfg(feature = "std")]
-    pub(crate) unsafe fn error_mut(this: Mut<Self>) -> &mut (dyn StdError + Send + Sync + 'static) {
+    #[cfg(any(feature = "std", not(anyhow_no_core_error)))]
+    pub(crate) unsafe fn error_mut(
+        this: Mut<'_, Self>,
+    ) -> &mut (dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
-        // original type E.
+        // original type E. The caller may change the error, so the frames
+        // parsed from it go stale.
+        Self::clear_frames(this);
 
         #[cfg(not(anyhow_no_ptr_addr_of))]
         return (vtable(this.ptr).object_ref)(this.by_ref())
@@ -874,7 +1574,7 @@ impl ErrorImpl {
     }
 
     #[cfg(any(backtrace, feature = "backtrace"))]
-    pub(crate) unsafe fn backtrace(this: Ref<Self>) -> &Backtrace {
+    pub(crate) unsafe fn backtrace(this: Ref<'_, Self>) -> &Backtrace {
         // This unwrap can only panic if the underlying error's backtrace method
         // is nondeterministic, which would only happen in maliciously
         // constructed code.
@@ -890,10 +1590,92 @@ impl ErrorImpl {
             .expect("backtrace capture failed")
     }
 
+    pub(crate) unsafe fn header(this: Ref<'_, Self>) -> &Header {
+        &this.deref().header
+    }
+
+    pub(crate) unsafe fn header_mut(this: Mut<'_, Self>) -> &mut Header {
+        &mut this.deref_mut().header
+    }
+
+    pub(crate) unsafe fn meta(this: Ref<'_, Self>) -> Option<&Meta> {
+        this.deref().header.meta.get()
+    }
+
+    pub(crate) unsafe fn meta_mut(this: Mut<'_, Self>) -> &mut Meta {
+        let meta = this.deref_mut().header.meta.get_or_default();
+        meta.frames.clear();
+        meta
+    }
+
+    unsafe fn take_header(this: Mut<Self>) -> Header {
+        let mut header = mem::replace(&mut this.deref_mut().header, Header::default());
+        if let Some(meta) = header.meta.get_mut() {
+            meta.frames.clear();
+        }
+        header
+    }
+
+    unsafe fn set_header(this: Mut<Self>, header: Header) {
+        this.deref_mut().header = header;
+        Self::clear_frames(this);
+    }
+
+    // The frames cache lives with the error-wide metadata, so only the
+    // outermost level, the one that is parsed, has one.
+    pub(crate) unsafe fn frames(this: Ref<'_, Self>) -> &FrameCache {
+        &this.deref().header.meta.get_or_init().frames
+    }
+
+    // Drops the cached frames, for when anything they were parsed from may
+    // change.
+    pub(crate) unsafe fn clear_frames(this: Mut<Self>) {
+        if let Some(meta) = this.deref_mut().header.meta.get_mut() {
+            meta.frames.clear();
+        }
+    }
+
     #[cold]
     pub(crate) unsafe fn chain(this: Ref<Self>) -> Chain {
         Chain::new(Self::error(this))
     }
+
+    // Number of levels at the front of the chain that were created by anyhow
+    // (ad-hoc messages and context), as opposed to foreign errors.
+    pub(crate) unsafe fn native_len(mut this: Ref<Self>) -> usize {
+        let mut len = 0;
+        loop {
+            match (vtable(this.ptr).object_layer)(this) {
+                Layer::Message | Layer::Context => return len + 1,
+                Layer::ContextChain(next) => {
+                    len += 1;
+                    this = next;
+                }
+                Layer::Foreign => return len,
+            }
+        }
+    }
+
+    // Whether this is a single ad-hoc message or display value, with nothing
+    // underneath it to downcast to.
+    pub(crate) unsafe fn is_message(this: Ref<Self>) -> bool {
+        match (vtable(this.ptr).object_layer)(this) {
+            Layer::Message => true,
+            _ => false,
+        }
+    }
+
+    // Whether the innermost level created by anyhow is an ad-hoc message, as
+    // opposed to context on top of a foreign error or no such level at all.
+    pub(crate) unsafe fn has_message_root(mut this: Ref<Self>) -> bool {
+        loop {
+            match (vtable(this.ptr).object_layer)(this) {
+                Layer::Message => return true,
+                Layer::ContextChain(next) => this = next,
+                Layer::Context | Layer::Foreign => return false,
+            }
+        }
+    }
 }
 
 impl<E> StdError for ErrorImpl<E>
@@ -952,14 +1734,14 @@ impl From<Error> for Box<dyn StdError + 'static> {
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", not(anyhow_no_core_error)))]
 impl AsRef<dyn StdError + Send + Sync> for Error {
     fn as_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
         &**self
     }
 }
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", not(anyhow_no_core_error)))]
 impl AsRef<dyn StdError> for Error {
     fn as_ref(&self) -> &(dyn StdError + 'static) {
         &**self
@@ -0,0 +1,113 @@
+use crate::alloc::String;
+use crate::error::ErrorImpl;
+use crate::location::untag;
+use crate::Error;
+
+impl Error {
+    /// A hash of the shape of this error, for grouping and deduplicating
+    /// errors that differ only in the values interpolated into their
+    /// messages.
+    ///
+    /// Every level of the chain contributes its message with the location tag
+    /// removed, together with the file and line of the location if it has
+    /// one. Before hashing, values that are likely to change from one
+    /// occurrence to the next are masked out of each message:
+    ///
+    /// - The message is split into words, a word being a run of ASCII
+    ///   letters, digits and `-`.
+    /// - A word made up only of hexadecimal digits and `-`, with at least one
+    ///   decimal digit among them, is replaced by `#`. This covers decimal
+    ///   numbers like `42` and `-1`, UUIDs, and hex ids and hashes, while
+    ///   words such as `v2`, `utf8` or `dead` are kept.
+    ///
+    /// The hash is computed with FNV-1a, so it is the same across runs,
+    /// platforms and versions of Rust, and can be stored or compared between
+    /// processes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let fetch = |id: u32| anyhow!("no user {}", id).context("loading profile");
+    ///
+    /// assert_eq!(fetch(7).fingerprint(), fetch(3500).fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint_impl(true)
+    }
+
+    /// Like [`fingerprint()`][Error::fingerprint], but leaving line numbers
+    /// out, so that the fingerprint stays the same when code moves within a
+    /// file.
+    pub fn fingerprint_without_lines(&self) -> u64 {
+        self.fingerprint_impl(false)
+    }
+
+    fn fingerprint_impl(&self, lines: bool) -> u64 {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut hash = Fnv::new();
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            // `{:-}` leaves an error wrapped by anyhow_error! unformatted, so
+            // the fingerprint does not change with the installed settings.
+            let tagged = crate::private::format!("{:-}", level);
+            // The location of Error::msg_at is kept next to the innermost
+            // message rather than in it.
+            let untagged = match untag(&tagged) {
+                Some(untagged) if n < native => Some(untagged),
+                _ if n < native && level.source().is_none() => self
+                    .origin()
+                    .map(|location| (location.clone(), tagged.clone())),
+                _ => None,
+            };
+            match untagged {
+                Some((location, message)) => {
+                    hash.write(location.file().as_bytes());
+                    if lines {
+                        hash.write(&location.line().to_le_bytes());
+                    }
+                    hash.write(&[0]);
+                    hash.write(mask(&message).as_bytes());
+                }
+                _ => hash.write(mask(&tagged).as_bytes()),
+            }
+            hash.write(&[0]);
+        }
+        hash.0
+    }
+}
+
+// 64-bit FNV-1a.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+// Replaces id-like words with `#`, as described on Error::fingerprint.
+pub(crate) fn mask(message: &str) -> String {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    let mut masked = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find(is_word) {
+        masked.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        let id = word.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+            && word.chars().any(|c| c.is_ascii_digit());
+        masked.push_str(if id { "#" } else { word });
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
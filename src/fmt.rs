@@ -1,15 +1,418 @@
+use crate::alloc::String;
+use crate::attach::{Printable, Url};
 use crate::chain::Chain;
 use crate::error::ErrorImpl;
+use crate::location::{line_range, split_tag, untag};
 use crate::ptr::Ref;
-use core::fmt::{self, Debug, Write};
+use crate::separator::line_separator;
+use crate::{Error, StdError};
+use core::fmt::{self, Debug, Display, Write};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+use core::mem;
+
+// The `{:-?}` representation of an error, with its line separators.
+struct Raw<'a>(Ref<'a, ErrorImpl>);
+
+impl Debug for Raw<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug(self.0, f) }
+    }
+}
+
+// The `{:-?}` representation of an error as the default settings render it,
+// whatever ErrorFormatter and foreign formatter are installed, for the code
+// that parses it back into frames. Its `{}` is likewise unformatted.
+pub(crate) struct Unformatted<'a>(pub &'a Error);
+
+impl Debug for Unformatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug_with(self.0.inner.by_ref(), f, true) }
+    }
+}
+
+impl Display for Unformatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::display_with(self.0.inner.by_ref(), f, true) }
+    }
+}
+
+// Writes through to `out` with every line separator turned into a newline, as
+// `{:?}` shows them. A separator can be split across writes, so the part of
+// one seen at the end of a write is held back, as the length `pending` of the
+// start of the separator, until the next write shows whether it completes.
+struct Unescape<W> {
+    out: W,
+    separator: &'static str,
+    pending: usize,
+}
+
+impl<W: Write> Unescape<W> {
+    fn new(out: W, separator: &'static str) -> Self {
+        Unescape {
+            out,
+            separator,
+            pending: 0,
+        }
+    }
+
+    // Writes the start of a separator still held back at the end.
+    fn finish(mut self) -> fmt::Result {
+        let pending = &self.separator[..self.pending];
+        self.out.write_str(pending)
+    }
+}
+
+impl<W: Write> Write for Unescape<W> {
+    fn write_str(&mut self, mut s: &str) -> fmt::Result {
+        let separator = self.separator;
+        while !s.is_empty() {
+            if self.pending > 0 {
+                let rest = &separator[self.pending..];
+                if s.starts_with(rest) {
+                    self.out.write_char('\n')?;
+                    self.pending = 0;
+                    s = &s[rest.len()..];
+                } else if rest.starts_with(s) {
+                    self.pending += s.len();
+                    return Ok(());
+                } else {
+                    // Not a separator after all. Its first character goes
+                    // out as is, and the rest is written again, since a
+                    // separator could start inside it.
+                    let held = &separator[..self.pending];
+                    self.pending = 0;
+                    let first = held.chars().next().map_or(0, char::len_utf8);
+                    self.out.write_str(&held[..first])?;
+                    self.write_str(&held[first..])?;
+                }
+                continue;
+            }
+            if let Some(i) = s.find(separator) {
+                self.out.write_str(&s[..i])?;
+                self.out.write_char('\n')?;
+                s = &s[i + separator.len()..];
+                continue;
+            }
+            // Hold back the longest end of `s` that starts a separator.
+            let held = (1..separator.len().min(s.len() + 1))
+                .rev()
+                .find(|&n| separator.is_char_boundary(n) && s.ends_with(&separator[..n]))
+                .unwrap_or(0);
+            self.out.write_str(&s[..s.len() - held])?;
+            self.pending = held;
+            return Ok(());
+        }
+        Ok(())
+    }
+}
+
+// Installed ErrorFormatter settings. Widths are stored plus one, so that zero
+// can mean no limit.
+static MAX_MESSAGE_WIDTH: AtomicUsize = AtomicUsize::new(0);
+static OMIT_BACKTRACE: AtomicBool = AtomicBool::new(false);
+static OMIT_LINE_NUMBERS: AtomicBool = AtomicBool::new(false);
+
+/// Settings for how errors are rendered by `{}`, `{:#}` and `{:?}`.
+///
+/// A formatter only takes effect once [installed][ErrorFormatter::install],
+/// after which it applies process-wide to every `anyhow::Error`, including
+/// [`Error::report`][crate::Error::report].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::ErrorFormatter;
+///
+/// ErrorFormatter::new().max_message_width(Some(80)).install();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorFormatter {
+    max_message_width: Option<usize>,
+    include_backtrace: bool,
+    omit_line_numbers: bool,
+}
+
+impl Default for ErrorFormatter {
+    fn default() -> Self {
+        ErrorFormatter {
+            max_message_width: None,
+            include_backtrace: true,
+            omit_line_numbers: false,
+        }
+    }
+}
+
+impl ErrorFormatter {
+    /// Default settings, the same as those in effect before any formatter is
+    /// installed.
+    pub fn new() -> Self {
+        ErrorFormatter::default()
+    }
+
+    /// The settings currently installed.
+    pub fn current() -> Self {
+        ErrorFormatter {
+            max_message_width: match MAX_MESSAGE_WIDTH.load(Ordering::SeqCst) {
+                0 => None,
+                width => Some(width - 1),
+            },
+            include_backtrace: !OMIT_BACKTRACE.load(Ordering::SeqCst),
+            omit_line_numbers: OMIT_LINE_NUMBERS.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Limit the message of each level of the chain to `width` characters.
+    ///
+    /// Longer messages are cut short and end in `…`, which counts toward the
+    /// width. For messages tagged with a location only the text inside
+    /// `emsg(...)` is shortened, so the location is always shown in full. A
+    /// width of 0 leaves no room for the `…` either: each message is dropped
+    /// and only its location tag, if it has one, is shown.
+    pub fn max_message_width(mut self, width: Option<usize>) -> Self {
+        self.max_message_width = width;
+        self
+    }
+
+    /// Whether `{:?}` ends with the captured backtrace, if there is one.
+    ///
+    /// This is on by default. Turning it off only changes how errors are
+    /// rendered, for when backtraces are logged separately;
+    /// [`Error::backtrace`][crate::Error::backtrace] still returns the
+    /// backtrace.
+    pub fn include_backtrace(mut self, include: bool) -> Self {
+        self.include_backtrace = include;
+        self
+    }
+
+    /// Whether locations in messages are shown as just the file, without the
+    /// line number.
+    ///
+    /// This is off by default. Turning it on renders `[src/x.rs:12 emsg(...)]`
+    /// as `[src/x.rs emsg(...)]`, so that output compared against snapshots
+    /// does not change whenever code moves. Methods that return locations,
+    /// such as [`Error::locations`][crate::Error::locations], are unaffected.
+    pub fn omit_line_numbers(mut self, omit: bool) -> Self {
+        self.omit_line_numbers = omit;
+        self
+    }
+
+    /// Make these the settings used for all errors from now on, replacing any
+    /// previously installed formatter.
+    pub fn install(&self) {
+        let width = self
+            .max_message_width
+            .map_or(0, |width| width.saturating_add(1));
+        MAX_MESSAGE_WIDTH.store(width, Ordering::SeqCst);
+        OMIT_BACKTRACE.store(!self.include_backtrace, Ordering::SeqCst);
+        OMIT_LINE_NUMBERS.store(self.omit_line_numbers, Ordering::SeqCst);
+    }
+}
+
+// Cuts the message short to `width` characters, leaving any location tag
+// intact.
+fn truncate(message: &str, width: usize) -> Option<String> {
+    let range = match split_tag(message) {
+        Some((_, range)) => range,
+        None => 0..message.len(),
+    };
+    let inner = &message[range.clone()];
+    if inner.chars().count() <= width {
+        return None;
+    }
+    let cut = inner
+        .char_indices()
+        .nth(width.saturating_sub(1))
+        .map_or(inner.len(), |(i, _)| i);
+    let mut truncated = String::with_capacity(message.len());
+    truncated.push_str(&message[..range.start]);
+    if width > 0 {
+        truncated.push_str(&inner[..cut]);
+        truncated.push('…');
+    }
+    truncated.push_str(&message[range.end..]);
+    Some(truncated)
+}
+
+#[cfg(feature = "std")]
+type ForeignFormatter = fn(&(dyn StdError + 'static)) -> Option<String>;
+
+#[cfg(feature = "std")]
+static FOREIGN_FORMATTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Customize how foreign errors are rendered when anyhow prints a chain.
+///
+/// A foreign error is any level of the chain that anyhow did not create
+/// itself, such as an `io::Error` passed to [`Error::new`][crate::Error::new]
+/// or propagated with `?`, together with all of its sources. Messages and
+/// context attached through anyhow are never passed to the formatter.
+///
+/// The formatter is consulted for each foreign level by the `{}`, `{:#}` and
+/// `{:?}` representations of [`Error`][crate::Error]. Returning `None` falls
+/// back to the error's own `Display` impl. Installing a formatter replaces any
+/// previously installed one.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error as StdError;
+/// use std::io;
+///
+/// fn truncate_io(error: &(dyn StdError + 'static)) -> Option<String> {
+///     let io_error = error.downcast_ref::<io::Error>()?;
+///     let message = io_error.to_string();
+///     if message.len() <= 16 {
+///         return None;
+///     }
+///     Some(format!("{}...", &message[..16]))
+/// }
+///
+/// anyhow::set_foreign_formatter(truncate_io);
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_foreign_formatter(formatter: ForeignFormatter) {
+    FOREIGN_FORMATTER.store(formatter as usize, Ordering::SeqCst);
+}
+
+#[cfg(feature = "std")]
+fn foreign_formatter() -> Option<ForeignFormatter> {
+    match FOREIGN_FORMATTER.load(Ordering::SeqCst) {
+        0 => None,
+        // Safety: the only nonzero values ever stored are fn pointers of this
+        // exact type, by set_foreign_formatter.
+        formatter => Some(unsafe { mem::transmute::<usize, ForeignFormatter>(formatter) }),
+    }
+}
+
+// One level of the chain, rendered through the foreign formatter if anyhow did
+// not create it. An unformatted level is its own `{:-}`, untouched by the
+// installed settings.
+struct Level<'a> {
+    error: &'a (dyn StdError + 'static),
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    foreign: bool,
+    hide_location: bool,
+    unformatted: bool,
+}
+
+impl Display for Level<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unformatted {
+            return if self.hide_location && !self.foreign {
+                let message = crate::private::format!("{:-}", self.error);
+                match untag(&message) {
+                    Some((_, untagged)) => f.write_str(&untagged),
+                    None => f.write_str(&message),
+                }
+            } else {
+                write!(f, "{:-}", self.error)
+            };
+        }
+        let width = MAX_MESSAGE_WIDTH.load(Ordering::SeqCst);
+        let omit_line_numbers = OMIT_LINE_NUMBERS.load(Ordering::SeqCst);
+        let hide_location = self.hide_location && !self.foreign;
+        if width == 0 && !omit_line_numbers && !hide_location {
+            return self.fmt_full(f);
+        }
+        let mut message = String::new();
+        if f.alternate() {
+            write!(message, "{:#}", Full(self))?;
+        } else {
+            write!(message, "{}", Full(self))?;
+        }
+        if hide_location {
+            if let Some((_, untagged)) = untag(&message) {
+                message = untagged;
+            }
+        } else if omit_line_numbers {
+            if let Some(range) = line_range(&message) {
+                message.replace_range(range, "");
+            }
+        }
+        if width > 0 {
+            if let Some(truncated) = truncate(&message, width - 1) {
+                message = truncated;
+            }
+        }
+        f.write_str(&message)
+    }
+}
+
+// The untruncated rendering of a level.
+struct Full<'a>(&'a Level<'a>);
+
+impl Display for Full<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_full(f)
+    }
+}
+
+impl Level<'_> {
+    fn fmt_full(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            if self.foreign {
+                if let Some(formatter) = foreign_formatter() {
+                    if let Some(rendered) = formatter(self.error) {
+                        return f.write_str(&rendered);
+                    }
+                }
+            }
+        }
+
+        Display::fmt(self.error, f)
+    }
+}
 
 impl ErrorImpl {
+    // Index of the first level of the chain whose location is hidden, if any.
+    unsafe fn hidden_from(this: Ref<Self>) -> Option<usize> {
+        match Self::meta(this) {
+            Some(meta) if meta.hidden_locations > 0 => {
+                let len = Self::chain(this).count();
+                Some(len.saturating_sub(meta.hidden_locations))
+            }
+            _ => None,
+        }
+    }
+
+    unsafe fn url(this: Ref<Self>) -> Option<&'static str> {
+        let meta = Self::meta(this)?;
+        meta.attachments
+            .iter()
+            .rev()
+            .find_map(|attachment| attachment.downcast_ref::<Url>())
+            .map(|url| url.0)
+    }
+
     pub(crate) unsafe fn display(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", Self::error(this))?;
+        Self::display_with(this, f, false)
+    }
 
-        if f.alternate() {
-            for cause in Self::chain(this).skip(1) {
-                write!(f, ": {}", cause)?;
+    unsafe fn display_with(
+        this: Ref<Self>,
+        f: &mut fmt::Formatter,
+        unformatted: bool,
+    ) -> fmt::Result {
+        let native = Self::native_len(this);
+        let hidden_from = Self::hidden_from(this);
+
+        for (n, error) in Self::chain(this).enumerate() {
+            if n > 0 && !f.alternate() {
+                break;
+            }
+            let level = Level {
+                error,
+                foreign: n >= native,
+                hide_location: hidden_from.map_or(false, |from| n >= from),
+                unformatted,
+            };
+            if n == 0 {
+                write!(f, "{}", level)?;
+            } else {
+                write!(f, ": {}", level)?;
             }
         }
 
@@ -17,13 +420,45 @@ impl ErrorImpl {
     }
 
     pub(crate) unsafe fn debug(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        Self::debug_with(this, f, false)
+    }
+
+    unsafe fn debug_with(
+        this: Ref<Self>,
+        f: &mut fmt::Formatter,
+        unformatted: bool,
+    ) -> fmt::Result {
         let error = Self::error(this);
 
         if f.alternate() {
             return Debug::fmt(error, f);
         }
 
-        write!(f, "{}", error)?;
+        // `{:?}` puts the frames joined by the line separator on lines of
+        // their own. `{:-?}` keeps the separators, for the code that splits
+        // the representation back into frames.
+        let separator = line_separator();
+        if !unformatted && !f.sign_minus() && !separator.is_empty() && separator != "\n" {
+            let mut out = Unescape::new(&mut *f, separator);
+            write!(out, "{:-?}", Raw(this))?;
+            return out.finish();
+        }
+
+        let native = Self::native_len(this);
+        let hidden_from = Self::hidden_from(this);
+        let origin = Self::meta(this).and_then(|meta| meta.origin.as_ref());
+        let level = Level {
+            error,
+            foreign: native == 0,
+            hide_location: hidden_from == Some(0),
+            unformatted,
+        };
+        match origin {
+            Some(origin) if error.source().is_none() => {
+                write!(f, "[{} emsg({})]", origin, level)?;
+            }
+            _ => write!(f, "{}", level)?,
+        }
 
         if let Some(cause) = error.source() {
             write!(f, "\n\nCaused by:")?;
@@ -35,12 +470,65 @@ impl ErrorImpl {
                     number: if multiple { Some(n) } else { None },
                     started: false,
                 };
-                write!(indented, "{}", error)?;
+                let level = Level {
+                    error,
+                    foreign: n + 1 >= native,
+                    hide_location: hidden_from.map_or(false, |from| n + 1 >= from),
+                    unformatted,
+                };
+                match origin {
+                    Some(origin) if error.source().is_none() => {
+                        write!(indented, "[{} emsg({})]", origin, level)?;
+                    }
+                    _ => write!(indented, "{}", level)?,
+                }
+            }
+        }
+
+        if let Some(meta) = Self::meta(this) {
+            let mut printable = meta
+                .attachments
+                .iter()
+                .filter_map(|attachment| attachment.downcast_ref::<Printable>())
+                .peekable();
+            if printable.peek().is_some() {
+                write!(f, "\n\nAttachments:")?;
+                for attachment in printable {
+                    writeln!(f)?;
+                    let mut indented = Indented {
+                        inner: f,
+                        number: None,
+                        started: false,
+                    };
+                    write!(indented, "{}", attachment)?;
+                }
+            }
+
+            if !meta.suppressed.is_empty() {
+                write!(f, "\n\nSuppressed:")?;
+                let multiple = meta.suppressed.len() > 1;
+                for (n, error) in meta.suppressed.iter().enumerate() {
+                    writeln!(f)?;
+                    let mut indented = Indented {
+                        inner: f,
+                        number: if multiple { Some(n) } else { None },
+                        started: false,
+                    };
+                    if unformatted {
+                        write!(indented, "{:#}", Unformatted(error))?;
+                    } else {
+                        write!(indented, "{:#}", error)?;
+                    }
+                }
             }
         }
 
+        if let Some(url) = Self::url(this) {
+            write!(f, "\n\nSee: {}", url)?;
+        }
+
         #[cfg(any(backtrace, feature = "backtrace"))]
-        {
+        if unformatted || !OMIT_BACKTRACE.load(Ordering::SeqCst) {
             use crate::backtrace::BacktraceStatus;
 
             let backtrace = Self::backtrace(this);
@@ -64,10 +552,10 @@ impl ErrorImpl {
     }
 }
 
-struct Indented<'a, D> {
-    inner: &'a mut D,
-    number: Option<usize>,
-    started: bool,
+pub(crate) struct Indented<'a, D> {
+    pub inner: &'a mut D,
+    pub number: Option<usize>,
+    pub started: bool,
 }
 
 impl<T> Write for Indented<'_, T>
@@ -0,0 +1,50 @@
+use crate::alloc::{String, ToString, Vec};
+use crate::error::ErrorImpl;
+use crate::location::untag;
+use crate::Error;
+
+impl Error {
+    /// Render the chain as one line of the folded stack format read by
+    /// flamegraph tools, for visualizing which paths errors commonly take.
+    ///
+    /// The line lists the messages of the chain root cause first, separated
+    /// by `;` and followed by a count of ` 1`, as in
+    /// `disk full;write failed;saving report 1`. Location tags are removed
+    /// from the messages. Since `;` separates frames and a line break ends
+    /// the record, semicolons in messages are replaced by `,` and line breaks
+    /// by spaces. Lines from many errors can be concatenated and fed to a tool
+    /// such as `flamegraph.pl`, which sums the counts of identical paths.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("write failed")
+    ///     .context("saving report");
+    ///
+    /// assert_eq!(error.to_folded(), "disk full;write failed;saving report 1");
+    /// ```
+    pub fn to_folded(&self) -> String {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut frames = Vec::new();
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            let tagged = level.to_string();
+            let message = match untag(&tagged) {
+                Some((_, message)) if n < native => message,
+                _ => tagged,
+            };
+            frames.push(
+                message
+                    .replace(';', ",")
+                    .replace(|c| c == '\r' || c == '\n', " "),
+            );
+        }
+        frames.reverse();
+        let mut folded = frames.join(";");
+        folded.push_str(" 1");
+        folded
+    }
+}
@@ -0,0 +1,77 @@
+use crate::alloc::{String, ToString};
+use crate::error::ErrorImpl;
+use crate::location::{untag, Location};
+use crate::{Error, StdError};
+
+/// One level of an error's chain, classified by how it came to be there.
+///
+/// Returned by [`Error::frames`]. Levels that anyhow created itself carry
+/// their message with the location tag split off; everything else is handed
+/// out as the foreign error it is.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug)]
+pub enum Frame<'a> {
+    /// The ad-hoc message an error chain started from, such as one created
+    /// by `anyhow!` or [`Error::msg`].
+    Adhoc {
+        message: String,
+        location: Option<Location>,
+    },
+    /// Context added on top of another error.
+    Context {
+        message: String,
+        location: Option<Location>,
+    },
+    /// An error that did not originate from anyhow, or one of its sources.
+    Foreign { error: &'a (dyn StdError + 'static) },
+}
+
+impl Error {
+    /// An iterator over the chain of this error, telling apart the levels
+    /// anyhow created from foreign errors.
+    ///
+    /// This yields exactly one frame per error visited by
+    /// [`chain()`][Error::chain], in the same order. Messages of
+    /// [`Adhoc`][Frame::Adhoc] and [`Context`][Frame::Context] frames have the
+    /// location tag removed, and the location is given separately.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Context, Frame};
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("failed to write report")
+    ///     .unwrap_err();
+    ///
+    /// for frame in error.frames() {
+    ///     match frame {
+    ///         Frame::Adhoc { message, .. } => eprintln!("error: {}", message),
+    ///         Frame::Context { message, .. } => eprintln!("while: {}", message),
+    ///         Frame::Foreign { error } => eprintln!("cause: {}", error),
+    ///     }
+    /// }
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'_>> {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let message_root = unsafe { ErrorImpl::has_message_root(self.inner.by_ref()) };
+        self.chain().enumerate().map(move |(n, level)| {
+            if n >= native {
+                return Frame::Foreign { error: level };
+            }
+            let tagged = level.to_string();
+            let (location, message) = match untag(&tagged) {
+                Some((location, message)) => (Some(location), message),
+                None if level.source().is_none() => (self.origin().cloned(), tagged),
+                None => (None, tagged),
+            };
+            if message_root && n + 1 == native {
+                Frame::Adhoc { message, location }
+            } else {
+                Frame::Context { message, location }
+            }
+        })
+    }
+}
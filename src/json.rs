@@ -0,0 +1,140 @@
+use crate::alloc::{String, ToString};
+use crate::error::ErrorImpl;
+use crate::location::{untag, Location};
+use crate::Error;
+use core::fmt::Write;
+
+impl Error {
+    /// Render this error as a small single-line JSON object, for telemetry
+    /// pipelines that record errors in high volume.
+    ///
+    /// The object has these keys, in this order:
+    ///
+    /// - `"msg"`: the whole chain joined by `": "` as in `{:#}`, with the
+    ///   location tags removed.
+    /// - `"loc"`: the location where the error originated, as `"file:line"`,
+    ///   taken from the innermost level of the chain that recorded one.
+    ///   Omitted if no level did.
+    /// - `"depth"`: the number of levels in the chain.
+    /// - `"ver"`: the [build version][Error::build_version], if one was set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("write failed");
+    ///
+    /// assert_eq!(
+    ///     error.to_compact_json(),
+    ///     r#"{"msg":"write failed: disk full","depth":2}"#,
+    /// );
+    /// ```
+    pub fn to_compact_json(&self) -> String {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut message = String::new();
+        let mut origin = None::<Location>;
+        let mut depth = 0;
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            if n > 0 {
+                message.push_str(": ");
+            }
+            let tagged = level.to_string();
+            match untag(&tagged) {
+                Some((location, untagged)) if n < native => {
+                    message.push_str(&untagged);
+                    origin = Some(location);
+                }
+                _ => message.push_str(&tagged),
+            }
+            if level.source().is_none() && self.origin().is_some() {
+                origin = self.origin().cloned();
+            }
+            depth += 1;
+        }
+
+        let mut json = String::from("{\"msg\":");
+        push_str(&mut json, &message);
+        if let Some(location) = origin {
+            json.push_str(",\"loc\":");
+            let loc = crate::private::format!("{}:{}", location.file(), location.line());
+            push_str(&mut json, &loc);
+        }
+        let _ = write!(json, ",\"depth\":{}", depth);
+        if let Some(version) = self.build_version() {
+            json.push_str(",\"ver\":");
+            push_str(&mut json, version);
+        }
+        json.push('}');
+        json
+    }
+
+    /// Render the frames of this error as a JSON array, for structured
+    /// logging backends.
+    ///
+    /// There is one object per frame of
+    /// [`parsed_frames()`][Error::parsed_frames], outermost first, with the
+    /// keys `"file"`, `"line"` and `"msg"`. Frames without a location tag,
+    /// such as the Debug representation of a foreign error at the bottom of
+    /// the chain, have a `null` file and line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, Error};
+    ///
+    /// let error = Err::<(), _>(Error::msg("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    /// let line = line!() - 2;
+    ///
+    /// assert!(error.to_json().starts_with(&format!(
+    ///     r#"[{{"file":"{}","line":{},"msg":"writing log"}},{{"file":null,"line":null,"msg":"disk full"#,
+    ///     file!(),
+    ///     line,
+    /// )));
+    /// ```
+    #[cfg(feature = "serde")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+        for (n, frame) in self.parsed_frames().enumerate() {
+            if n > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"file\":");
+            if frame.file.is_empty() {
+                json.push_str("null,\"line\":null");
+            } else {
+                push_str(&mut json, &frame.file);
+                let _ = write!(json, ",\"line\":{}", frame.line);
+            }
+            json.push_str(",\"msg\":");
+            push_str(&mut json, &frame.msg);
+            json.push('}');
+        }
+        json.push(']');
+        json
+    }
+}
+
+// Appends `s` as a JSON string literal.
+fn push_str(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
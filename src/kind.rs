@@ -44,6 +44,10 @@
 //     let error = $msg;
 //     (&error).anyhow_kind().new(error)
 
+use crate::alloc::ToString;
+use crate::error::ErrorImpl;
+use crate::location::{nest, split_tag, tag_display, wrap_debug};
+use crate::template::tag;
 use crate::Error;
 use core::fmt::{Debug, Display};
 
@@ -69,6 +73,78 @@ impl Adhoc {
     {
         Error::from_adhoc(message, backtrace!())
     }
+
+    // anyhow_located!(message): the message is formatted into its tag.
+    #[cold]
+    pub fn located<M>(self, message: M, site: &str, line: u32, column: u32) -> Error
+    where
+        M: Display,
+    {
+        Error::msg(tag_display(site, line, column, "", &message))
+    }
+}
+
+// anyhow_error! dispatches the same way on the error it wraps: an
+// anyhow::Error is kept under the new tag, and the Debug of anything else is
+// rendered into the message.
+//
+//     |e| {
+//         use $crate::private::kind::*;
+//         (&e).wrap_kind().wrap(target, site, line, column, msg, e)
+//     }
+pub struct Rendered;
+
+pub trait RenderedKind: Sized {
+    #[inline]
+    fn wrap_kind(&self) -> Rendered {
+        Rendered
+    }
+}
+
+impl<T> RenderedKind for &T where T: ?Sized + Debug {}
+
+impl Rendered {
+    #[cold]
+    pub fn wrap<E>(
+        self,
+        target: &str,
+        site: &str,
+        line: u32,
+        column: u32,
+        msg: &dyn Display,
+        error: E,
+    ) -> Error
+    where
+        E: Debug,
+    {
+        wrap_debug(target, site, line, column, msg, &error)
+    }
+}
+
+pub struct Nested;
+
+pub trait NestedKind: Sized {
+    #[inline]
+    fn wrap_kind(&self) -> Nested {
+        Nested
+    }
+}
+
+impl NestedKind for Error {}
+
+impl Nested {
+    #[cold]
+    pub fn wrap(
+        self,
+        target: &str,
+        site: &str,
+        line: u32,
+        column: u32,
+        msg: &dyn Display,
+        error: Error,
+    ) -> Error {
+        nest(target, site, line, column, msg, error)
+    }
 }
 
 pub struct Trait;
@@ -90,6 +166,16 @@ impl Trait {
     {
         error.into()
     }
+
+    // Calls From directly rather than Into, which would leave a frame of
+    // core::convert above the caller's in the backtrace captured by From.
+    #[cold]
+    pub fn located<E>(self, error: E, site: &str, line: u32, column: u32) -> Error
+    where
+        Error: From<E>,
+    {
+        located(Error::from(error), site, line, column)
+    }
 }
 
 #[cfg(feature = "std")]
@@ -113,4 +199,35 @@ impl Boxed {
         let backtrace = backtrace_if_absent!(error);
         Error::from_boxed(error, backtrace)
     }
+
+    #[cold]
+    pub fn located(
+        self,
+        error: Box<dyn StdError + Send + Sync>,
+        site: &str,
+        line: u32,
+        column: u32,
+    ) -> Error {
+        located(self.new(error), site, line, column)
+    }
+}
+
+// anyhow_located!(error): the error is kept, to be downcast and to keep its
+// source chain, and the tag is added as context on top of it. The message of
+// an error that is already tagged, such as one made by anyhow_located!, is
+// put after the new tag as tag_display describes rather than inside it. A
+// lone tagged message, with nothing underneath to downcast to, has no use
+// for another level whose message repeats its own, and is flattened into the
+// new one.
+fn located(error: Error, site: &str, line: u32, column: u32) -> Error {
+    let message = error.to_string();
+    if split_tag(&message).is_none() {
+        return error.context(tag(site, line, column, "", &message));
+    }
+    let tagged = tag_display(site, line, column, "", &message);
+    if unsafe { ErrorImpl::is_message(error.inner.by_ref()) } {
+        Error::msg(tagged)
+    } else {
+        error.context(tagged)
+    }
 }
@@ -0,0 +1,114 @@
+use crate::alloc::String;
+use crate::separator::{escape_from, unescape};
+use crate::template::{builtin, tag};
+use crate::Error;
+use core::fmt::{Display, Write};
+
+impl Error {
+    /// The value recorded for `key` by [`anyhow_kv!`][crate::anyhow_kv] in
+    /// one of the location tags of this error, outermost first.
+    ///
+    /// The values are read out of the `{:?}` representation, so a key-value
+    /// pair is found at any depth of the chain, including through
+    /// `anyhow_error!`, which keeps only the text of the error it wraps.
+    /// Returns `None` if no tag has the key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_kv};
+    ///
+    /// let id = "req-7";
+    /// let error = Err::<(), _>(anyhow_kv!("upstream timed out", request_id = id))
+    ///     .map_err(anyhow_error!("rendering page"))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error.kv("request_id"), Some("req-7"));
+    /// assert_eq!(error.kv("retry"), None);
+    /// ```
+    pub fn kv(&self, key: &str) -> Option<&str> {
+        self.parsed()
+            .kv
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// The message of anyhow_kv!: `msg` tagged as by anyhow_located!, with the
+// key-value pairs after it in the tag as `[site:line:col emsg(msg)
+// kv(key=value,...)]`. A custom tag format has no place for the pairs, so
+// they are left out.
+#[doc(hidden)]
+pub fn tag_kv(
+    site: &str,
+    line: u32,
+    column: u32,
+    punct: &str,
+    msg: &dyn Display,
+    kv: &[(&str, &dyn Display)],
+) -> String {
+    let mut tagged = tag(site, line, column, punct, msg);
+    if kv.is_empty() || !builtin() {
+        return tagged;
+    }
+    tagged.truncate(tagged.len() - ")]".len());
+    tagged.push_str(") kv(");
+    for (n, (key, value)) in kv.iter().enumerate() {
+        if n > 0 {
+            tagged.push(',');
+        }
+        tagged.push_str(key);
+        tagged.push('=');
+        encode(&mut tagged, *value);
+    }
+    tagged.push_str(")]");
+    tagged
+}
+
+// Appends `value` with `%`, `)`, `,` and control characters written as `%XX`,
+// and the line separators escaped as in a message, so that it can be found
+// again between the `,` of the pairs and the `)]` closing the tag.
+fn encode(tagged: &mut String, value: &dyn Display) {
+    let mut plain = String::new();
+    let _ = write!(plain, "{}", value);
+    let start = tagged.len();
+    for c in plain.chars() {
+        match c {
+            c if c < ' ' || "%),".contains(c) => {
+                let _ = write!(tagged, "%{:02X}", c as u32);
+            }
+            c => tagged.push(c),
+        }
+    }
+    escape_from(tagged, start);
+}
+
+// The pairs of the `key=value,...` part of a tag, decoded.
+pub(crate) fn decode(kv: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    kv.split(',').filter_map(|pair| {
+        let eq = pair.find('=')?;
+        let value = unescape(&pair[eq + 1..]);
+        let mut decoded = String::with_capacity(value.len());
+        let mut rest = &*value;
+        while let Some(percent) = rest.find('%') {
+            decoded.push_str(&rest[..percent]);
+            rest = &rest[percent..];
+            match rest
+                .get(1..3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) if byte < 0x80 => {
+                    decoded.push(byte as char);
+                    rest = &rest[3..];
+                }
+                _ => {
+                    decoded.push('%');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        decoded.push_str(rest);
+        Some((String::from(&pair[..eq]), decoded))
+    })
+}
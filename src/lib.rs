@@ -194,6 +194,153 @@
 //!
 //! <br>
 //!
+//! # Upstream compatibility
+//!
+//! By default [`anyhow!`], [`bail!`] and [`ensure!`] tag the errors they make
+//! with the file and line of the call. Code written against upstream anyhow
+//! that relies on their plain behavior, such as downcasting the result of
+//! `anyhow!(error)`, can enable the "compat" feature to make these three
+//! macros behave exactly as upstream. The located versions stay available as
+//! [`anyhow_located!`], [`bail_located!`] and [`ensure_located!`].
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["compat"] }
+//! ```
+//!
+//! The same feature is also available as "no-location", for crates that
+//! enable it to keep messages free of location tags rather than for
+//! upstream compatibility as such: with either, `anyhow!("foo").to_string()`
+//! is `"foo"`. Only these three macros are affected. The macros that exist
+//! to add locations, [`anyhow_located!`], [`anyhow_error!`],
+//! [`anyhow_context!`] and the others, go on tagging their messages, so code
+//! can still opt in to locations where it wants them.
+//!
+//! To keep [`anyhow!`] located while making some errors plain, use
+//! [`anyhow_basic!`] (also available as `format_err_basic!`), which is
+//! upstream's `anyhow!` under a name of its own whatever the features.
+//! [`format_err!`] is the same as [`anyhow_basic!`] too, as it is upstream's
+//! other name for its `anyhow!`.
+//!
+//! <br>
+//!
+//! # Module paths in locations
+//!
+//! In large workspaces a file name such as `src/main.rs` can be ambiguous.
+//! With the "module-path" feature enabled, the tagging macros put the
+//! `module_path!()` of the call in front of the file, as in
+//! `[my_crate::db src/db.rs:42 emsg(...)]`. Locations read back out of
+//! messages, such as by [`Error::location`], leave the module path out of
+//! [`Location::file`].
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["module-path"] }
+//! ```
+//!
+//! `file!()` is an absolute path for some builds, such as those of crates
+//! outside the workspace being built, which leaks the layout of the build
+//! machine into the messages. With the "relative-paths" feature enabled, the
+//! tagging macros remove the manifest directory of the crate they are called
+//! from, or the directory in the `ANYHOW_STRIP_PREFIX` environment variable
+//! if that is set when the crate is compiled, from the front of the file, so
+//! that tags read `[src/db.rs:42 emsg(...)]`. Files that are already
+//! relative are kept as they are.
+//!
+//! <br>
+//!
+//! # Locations from `.context()`
+//!
+//! [`Context::context`] and [`Context::with_context`] on a `Result` attach
+//! their context as it is. With the "with-location" feature enabled, they
+//! tag it with the location of the call the way [`anyhow_context!`] does, as
+//! in `[src/db.rs:42:10, emsg(...)]`, so that chains built with plain `?` and
+//! `.context(...)` report their locations just like those built with the
+//! macros. As with `anyhow_context!(move context)`, the context is kept as
+//! it was passed in, so it can still be downcast to its type, and the tag
+//! is a level of its own on top of it. This needs Rust 1.46 or newer, for
+//! `#[track_caller]`.
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["with-location"] }
+//! ```
+//!
+//! <br>
+//!
+//! # Locations kept out of messages
+//!
+//! A tag puts the location into the message itself, where it is shown to
+//! whoever reads the message. With the "structured-location" feature
+//! enabled, [`anyhow!`], [`bail!`] and [`ensure!`] given a message store the
+//! location of the call next to it, as [`Error::msg_at`] does, so that
+//! `Display` and `{:#}` show the message alone. [`Error::location`],
+//! [`Error::locations`], [`Error::frames`], [`parse_frames`] and the JSON
+//! output still report the location, and `{:?}` renders it as a tag for
+//! developers. Given an error value, the macros tag it as ever, and so do
+//! the `_located` macros.
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["structured-location"] }
+//! ```
+//!
+//! <br>
+//!
+//! # Requiring messages
+//!
+//! Called without arguments, [`anyhow_error!`], [`anyhow_context!`] and
+//! [`anyhow_wrap!`] make a tag with an empty message, and [`anyhow!`] one
+//! with the module path in place of a message, which usually means someone
+//! forgot to write one. With the "deny-empty-messages" feature enabled, these
+//! forms are compile errors instead.
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["deny-empty-messages"] }
+//! ```
+//!
+//! <br>
+//!
+//! # Logging
+//!
+//! With the "log-integration" feature enabled, each error made by
+//! [`anyhow_error!`] is also logged through the [`log`] crate, at the error
+//! level. The record's target is the module path of the `anyhow_error!`
+//! call, its message is the message given to the macro, and the file and
+//! line of the call are attached as the `file` and `line` key-values.
+//! Without the feature nothing is logged and the `log` crate is not a
+//! dependency.
+//!
+//! [`log`]: https://docs.rs/log
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["log-integration"] }
+//! ```
+//!
+//! <br>
+//!
+//! # Tracing
+//!
+//! With the "tracing-integration" feature enabled, the location tags put on
+//! messages by [`anyhow!`], [`anyhow_error!`] and the other tagging macros
+//! record the [`tracing`] span the thread is in when the error is made, as
+//! ` span(target::name)` after the location:
+//! `[file:line:column span(my_app::load_config) emsg(...)]`. Tags made
+//! outside of any span are unchanged, and so are the parsers, which skip the
+//! span. Without the feature no span is looked up and the `tracing` crate is
+//! not a dependency.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+//!
+//! ```toml
+//! [dependencies]
+//! anyhow = { version = "1.0", features = ["tracing-integration"] }
+//! ```
+//!
+//! <br>
+//!
 //! # No-std support
 //!
 //! In no_std mode, the same API is almost all available and works the same way.
@@ -209,6 +356,10 @@
 //! `std::error::Error` trait which is only available through std, no_std mode
 //! will require an explicit `.map_err(Error::msg)` when working with a
 //! non-Anyhow error type inside a function that returns Anyhow's error type.
+//!
+//! On Rust 1.81 and newer, which have `core::error::Error`, an `Error` in
+//! no_std mode still derefs to `dyn core::error::Error + Send + Sync` and
+//! converts into a `Box` of it, for crates built on that trait.
 
 #![doc(html_root_url = "https://docs.rs/anyhow/1.0.45")]
 #![cfg_attr(backtrace, feature(backtrace))]
@@ -235,42 +386,141 @@ mod alloc {
     #[cfg(not(feature = "std"))]
     pub extern crate alloc;
 
+    #[cfg(not(feature = "std"))]
+    pub use alloc::borrow::Cow;
     #[cfg(not(feature = "std"))]
     pub use alloc::boxed::Box;
+    #[cfg(not(feature = "std"))]
+    pub use alloc::string::{String, ToString};
+    #[cfg(not(feature = "std"))]
+    pub use alloc::vec::Vec;
 
+    #[cfg(feature = "std")]
+    pub use std::borrow::Cow;
     #[cfg(feature = "std")]
     pub use std::boxed::Box;
+    #[cfg(feature = "std")]
+    pub use std::string::{String, ToString};
+    #[cfg(feature = "std")]
+    pub use std::vec::Vec;
 }
 
+mod attach;
 #[macro_use]
 mod backtrace;
+#[cfg(feature = "std")]
+mod breadcrumbs;
+mod builder;
+#[cfg(all(feature = "std", not(anyhow_no_track_caller)))]
+#[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+mod catch;
 mod chain;
 mod context;
+mod ensure;
 mod error;
+mod fingerprint;
 mod fmt;
+mod folded;
+#[cfg(feature = "std")]
+mod frame;
+mod json;
 mod kind;
+mod kv;
+mod location;
 mod macros;
+#[cfg(feature = "std")]
+mod markdown;
+mod meta;
+mod metric;
+#[cfg(feature = "std")]
+mod must_use;
+mod parens;
+pub mod parse;
+mod parts;
+#[cfg(feature = "prost")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "prost")))]
+pub mod proto;
 mod ptr;
+#[cfg(feature = "std")]
+mod redact;
+mod report;
+#[cfg(not(anyhow_no_track_caller))]
+#[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+mod scope;
+mod separator;
+mod severity;
+#[cfg(feature = "std")]
+mod shared;
+mod step;
+mod template;
+#[cfg(feature = "testing")]
+mod testing;
+mod version;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod wire;
 mod wrapper;
 
 use crate::error::ErrorImpl;
 use crate::ptr::Own;
 use core::fmt::Display;
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(not(feature = "std"), anyhow_no_core_error))]
 use core::fmt::Debug;
 
 #[cfg(feature = "std")]
 use std::error::Error as StdError;
 
-#[cfg(not(feature = "std"))]
+// Without std, errors are those of core::error where the toolchain has it,
+// so that anyhow::Error converts from and derefs to the same trait objects
+// as other crates built on it.
+#[cfg(all(not(feature = "std"), not(anyhow_no_core_error)))]
+use core::error::Error as StdError;
+
+#[cfg(all(not(feature = "std"), anyhow_no_core_error))]
 trait StdError: Debug + Display {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         None
     }
 }
 
-pub use anyhow as format_err;
+pub use anyhow_basic as format_err_basic;
+
+pub use crate::attach::{set_max_attachments, set_max_suppressed};
+pub use crate::builder::ErrorBuilder;
+pub use crate::ensure::set_condition_prefix;
+pub use crate::fmt::ErrorFormatter;
+pub use crate::location::{context_at, extract_all, map_err_at, tagged, Location, LocationRef};
+pub use crate::metric::Counter;
+pub use crate::parse::parse_frames;
+pub use crate::parts::{FromError, ReportParts, ToReport};
+pub use crate::report::Report;
+#[cfg(not(anyhow_no_track_caller))]
+pub use crate::scope::{scoped, Scoped};
+pub use crate::separator::{line_separator, set_line_separator};
+pub use crate::severity::Severity;
+pub use crate::template::{set_tag_format, Template};
+pub use crate::version::set_build_version;
+
+#[cfg(feature = "std")]
+pub use crate::breadcrumbs::Breadcrumbs;
+#[cfg(all(feature = "std", not(anyhow_no_track_caller)))]
+pub use crate::catch::catch;
+#[cfg(feature = "std")]
+pub use crate::ensure::with_condition_prefix;
+#[cfg(feature = "std")]
+pub use crate::fmt::set_foreign_formatter;
+#[cfg(feature = "std")]
+pub use crate::frame::Frame;
+#[cfg(feature = "std")]
+pub use crate::must_use::{set_unobserved_hook, MustUse};
+#[cfg(feature = "std")]
+pub use crate::parse::file_histogram;
+#[cfg(feature = "std")]
+pub use crate::shared::SharedError;
+#[cfg(feature = "std")]
+pub use crate::template::with_tag_format;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use crate::wire::DeserializedError;
 
 /// The `Error` type, a wrapper around a dynamic error type.
 ///
@@ -382,6 +632,11 @@ pub struct Error {
 ///
 /// This type is the iterator returned by [`Error::chain`].
 ///
+/// It is double-ended, so `error.chain().rev()` yields the root cause first.
+/// The causes are only linked from the outermost down, so the first call to
+/// `next_back` walks the rest of the chain and buffers it in a `Vec`, a
+/// single allocation with one pointer per cause.
+///
 /// # Example
 ///
 /// ```
@@ -608,27 +863,239 @@ pub trait Context<T, E>: context::private::Sealed {
     where
         C: Display + Send + Sync + 'static,
         F: FnOnce() -> C;
+
+    /// Wrap the error value with additional context, and tag the error with
+    /// a machine-readable code.
+    ///
+    /// The message joins the chain like any other context, for humans to
+    /// read, while the code is for callers to branch on and is retrieved
+    /// with [`Error::code`]. The code stays with the error as further context
+    /// is added, and a later `context_coded` replaces it.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn load_user(id: u64) -> Result<String> {
+    ///     let path = format!("users/{}.json", id);
+    ///     fs::read_to_string(&path)
+    ///         .context_coded("user_not_found", format!("no user with id {}", id))
+    /// }
+    ///
+    /// let error = load_user(7).unwrap_err();
+    /// assert_eq!(error.code(), Some("user_not_found"));
+    /// assert_eq!(error.to_string(), "no user with id 7");
+    /// ```
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Fall back to another operation if this one failed, keeping the
+    /// original error as the cause if the fallback fails too.
+    ///
+    /// On success the value is passed through and `fallback` is not called.
+    /// Otherwise the fallback's result is returned: its value if it succeeds,
+    /// or its error with this error's chain continuing underneath it, so that
+    /// the report reads "the fallback failed, because first this failed". The
+    /// causes of a foreign error at the bottom of the fallback's chain give
+    /// way to that of this error. For `Option`, which carries no error, a
+    /// failed fallback's error is returned as it is.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn from_cache() -> Result<String> {
+    ///     Err(anyhow!("cache miss"))
+    /// }
+    ///
+    /// fn from_origin() -> Result<String> {
+    ///     Err(anyhow!("origin unreachable"))
+    /// }
+    ///
+    /// let error = from_cache().or_else_context(from_origin).unwrap_err();
+    /// let chain: Vec<String> = error.chain().map(|cause| cause.to_string()).collect();
+    /// assert_eq!(chain.len(), 2);
+    /// assert!(chain[0].contains("origin unreachable"));
+    /// assert!(chain[1].contains("cache miss"));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    fn or_else_context<F>(self, fallback: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error>;
+
+    /// Wrap the error value with lazily evaluated context, but only if
+    /// `enabled` is true.
+    ///
+    /// This is for context that is costly to build and only wanted in some
+    /// runs, such as under a `--verbose` flag decided at runtime. When
+    /// `enabled` is false, `f` is not called and the error is converted into
+    /// an `anyhow::Error` without adding a level to its chain. For `Option`,
+    /// whose `None` carries no error, the context is the only message there
+    /// could be, so `f` is called on `None` regardless of `enabled`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn load(path: &str, verbose: bool) -> Result<String> {
+    ///     fs::read_to_string(path).context_if_enabled(verbose, || {
+    ///         format!("reading {} from {:?}", path, std::env::current_dir())
+    ///     })
+    /// }
+    ///
+    /// let error = load("/nonexistent", false).unwrap_err();
+    /// assert_eq!(error.chain().count(), 1);
+    /// ```
+    fn context_if_enabled<C, F>(self, enabled: bool, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+
+    /// Wrap the error value with context given as [`fmt::Arguments`],
+    /// evaluated lazily only once an error does occur.
+    ///
+    /// When the arguments are a plain string with nothing interpolated, as
+    /// from `format_args!("loading config")`, the string is used as the
+    /// context directly, and adding the context costs the same single
+    /// allocation as `.context("loading config")`, where `.with_context(||
+    /// format!("loading config"))` allocates at least twice, for the `String`
+    /// and for the error. Arguments that do interpolate values are formatted
+    /// into a `String` as `format!` would, and cost the same as `format!` with
+    /// `with_context`.
+    ///
+    /// Because the arguments borrow the values they interpolate, a closure can
+    /// only return arguments whose values outlive it, such as constants.
+    ///
+    /// Before Rust 1.52 the arguments are always formatted into a `String`.
+    ///
+    /// [`fmt::Arguments`]: core::fmt::Arguments
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn load() -> Result<String> {
+    ///     fs::read_to_string("/nonexistent").with_context_args(|| format_args!("loading config"))
+    /// }
+    ///
+    /// let error = load().unwrap_err();
+    /// assert_eq!(error.to_string(), "loading config");
+    /// ```
+    fn with_context_args<'a, F>(self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> core::fmt::Arguments<'a>;
+
+    /// Map the success value with `f`, or wrap the error value with context
+    /// tagged with the caller's location.
+    ///
+    /// This is `.map(f).map_err(anyhow_context!("{}", context))` in one call.
+    /// The context is tagged as `[file:line, emsg(...)]`, the same as
+    /// [`anyhow_context!`] would tag it at the call site.
+    ///
+    /// Requires Rust 1.46 or newer, which is the first to support
+    /// `#[track_caller]`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    ///
+    /// fn port(s: &str) -> Result<u32> {
+    ///     s.parse::<u16>().map_context(u32::from, "invalid port")
+    /// }
+    ///
+    /// assert_eq!(port("80").unwrap(), 80);
+    /// assert!(port("eighty").unwrap_err().to_string().ends_with(", emsg(invalid port)]"));
+    /// ```
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn map_context<U, C, F>(self, f: F, context: C) -> Result<U, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce(T) -> U;
+
+    /// Wrap the error value with context that is evaluated lazily only once
+    /// an error does occur, tagged with the caller's location.
+    ///
+    /// This is [`with_context`][Context::with_context] with the context
+    /// tagged as `[file:line:column, emsg(...)]`, the same as
+    /// [`anyhow_context!`] would tag it at the call site.
+    ///
+    /// Requires Rust 1.46 or newer, which is the first to support
+    /// `#[track_caller]`.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    /// use std::fs;
+    ///
+    /// fn read(path: &str) -> Result<String> {
+    ///     fs::read_to_string(path).with_location_context(|| format!("failed to read {}", path))
+    /// }
+    ///
+    /// let error = read("/nonexistent").unwrap_err();
+    /// assert!(error.to_string().ends_with(", emsg(failed to read /nonexistent)]"));
+    /// ```
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    fn with_location_context<C, F>(self, f: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
 }
 
 // Not public API. Referenced by macro-generated code.
 #[doc(hidden)]
 pub mod private {
-    pub use core::result::Result::Err;
+    pub use crate::attach::coded;
+    pub use crate::context::typed as typed_context;
+    pub use crate::kv::tag_kv;
+    pub use crate::location::{
+        log_failure, message_at, relative_site, tag_display, wrap_debug, wrap_display,
+    };
+    pub use crate::step::{run as step, StepError};
+    pub use crate::template::tag;
+    #[cfg(feature = "testing")]
+    pub use crate::testing::check_line;
+    pub use core::option::Option::{None, Some};
+    pub use core::result::Result::{self, Err, Ok};
+
+    #[doc(hidden)]
+    pub mod ensure {
+        pub use crate::ensure::{DebugOperand, Failed, Operand, PlainOperand};
+    }
 
     #[doc(hidden)]
     pub mod kind {
-        pub use crate::kind::{AdhocKind, TraitKind};
+        pub use crate::kind::{AdhocKind, NestedKind, RenderedKind, TraitKind};
 
         #[cfg(feature = "std")]
         pub use crate::kind::BoxedKind;
     }
 
+    // anyhow_basic!("literal"): the literal is kept as a &'static str if it
+    // interpolates nothing, and formatted into a String otherwise, for
+    // literals capturing identifiers as in "{name}".
+    #[doc(hidden)]
+    #[cold]
+    pub fn format_err(args: core::fmt::Arguments) -> crate::Error {
+        #[cfg(not(anyhow_no_fmt_arguments_as_str))]
+        #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_fmt_arguments_as_str
+        {
+            if let Some(message) = args.as_str() {
+                return crate::Error::msg(message);
+            }
+        }
+        crate::Error::msg(format!("{}", args))
+    }
+
     #[cfg(anyhow_no_macro_reexport)]
     pub use crate::{
-        __anyhow_concat as concat, __anyhow_format as format, __anyhow_stringify as stringify,
+        __anyhow_concat as concat, __anyhow_format as format, __anyhow_format_args as format_args,
+        __anyhow_stringify as stringify,
     };
     #[cfg(not(anyhow_no_macro_reexport))]
-    pub use core::{concat, stringify};
+    pub use core::{concat, format_args, stringify};
 
     #[cfg(all(not(anyhow_no_macro_reexport), not(feature = "std")))]
     pub use crate::alloc::alloc::format;
@@ -653,6 +1120,15 @@ pub mod private {
         };
     }
 
+    #[cfg(anyhow_no_macro_reexport)]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __anyhow_format_args {
+        ($($tt:tt)*) => {
+            format_args!($($tt)*)
+        };
+    }
+
     #[cfg(anyhow_no_macro_reexport)]
     #[doc(hidden)]
     #[macro_export]
@@ -663,6 +1139,10 @@ pub mod private {
     }
 }
 
-
-/// END_OF_LINE
-pub const END_OF_LINE: &str = "<<EOL>>";
\ No newline at end of file
+/// The default separator between the message of `anyhow_error!` and the `{:?}`
+/// of the error it wraps. See [`set_line_separator`] to use another.
+///
+/// The separator is part of the message, and `{}` shows it as it is, while
+/// `{:?}` starts a new line in its place so that each frame is on a line of
+/// its own. `{:-?}` keeps the separator, as in the message.
+pub const END_OF_LINE: &str = "<<EOL>>";
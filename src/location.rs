@@ -0,0 +1,1270 @@
+use crate::alloc::{Box, Cow, String, ToString, Vec};
+use core::fmt::{self, Debug, Display, Write};
+use core::iter;
+use core::mem;
+use core::ops::Range;
+
+use crate::error::ErrorImpl;
+use crate::fmt::Unformatted;
+use crate::parens::{message_end, unbalance};
+use crate::separator::{line_separator, unescape};
+use crate::template::{render, span_punct, tag};
+use crate::Error;
+
+/// The source location recorded for one level of an error.
+///
+/// Locations are recorded by the `anyhow!` family of macros, which tag the
+/// messages they produce with the `file!()` and `line!()` of the macro call.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow;
+///
+/// let error = anyhow!("oh no!");
+/// let location = error.locations().next().unwrap();
+/// if let Some(location) = location {
+///     eprintln!("created at {}:{}", location.file(), location.line());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    file: Cow<'static, str>,
+    line: u32,
+    column: Option<u32>,
+}
+
+impl Location {
+    /// A location in the given file and line, for errors rebuilt from data
+    /// that was recorded elsewhere.
+    pub fn new<F>(file: F, line: u32) -> Self
+    where
+        F: Into<Cow<'static, str>>,
+    {
+        Location {
+            file: file.into(),
+            line,
+            column: None,
+        }
+    }
+
+    // The location `#[track_caller]` reported.
+    #[cfg(not(anyhow_no_track_caller))]
+    pub(crate) fn of(caller: &'static core::panic::Location<'static>) -> Self {
+        Location {
+            file: Cow::Borrowed(caller.file()),
+            line: caller.line(),
+            column: Some(caller.column()),
+        }
+    }
+
+    /// The same location, narrowed down to the given column of its line.
+    pub fn with_column(mut self, column: u32) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    /// The source file, as reported by `file!()` at the point of capture.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// The 1-based line number in [`file()`][Location::file].
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// The 1-based column in [`line()`][Location::line], if it was recorded.
+    ///
+    /// The tagging macros record the `column!()` of their call, which tells
+    /// apart several fallible calls on one line.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn parse(tagged: &str) -> Option<Self> {
+        split_tag(tagged).map(|(location, _)| location)
+    }
+}
+
+// Splits a message tagged by the anyhow! family of macros into the location and
+// the byte range of the message inside `emsg(...)`. Tags look like
+// `[file:line:column emsg(...)]` or, for anyhow_error! and anyhow_context!,
+// `[file:line:column, emsg(...)]`, and for anyhow_code!
+// `[file:line:column code(...) emsg(...)]`, where the column may be missing
+// and, with "tracing-integration", ` span(target::name)` may come before
+// ` emsg(`; anyhow_error! follows the tag with the line separator and the Debug
+// representation of the error it wrapped.
+pub(crate) fn split_tag(tagged: &str) -> Option<(Location, Range<usize>)> {
+    let (location, range) = split_tag_ref(tagged)?;
+    let location = Location {
+        file: Cow::Owned(String::from(location.file)),
+        line: location.line,
+        column: location.column,
+    };
+    Some((location, range))
+}
+
+// Like split_tag, with the file borrowed from the message.
+fn split_tag_ref(tagged: &str) -> Option<(LocationRef<'_>, Range<usize>)> {
+    if !tagged.starts_with('[') {
+        return None;
+    }
+    let open = tagged.find(" emsg(")?;
+    let (file, line, column, _) = parse_head(&tagged[1..open])?;
+    let start = open + " emsg(".len();
+    let body = &tagged[start..];
+    let (end, _, _) = close_tag(body, &|close| {
+        let after = &body[close + 2..];
+        after.is_empty() || after.starts_with(line_separator())
+    })?;
+    let location = LocationRef {
+        file: strip_module(file),
+        line,
+        column,
+    };
+    Some((location, start..start + end))
+}
+
+// Splits the part of a tag before ` emsg(` into the file, line and column,
+// along with the offset of the `:` in front of the line.
+fn parse_head(head: &str) -> Option<(&str, u32, Option<u32>, usize)> {
+    let head = strip_annotations(head).trim_end_matches(',');
+    let colon = head.rfind(':')?;
+    let last = head[colon + 1..].parse().ok()?;
+    let file = &head[..colon];
+    if let Some(before) = file.rfind(':') {
+        if let Ok(line) = file[before + 1..].parse() {
+            return Some((&file[..before], line, Some(last), before));
+        }
+    }
+    Some((file, last, None, colon))
+}
+
+// The part of a tag before ` emsg(` without the ` code(...)` that
+// anyhow_code! puts after the location, or the ` span(...)` put after that
+// with "tracing-integration".
+fn strip_annotations(head: &str) -> &str {
+    let mut head = head;
+    for annotation in [" span(", " code("].iter() {
+        match head.rfind(annotation) {
+            Some(open) if head.ends_with(')') => head = &head[..open],
+            _ => {}
+        }
+    }
+    head
+}
+
+// Whether `value` displays as exactly `expected`, found by comparing the
+// output piece by piece as it is written instead of collecting it.
+fn displays_as(value: &dyn Display, expected: &str) -> bool {
+    struct Compare<'a> {
+        rest: &'a str,
+    }
+
+    impl Write for Compare<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if !self.rest.starts_with(s) {
+                return Err(fmt::Error);
+            }
+            self.rest = &self.rest[s.len()..];
+            Ok(())
+        }
+    }
+
+    let mut compare = Compare { rest: expected };
+    write!(compare, "{}", value).is_ok() && compare.rest.is_empty()
+}
+
+// The site of a tag with `prefix` and the path separator after it removed
+// from the front of its file, for the "relative-paths" feature. Sites whose
+// file does not start with the prefix are kept as they are.
+#[doc(hidden)]
+pub fn relative_site(site: &'static str, prefix: Option<&'static str>) -> Cow<'static, str> {
+    let prefix = match prefix {
+        Some(prefix) if !prefix.is_empty() => prefix.trim_end_matches(|c| c == '/' || c == '\\'),
+        _ => return Cow::Borrowed(site),
+    };
+    let file = site.len() - strip_module(site).len();
+    if !site[file..].starts_with(prefix) {
+        return Cow::Borrowed(site);
+    }
+    let rest = &site[file + prefix.len()..];
+    if !rest.starts_with(|c| c == '/' || c == '\\') {
+        return Cow::Borrowed(site);
+    }
+    let rest = &rest[1..];
+    if file == 0 {
+        Cow::Borrowed(rest)
+    } else {
+        Cow::Owned([&site[..file], rest].concat())
+    }
+}
+
+// The file of a tag, without the module path that the tagging macros put in
+// front of it, separated by a space, when the "module-path" feature is on.
+fn strip_module(file: &str) -> &str {
+    if !cfg!(feature = "module-path") {
+        return file;
+    }
+    match file.find(' ') {
+        Some(space) => &file[space + 1..],
+        None => file,
+    }
+}
+
+// The byte range of the `:line:column` part of a tagged message's location.
+pub(crate) fn line_range(tagged: &str) -> Option<Range<usize>> {
+    split_tag(tagged)?;
+    let open = tagged.find(" emsg(")?;
+    let head = &tagged[1..open];
+    let (_, _, _, colon) = parse_head(head)?;
+    Some(1 + colon..1 + strip_annotations(head).trim_end_matches(',').len())
+}
+
+// The location and the message with its tag removed, keeping anything that
+// follows the tag.
+pub(crate) fn untag(tagged: &str) -> Option<(Location, String)> {
+    let (location, range) = split_tag(tagged)?;
+    // The tag ends at the first `)]` past the message, after any key-value
+    // pairs.
+    let end = range.end + tagged[range.end..].find(")]")? + 2;
+    let message = [
+        &unescape(&unbalance(&tagged[range.clone()])),
+        &tagged[end..],
+    ]
+    .concat();
+    Some((location, message))
+}
+
+// The message of `anyhow_located!(value)` and `anyhow_context!(value)`: the
+// Display of `value` inside a tag for the given site, with `punct` (empty or
+// ",") after the site. A value whose Display already starts with a tag, such
+// as an error made by anyhow_located!, is not wrapped in a second one.
+// Instead a tag with an empty message is put in front of it, joined by the
+// line separator the way anyhow_error! joins the error it wraps, so that
+// each location stays its own frame.
+pub fn tag_display(site: &str, line: u32, column: u32, punct: &str, value: &dyn Display) -> String {
+    let message = value.to_string();
+    if split_tag(&message).is_some() {
+        let mut tagged = tag(site, line, column, punct, &"");
+        tagged.push_str(line_separator());
+        tagged.push_str(&message);
+        tagged
+    } else {
+        tag(site, line, column, punct, &message)
+    }
+}
+
+// The error made by the closure of anyhow_error!: `msg` tagged with the site,
+// followed by the line separator and the Debug of the error it wraps. With
+// "log-integration", the message is also logged at the error level, under
+// the module path of the call as the target and with the file and line as
+// key-values.
+//
+// When the Debug of the wrapped error ends with a
+// backtrace, as that of an anyhow::Error does if one was captured, no new one
+// is captured: the chain already shows the one taken nearest to where it
+// started, and capturing one at every level would put a backtrace per level
+// into the messages of a deep chain, each copied again into every level
+// above it.
+#[cold]
+pub fn wrap_debug(
+    target: &str,
+    site: &str,
+    line: u32,
+    column: u32,
+    msg: &dyn Display,
+    error: &dyn Debug,
+) -> Error {
+    // An anyhow::Error keeps its line separators in `{:-?}`, so that its
+    // frames stay apart in the new message; other types ignore the flag.
+    wrap(
+        target,
+        site,
+        line,
+        Some(column),
+        &span_punct(","),
+        msg,
+        format_args!("{:-?}", error),
+    )
+}
+
+// The error made by the closure of anyhow_error_brief!: like wrap_debug, with
+// the Display of the error in place of its Debug.
+#[cold]
+pub fn wrap_display(
+    target: &str,
+    site: &str,
+    line: u32,
+    column: u32,
+    msg: &dyn Display,
+    error: &dyn Display,
+) -> Error {
+    wrap(
+        target,
+        site,
+        line,
+        Some(column),
+        &span_punct(","),
+        msg,
+        format_args!("{}", error),
+    )
+}
+
+// The error made by the closure of anyhow_error! for an anyhow::Error: the
+// same message as wrap_debug makes it, with the error kept under the new tag
+// rather than rendered into a string, so that wrapping a deep chain is not
+// paid for again at every level. An error that nest made and that nothing has
+// been done to since gets the tag added to its list instead of being wrapped
+// once more, so the innermost error is rendered once however deep the chain.
+#[cold]
+pub(crate) fn nest(
+    target: &str,
+    site: &str,
+    line: u32,
+    column: u32,
+    msg: &dyn Display,
+    mut error: Error,
+) -> Error {
+    log(target, site, line, msg);
+
+    let tag = render(site, line, Some(column), &span_punct(","), msg);
+    if let Some(nested) = Nested::of(&mut error) {
+        nested.tags.push(tag);
+        return error;
+    }
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    let backtrace = {
+        use crate::backtrace::BacktraceStatus;
+
+        match unsafe { ErrorImpl::backtrace(error.inner.by_ref()) }.status() {
+            BacktraceStatus::Captured => no_backtrace!(),
+            _ => backtrace!(),
+        }
+    };
+    #[cfg(not(any(backtrace, feature = "backtrace")))]
+    let backtrace = None;
+    let tags = iter::once(tag).collect();
+    Error::from_adhoc(Nested { tags, error }, backtrace)
+}
+
+// The message of an error made by nest: the tags, outermost first and each
+// followed by the line separator, then the `{:-?}` of the error, rendered each
+// time the message is. Its `{:-}`, the form the frame parsers read, has the
+// error rendered unformatted.
+pub(crate) struct Nested {
+    // Innermost first, as they were added.
+    tags: Vec<String>,
+    error: Error,
+}
+
+impl Nested {
+    // The Nested of an error made by nest, if it is still a bare message that
+    // a tag can be added to in place: one with nothing recorded on it, which
+    // would otherwise describe only the levels it was recorded on.
+    fn of(error: &mut Error) -> Option<&mut Nested> {
+        unsafe {
+            if !ErrorImpl::is_message(error.inner.by_ref()) {
+                return None;
+            }
+            if !ErrorImpl::header(error.inner.by_ref()).is_fresh() {
+                return None;
+            }
+        }
+        // Also clears the frames parsed from the message, which the new tag
+        // makes stale.
+        error.downcast_mut::<Nested>()
+    }
+
+    // The outermost tag.
+    fn tag(&self) -> &str {
+        &self.tags[self.tags.len() - 1]
+    }
+}
+
+impl Display for Nested {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for tag in self.tags.iter().rev() {
+            write!(f, "{}{}", tag, line_separator())?;
+        }
+        if f.sign_minus() {
+            write!(f, "{:-?}", Unformatted(&self.error))
+        } else {
+            write!(f, "{:-?}", self.error)
+        }
+    }
+}
+
+impl Debug for Nested {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.to_string(), f)
+    }
+}
+
+fn wrap(
+    target: &str,
+    site: &str,
+    line: u32,
+    column: Option<u32>,
+    punct: &str,
+    msg: &dyn Display,
+    error: fmt::Arguments,
+) -> Error {
+    log(target, site, line, msg);
+
+    let mut message = render(site, line, column, punct, msg);
+    message.push_str(line_separator());
+    let start = message.len();
+    let _ = message.write_fmt(error);
+    let backtrace = if message[start..].contains("\n\nStack backtrace:\n") {
+        no_backtrace!()
+    } else {
+        backtrace!()
+    };
+    Error::from_adhoc(message, backtrace)
+}
+
+// The error of fail_metric!, logged with "log-integration" the way the
+// message of anyhow_error! is: by its emsg, or by its Display if untagged.
+#[cold]
+pub fn log_failure(target: &str, site: &str, line: u32, error: Error) -> Error {
+    match error.emsg() {
+        Some(msg) => log(target, site, line, &msg),
+        None => log(target, site, line, &error),
+    }
+    error
+}
+
+// The message of anyhow! with "structured-location", kept as it is, with the
+// location of the call next to it as Error::msg_at keeps one.
+#[cold]
+pub fn message_at(message: fmt::Arguments, file: &'static str, line: u32, column: u32) -> Error {
+    let mut error = Error::msg(crate::context::render(message));
+    let meta = unsafe { ErrorImpl::meta_mut(error.inner.by_mut()) };
+    meta.origin = Some(Location::new(file, line).with_column(column));
+    error
+}
+
+// Logs the message of anyhow_error! with "log-integration".
+fn log(target: &str, site: &str, line: u32, msg: &dyn Display) {
+    #[cfg(feature = "log-integration")]
+    log::error!(target: target, file = strip_module(site), line = line; "{}", msg);
+    #[cfg(not(feature = "log-integration"))]
+    let _ = (target, site, line, msg);
+}
+
+/// An ad-hoc error tagged with a location known only at runtime, such as one
+/// received over the network along with the message.
+///
+/// The message is `[file:line emsg(msg)]`, with `msg` escaped and the tag
+/// rendered the way the macros render theirs, so that
+/// [`location()`][Error::location] and the frame parsers read `file` and
+/// `line` back out of it. No column is recorded. Unlike
+/// [`Error::new_tagged`], nothing about the location is taken from the call.
+/// For an error of several frames, see [`Error::from_frames`].
+///
+/// # Example
+///
+/// ```
+/// let (file, line) = (String::from("worker/src/job.rs"), 88);
+///
+/// let error = anyhow::tagged(&file, line, "job timed out");
+/// assert_eq!(error.to_string(), "[worker/src/job.rs:88 emsg(job timed out)]");
+/// assert_eq!(error.location().unwrap().line(), 88);
+/// ```
+pub fn tagged<M>(file: &str, line: u32, msg: M) -> Error
+where
+    M: Display,
+{
+    Error::msg(render(file, line, None, "", &msg))
+}
+
+/// A function for `.map_err(...)` that does what the closure of
+/// `anyhow_error!(msg)` does, with a location given explicitly rather than
+/// taken from a macro call.
+///
+/// The returned closure tags `msg` with `file` and `line`, without a column,
+/// and follows it with the `Debug` of the error it is given. Being boxed, it
+/// can be stored, passed around and reused for any number of errors. The
+/// error type only needs `Debug`: it is formatted, not kept, so it need not
+/// be `'static`, `Send` or `Sync`, while the closure itself is all three.
+///
+/// # Example
+///
+/// ```
+/// use std::num::ParseIntError;
+///
+/// let invalid_port = anyhow::map_err_at::<ParseIntError, _>("src/config.rs", 12, "invalid port");
+///
+/// let error = "eighty".parse::<u16>().map_err(&invalid_port).unwrap_err();
+/// assert!(error.to_string().starts_with("[src/config.rs:12, emsg(invalid port)]"));
+/// ```
+pub fn map_err_at<E, M>(
+    file: &'static str,
+    line: u32,
+    msg: M,
+) -> Box<dyn Fn(E) -> Error + Send + Sync>
+where
+    E: Debug,
+    M: Display + Send + Sync + 'static,
+{
+    Box::new(move |error| {
+        wrap(
+            "anyhow",
+            file,
+            line,
+            None,
+            ",",
+            &msg,
+            format_args!("{:-?}", error),
+        )
+    })
+}
+
+/// A function for `.map_err(...)` that does what the closure of
+/// `anyhow_context!(msg)` does, with a location given explicitly rather than
+/// taken from a macro call.
+///
+/// The returned closure adds `msg` tagged with `file` and `line`, without a
+/// column, as context to the `anyhow::Error` it is given. Like
+/// [`map_err_at`], it can be stored and reused.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_located;
+///
+/// let loading = anyhow::context_at("src/config.rs", 30, "loading config");
+///
+/// let error = Err::<(), _>(anyhow_located!("no such file")).map_err(&loading).unwrap_err();
+/// assert_eq!(error.to_string(), "[src/config.rs:30, emsg(loading config)]");
+/// ```
+pub fn context_at<M>(
+    file: &'static str,
+    line: u32,
+    msg: M,
+) -> Box<dyn Fn(Error) -> Error + Send + Sync>
+where
+    M: Display + Send + Sync + 'static,
+{
+    Box::new(move |error| error.context(render(file, line, None, ",", &msg)))
+}
+
+/// Extract every location-tagged message from a line of text, such as a line
+/// of a log file written by a program using anyhow.
+///
+/// Each `[file:line emsg(message)]` or `[file:line, emsg(message)]` segment
+/// found in `log_line` is returned as a `(file, line, message)` triple, in
+/// the order they appear. This includes the segments that `anyhow_error!`
+/// chains together with the [line separator][crate::line_separator], and
+/// segments separated by the `: ` of `{:#}`. Text around and between the
+/// segments is skipped, as is anything that looks like a tag but is not one.
+///
+/// A message ends at the first `)]` that is followed by the end of the line,
+/// by the [line separator][crate::line_separator], by whitespace, or by one
+/// of `:,;[`. Messages containing such a sequence themselves are cut short
+/// there. The column of a `[file:line:column ...]` tag is not returned.
+///
+/// # Example
+///
+/// ```
+/// let line = "ERROR [src/api.rs:40, emsg(request failed)]<<EOL>>[src/db.rs:7 emsg(timed out)] in 3ms";
+///
+/// assert_eq!(
+///     anyhow::extract_all(line),
+///     [
+///         ("src/api.rs".to_owned(), 40, "request failed".to_owned()),
+///         ("src/db.rs".to_owned(), 7, "timed out".to_owned()),
+///     ],
+/// );
+/// ```
+pub fn extract_all(log_line: &str) -> Vec<(String, u32, String)> {
+    let mut found = Vec::new();
+    let mut rest = log_line;
+    while let Some(open) = rest.find('[') {
+        rest = &rest[open..];
+        match extract_one(rest) {
+            Some((file, line, message, len)) => {
+                found.push((
+                    String::from(file),
+                    line,
+                    unescape(&unbalance(message)).into_owned(),
+                ));
+                rest = &rest[len..];
+            }
+            None => rest = &rest[1..],
+        }
+    }
+    found
+}
+
+// Parses a tag at the start of `text`, returning the file, line and message
+// along with the length of the tag.
+pub(crate) fn extract_one(text: &str) -> Option<(&str, u32, &str, usize)> {
+    let (file, line, msg, _, len) = extract_tag(text)?;
+    Some((file, line, msg, len))
+}
+
+// Like extract_one, along with the key-value pairs of the tag, if it has any.
+pub(crate) fn extract_tag(text: &str) -> Option<(&str, u32, &str, Option<&str>, usize)> {
+    let open = text.find(" emsg(")?;
+    let head = strip_module(strip_annotations(&text[1..open]).trim_end_matches(','));
+    if head.contains(|c: char| c.is_whitespace() || c == '[' || c == ']') {
+        return None;
+    }
+    let (file, line, _, _) = parse_head(head)?;
+    let start = open + " emsg(".len();
+    let body = &text[start..];
+    let (end, kv, close) = close_tag(body, &|close| {
+        let after = &body[close + 2..];
+        after.is_empty()
+            || after.starts_with(line_separator())
+            || after.starts_with(|c: char| c.is_whitespace() || ":,;[".contains(c))
+    })?;
+    Some((file, line, &body[..end], kv, start + close + 2))
+}
+
+// Finds the end of the body of a tag, the part after ` emsg(`: the offset
+// where the message ends, the key-value pairs if there are any, and the
+// offset of the `)]` closing the tag. `closes` says whether a `)]` at an
+// offset can end the tag, going by what follows it.
+//
+// The message is balanced when it is written into the tag, so it normally
+// ends at the first `)` not matched inside it. Tags with unbalanced messages,
+// as written before messages were balanced or by hand, end at the first `)]`
+// that `closes` accepts.
+fn close_tag<'a>(
+    body: &'a str,
+    closes: &dyn Fn(usize) -> bool,
+) -> Option<(usize, Option<&'a str>, usize)> {
+    if let Some(end) = message_end(body) {
+        let rest = &body[end..];
+        if rest.starts_with(")]") && closes(end) {
+            return Some((end, None, end));
+        }
+        if rest.starts_with(") kv(") {
+            let start = end + ") kv(".len();
+            if let Some(len) = body[start..].find(')') {
+                let close = start + len;
+                if body[close..].starts_with(")]") && closes(close) {
+                    return Some((end, Some(&body[start..close]), close));
+                }
+            }
+        }
+    }
+    let close = body
+        .match_indices(")]")
+        .map(|(i, _)| i)
+        .find(|&i| closes(i))?;
+    let (msg, kv) = split_kv(&body[..close]);
+    Some((msg.len(), kv, close))
+}
+
+// Splits the body of a tag into the message and the key-value pairs that
+// anyhow_kv! puts after it as `msg) kv(key=value,...`. The values are encoded
+// so that they never contain a `)`.
+fn split_kv(body: &str) -> (&str, Option<&str>) {
+    match body.rfind(") kv(") {
+        Some(open) if !body[open + ") kv(".len()..].contains(')') => {
+            (&body[..open], Some(&body[open + ") kv(".len()..]))
+        }
+        _ => (body, None),
+    }
+}
+
+// Appends `text` with each tag replaced by its own message, stripped in turn,
+// and line separators between tags replaced by ": ".
+pub(crate) fn strip_tags(text: &str, stripped: &mut String) {
+    let separator = line_separator();
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        stripped.push_str(&rest[..open]);
+        rest = &rest[open..];
+        match extract_one(rest) {
+            Some((_, _, message, len)) => {
+                strip_tags(&unbalance(message), stripped);
+                rest = &rest[len..];
+                if rest.starts_with(separator) {
+                    stripped.push_str(": ");
+                    rest = &rest[separator.len()..];
+                }
+            }
+            None => {
+                stripped.push('[');
+                rest = &rest[1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`Location`] borrowed from the message of an error, as returned by
+/// [`Error::location_ref`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LocationRef<'a> {
+    file: &'a str,
+    line: u32,
+    column: Option<u32>,
+}
+
+impl<'a> LocationRef<'a> {
+    /// The source file, as reported by `file!()` at the point of capture.
+    pub fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// The 1-based line number in [`file()`][LocationRef::file].
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// The 1-based column in [`line()`][LocationRef::line], if it was
+    /// recorded.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+}
+
+impl Display for LocationRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// An iterator of the location recorded for each level of the chain.
+    ///
+    /// This yields exactly one item per error visited by
+    /// [`chain()`][Error::chain], in the same order. Levels that were not
+    /// created through one of the location-tagging macros, including every
+    /// foreign error, yield `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, anyhow_context, Result};
+    ///
+    /// fn open() -> Result<()> {
+    ///     Err(anyhow!("no such file"))
+    /// }
+    ///
+    /// let error = open().map_err(anyhow_context!("loading config")).unwrap_err();
+    /// let breadcrumb: Vec<String> = error
+    ///     .locations()
+    ///     .map(|location| location.map_or("?".to_owned(), |l| l.to_string()))
+    ///     .collect();
+    /// eprintln!("{}", breadcrumb.join(" <- "));
+    /// assert_eq!(breadcrumb.len(), error.chain().count());
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn locations(&self) -> impl Iterator<Item = Option<Location>> + '_ {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let len = self.chain().count();
+        let origin = self.origin();
+        self.chain().enumerate().map(move |(n, level)| {
+            if n + 1 == len && origin.is_some() {
+                origin.cloned()
+            } else if n < native {
+                Location::parse(&level.to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The location recorded for the outermost level of the chain, if it was
+    /// created through one of the location-tagging macros.
+    ///
+    /// This is the first item of [`locations()`][Error::locations], and is
+    /// available without the "std" feature. Errors made with [`Error::msg`]
+    /// from an untagged message, and foreign errors, give `None`. For an error
+    /// made by [`Error::msg_at`], this is the location given to it, as long as
+    /// no context has been added.
+    ///
+    /// The location is parsed the first time it is asked for, and kept with
+    /// the error for the next calls.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error};
+    ///
+    /// let location = anyhow!("oh no!").location().unwrap();
+    /// assert_eq!(location.file(), file!());
+    ///
+    /// assert!(Error::msg("oh no!").location().is_none());
+    /// ```
+    pub fn location(&self) -> Option<Location> {
+        self.cached_location().cloned()
+    }
+
+    // The location of location(), parsed afresh rather than read from the
+    // cache of the error.
+    pub(crate) fn find_location(&self) -> Option<Location> {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        if native == 0 {
+            return None;
+        }
+        let mut chain = unsafe { ErrorImpl::chain(self.inner.by_ref()) };
+        let outermost = chain.next()?;
+        match self.origin() {
+            Some(origin) if chain.next().is_none() => Some(origin.clone()),
+            _ => Location::parse(&outermost.to_string()),
+        }
+    }
+
+    /// The same location as [`location()`][Error::location], borrowed from
+    /// the error rather than copied out of it, for code on a hot path such as
+    /// deciding whether to retry.
+    ///
+    /// Finding the location allocates nothing. It relies on the outermost
+    /// message being stored as a string, as the tagging macros store it; for
+    /// an outermost level of any other type this is `None`, even where
+    /// [`location()`][Error::location] would find a tag in its `Display`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("connection reset");
+    /// let location = error.location_ref().unwrap();
+    /// assert_eq!(location.file(), file!());
+    /// assert_eq!(location.line(), line!() - 3);
+    /// ```
+    pub fn location_ref(&self) -> Option<LocationRef<'_>> {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        if native == 0 {
+            return None;
+        }
+        let mut chain = unsafe { ErrorImpl::chain(self.inner.by_ref()) };
+        chain.next()?;
+        match self.origin() {
+            Some(origin) if chain.next().is_none() => Some(LocationRef {
+                file: origin.file(),
+                line: origin.line,
+                column: origin.column,
+            }),
+            _ => split_tag_ref(self.outermost_str()?).map(|(location, _)| location),
+        }
+    }
+
+    /// Whether the outermost level's message is a location tag in the
+    /// `[file:line emsg(...)]` format, as made by this crate's tagging macros
+    /// and methods, rather than a plain message such as that of
+    /// [`Error::msg`] or of upstream anyhow's `anyhow!`.
+    ///
+    /// This is a quick check before handing the error to the parsers of
+    /// [`anyhow::parse`][crate::parse]. A tag rendered with a custom
+    /// [`Template`][crate::Template] is not recognized, as the parsers do not
+    /// recognize it either, and neither is a location recorded with
+    /// [`msg_at()`][Error::msg_at], which is not part of the message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_located, Error};
+    ///
+    /// assert!(anyhow_located!("x").is_tagged());
+    /// assert!(!Error::msg("x").is_tagged());
+    /// ```
+    pub fn is_tagged(&self) -> bool {
+        match self.outermost_str() {
+            Some(message) => split_tag_ref(message).is_some(),
+            None => match unsafe { ErrorImpl::chain(self.inner.by_ref()) }.next() {
+                Some(outermost) => split_tag_ref(&outermost.to_string()).is_some(),
+                None => false,
+            },
+        }
+    }
+
+    // The location given to Error::msg_at, which belongs to the innermost
+    // level.
+    pub(crate) fn origin(&self) -> Option<&Location> {
+        let meta = unsafe { ErrorImpl::meta(self.inner.by_ref()) }?;
+        meta.origin.as_ref()
+    }
+
+    /// Strip the location tag from the outermost message.
+    ///
+    /// The result is the error that `anyhow_basic!` would have produced in
+    /// place of the tagging macro: the outermost message is reduced to the
+    /// text inside `emsg(...)`, while every cause underneath, the backtrace,
+    /// and anything following the tag (such as the error wrapped by
+    /// `anyhow_error!`) are kept as they are. An error whose outermost
+    /// message is not tagged is returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("no such file"))
+    ///     .map_err(anyhow_context!("failed to read config"))
+    ///     .unwrap_err()
+    ///     .plain();
+    ///
+    /// assert_eq!(error.to_string(), "failed to read config");
+    /// assert!(error.root_cause().to_string().ends_with(" emsg(no such file)]"));
+    /// ```
+    pub fn plain(self) -> Self {
+        let mut outermost = true;
+        self.rewrite(&mut |message| {
+            if !mem::replace(&mut outermost, false) {
+                return None;
+            }
+            untag(message).map(|(_, message)| message)
+        })
+    }
+
+    /// The whole chain as `{:#}` would render it, with every location tag
+    /// stripped down to the message inside it, for showing to end users.
+    ///
+    /// This strips tags anywhere in the messages anyhow created, not only at
+    /// their start: the `{:?}` of an error wrapped by `anyhow_error!` has its
+    /// tags stripped too, and the [line separator][crate::line_separator]
+    /// joining them becomes `": "`, just like the levels of the chain.
+    /// Messages of foreign errors are kept as they are. The error itself is
+    /// not changed, so `{:?}` still shows the tags for developers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("no such file"))
+    ///     .map_err(anyhow_context!("failed to read config"))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error.user_message(), "failed to read config: no such file");
+    /// ```
+    pub fn user_message(&self) -> String {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut rendered = String::new();
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            if n > 0 {
+                rendered.push_str(": ");
+            }
+            let message = level.to_string();
+            if n < native {
+                strip_tags(&message, &mut rendered);
+            } else {
+                rendered.push_str(&message);
+            }
+        }
+        rendered
+    }
+
+    /// The message inside the `emsg(...)` group of the outermost level's
+    /// location tag, as it was passed to the tagging macro.
+    ///
+    /// Unlike [`user_message()`][Error::user_message], this is one level
+    /// only, and anything the tag is followed by, such as the Debug of the
+    /// error wrapped by `anyhow_error!`, is left out. Returns `None` if the
+    /// outermost message is not in tagged form, including errors created by
+    /// [`msg_at()`][Error::msg_at], whose location is recorded apart from the
+    /// message.
+    ///
+    /// The message is borrowed from the tag, so a parenthesis in it that does
+    /// not pair up with another keeps the backslash that the tag puts in
+    /// front of it; see [`parse`][crate::parse]. The frames of
+    /// [`parsed_frames()`][Error::parsed_frames] have it removed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow_located;
+    ///
+    /// let error = anyhow_located!("no such user {}", 7);
+    ///
+    /// assert_eq!(error.emsg(), Some("no such user 7"));
+    /// assert_eq!(anyhow::Error::msg("no such user").emsg(), None);
+    /// ```
+    pub fn emsg(&self) -> Option<&str> {
+        let message = self.outermost_str()?;
+        let (_, range) = split_tag(message)?;
+        Some(&message[range])
+    }
+
+    /// The message of the outermost frame of this error: the message inside
+    /// the outermost level's location tag, or the outermost level's whole
+    /// message if it has no tag.
+    ///
+    /// This is the most recent thing that went wrong, as opposed to the
+    /// [root cause][Error::root_cause]. It differs from
+    /// [`emsg()`][Error::emsg] for an outermost level without a tag, such as
+    /// one created by [`Error::msg`] or [`msg_at()`][Error::msg_at], whose
+    /// message is returned as it is. Returns `None` if the outermost level's
+    /// message is not a string, such as that of a foreign error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_located, Context, Error};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("no such file"))
+    ///     .map_err(anyhow_error!("loading config"))
+    ///     .unwrap_err();
+    /// assert_eq!(error.outermost_message(), Some("loading config"));
+    ///
+    /// let error = error.context("starting up");
+    /// assert_eq!(error.outermost_message(), Some("starting up"));
+    /// assert_eq!(error.emsg(), None);
+    /// ```
+    pub fn outermost_message(&self) -> Option<&str> {
+        let message = self.outermost_str()?;
+        match split_tag(message) {
+            Some((_, range)) => Some(&message[range]),
+            None => Some(message),
+        }
+    }
+
+    // The outermost level's message, if it is a string.
+    fn outermost_str(&self) -> Option<&str> {
+        let outermost = unsafe { ErrorImpl::chain(self.inner.by_ref()) }.next()?;
+        // The tagging macros store their message as the outermost level's
+        // context or ad-hoc message, so one of these downcasts finds it.
+        // Downcasting also looks through context of other types, hence the
+        // comparison against the outermost level, which is made as it is
+        // formatted so as not to allocate. The message of an error nested by
+        // anyhow_error! is only rendered as it is formatted, so its tag is
+        // taken instead; it is the outermost level if that is a message.
+        if unsafe { ErrorImpl::is_message(self.inner.by_ref()) } {
+            if let Some(nested) = self.downcast_ref::<Nested>() {
+                return Some(nested.tag());
+            }
+        }
+        self.downcast_ref::<String>()
+            .map(String::as_str)
+            .filter(|message| displays_as(outermost, message))
+            .or_else(|| {
+                self.downcast_ref::<Cow<'static, str>>()
+                    .map(|message| &**message)
+                    .filter(|message| displays_as(outermost, message))
+            })
+            .or_else(|| {
+                self.downcast_ref::<&'static str>()
+                    .cloned()
+                    .filter(|message| displays_as(outermost, message))
+            })
+    }
+
+    /// Leave the location tags of this error out when it is rendered.
+    ///
+    /// Unlike [`plain()`][Error::plain], the messages are not changed: `{}`,
+    /// `{:#}` and `{:?}` show them without their `[file:line emsg(...)]`
+    /// tag, while [`locations()`][Error::locations] and
+    /// [`frames()`][Error::frames] still report where each level was
+    /// created. This applies to every level of the chain so far. Context
+    /// added afterwards is rendered with its location as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow_located;
+    ///
+    /// let error = anyhow_located!("username is already taken").hide_location();
+    ///
+    /// assert_eq!(error.to_string(), "username is already taken");
+    /// # #[cfg(feature = "std")]
+    /// assert!(error.locations().next().unwrap().is_some());
+    /// ```
+    pub fn hide_location(mut self) -> Self {
+        let depth = unsafe { ErrorImpl::chain(self.inner.by_ref()) }.count();
+        let meta = unsafe { ErrorImpl::meta_mut(self.inner.by_mut()) };
+        meta.hidden_locations = depth;
+        self
+    }
+
+    /// Create an error from a runtime message, tagged with the caller's
+    /// location.
+    ///
+    /// This produces the same message as `anyhow_located!("{}", message)`
+    /// written at the call site, for messages that are only built at runtime
+    /// and for places where a function is needed rather than a macro.
+    ///
+    /// Requires Rust 1.46 or newer, which is the first to support
+    /// `#[track_caller]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Error, Result};
+    ///
+    /// fn check(values: &[i32]) -> Result<()> {
+    ///     for (i, value) in values.iter().enumerate() {
+    ///         if *value < 0 {
+    ///             let message = format!("value #{} is negative: {}", i, value);
+    ///             return Err(Error::msg_located(message));
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let error = check(&[1, -2]).unwrap_err();
+    /// let location = error.locations().next().unwrap().unwrap();
+    /// assert_eq!(location.file(), file!());
+    /// ```
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[cold]
+    #[track_caller]
+    pub fn msg_located<M>(message: M) -> Self
+    where
+        M: Display,
+    {
+        let caller = core::panic::Location::caller();
+        Error::msg(tag(
+            caller.file(),
+            caller.line(),
+            caller.column(),
+            "",
+            &message,
+        ))
+    }
+
+    /// Create an error from a message, recording the given location
+    /// separately rather than tagging the message with it.
+    ///
+    /// The `Display` representation is just the message, with no
+    /// `[file:line emsg(...)]` around it, which keeps location noise out of
+    /// messages shown to end users. The location is reported by
+    /// [`location()`][Error::location] and
+    /// [`locations()`][Error::locations] as that of the innermost level of the
+    /// chain, and the `{:?}` representation renders that level tagged as
+    /// `anyhow!` would have tagged it.
+    ///
+    /// As with the tagging macros, a backtrace captured for the error starts
+    /// at the caller of `msg_at`, so that its first frame outside anyhow is
+    /// the location recorded here when that is `file!()` and `line!()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::msg_at("permission denied", file!(), line!());
+    /// assert_eq!(error.to_string(), "permission denied");
+    /// assert_eq!(error.location().unwrap().file(), file!());
+    /// assert!(format!("{:?}", error).starts_with(&format!("[{}:", file!())));
+    /// ```
+    #[cold]
+    pub fn msg_at<M>(message: M, file: &'static str, line: u32) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        let mut error = Error::msg(message);
+        let meta = unsafe { ErrorImpl::meta_mut(error.inner.by_mut()) };
+        meta.origin = Some(Location::new(file, line));
+        error
+    }
+
+    /// Put a new location-tagged frame in front of this error, the way the
+    /// closure made by `anyhow_error!` does.
+    ///
+    /// The result is a new ad-hoc error whose message is
+    /// `[file:line, emsg(msg)]`, followed by the
+    /// [line separator][crate::line_separator] and the `{:?}` representation
+    /// of this error. This is for code that has a location from somewhere
+    /// other than the macros, such as a `#[track_caller]` helper.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::msg("disk full").prepend_location("src/log.rs", 12, "writing log");
+    ///
+    /// let frames = anyhow::parse_frames(&error);
+    /// assert_eq!((frames[0].file.as_str(), frames[0].line), ("src/log.rs", 12));
+    /// assert_eq!(frames[0].msg, "writing log");
+    /// assert_eq!(frames[1].msg, "disk full");
+    /// ```
+    pub fn prepend_location<M>(self, file: &str, line: u32, msg: M) -> Self
+    where
+        M: Display,
+    {
+        Error::msg(crate::private::format!(
+            "{}{}{:-?}",
+            render(file, line, None, ",", &msg),
+            line_separator(),
+            self,
+        ))
+    }
+
+    /// Record the caller's location as a site this error propagated through.
+    ///
+    /// The conversion performed by `?` from any other error type into
+    /// `anyhow::Error` records its location this way automatically. Passing an
+    /// `anyhow::Error` up with `?` involves no conversion, so sites after the
+    /// first are recorded by calling this method, for example as
+    /// `.map_err(|e| e.propagated())?`.
+    ///
+    /// Requires Rust 1.46 or newer, which is the first to support
+    /// `#[track_caller]`.
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[track_caller]
+    pub fn propagated(mut self) -> Self {
+        let caller = core::panic::Location::caller();
+        let depth = unsafe { ErrorImpl::chain(self.inner.by_ref()) }.count();
+        let header = unsafe { ErrorImpl::header_mut(self.inner.by_mut()) };
+        header.propagate(caller, depth as u32);
+        self
+    }
+
+    /// The sites this error was propagated through, in the order they were
+    /// passed.
+    ///
+    /// Unlike [`locations()`][Error::locations], which says where each level
+    /// of the chain was created, this is the path the error took on its way
+    /// out. See [`propagated()`][Error::propagated] for which sites are
+    /// recorded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Result;
+    ///
+    /// fn parse(s: &str) -> Result<u32> {
+    ///     Ok(s.parse()?)
+    /// }
+    ///
+    /// fn load() -> Result<u32> {
+    ///     let n = parse("x").map_err(|e| e.propagated())?;
+    ///     Ok(n)
+    /// }
+    ///
+    /// let error = load().unwrap_err();
+    /// assert_eq!(error.propagations().len(), 2);
+    /// for site in error.propagations() {
+    ///     eprintln!("  via {}", site);
+    /// }
+    /// ```
+    pub fn propagations(&self) -> Vec<Location> {
+        #[cfg(not(anyhow_no_track_caller))]
+        return unsafe { ErrorImpl::header(self.inner.by_ref()) }
+            .propagation()
+            .map(|site| Location::of(site.location))
+            .collect();
+        #[cfg(anyhow_no_track_caller)]
+        return Vec::new();
+    }
+}
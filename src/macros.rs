@@ -50,16 +50,62 @@
 /// #     Ok(())
 /// # }
 /// ```
+#[cfg(not(any(feature = "compat", feature = "structured-location")))]
 #[macro_export]
 macro_rules! bail {
+    ($($tt:tt)*) => {
+        $crate::bail_located!($($tt)*)
+    };
+}
+
+/// Return early with an error.
+///
+/// With the "structured-location" feature enabled this is
+/// `return Err(`[`anyhow!($args...)`][crate::anyhow!]`)`, which keeps the
+/// location of a message next to it rather than in it.
+#[cfg(all(feature = "structured-location", not(feature = "compat")))]
+#[macro_export]
+macro_rules! bail {
+    ($($tt:tt)*) => {
+        return $crate::private::Err($crate::anyhow!($($tt)*))
+    };
+}
+
+/// Return early with an error.
+///
+/// With the "compat" feature enabled this is upstream anyhow's `bail!`,
+/// equivalent to `return Err(`[`anyhow_basic!($args...)`][anyhow_basic!]`)`.
+/// The located behavior remains available as [`bail_located!`].
+#[cfg(feature = "compat")]
+#[macro_export]
+macro_rules! bail {
+    ($($tt:tt)*) => {
+        return $crate::private::Err($crate::anyhow_basic!($($tt)*))
+    };
+}
+
+/// Return early with a located error.
+///
+/// This is what [`bail!`] expands to unless the "compat" feature is enabled,
+/// and it is available under this name regardless of features.
+///
+/// The error is tagged with the file and line of the `bail!` or
+/// `bail_located!` call itself, in every form including `bail!(error)`:
+/// `file!()` and `line!()` expand to the location of the outermost macro
+/// call, not to somewhere inside this crate's macros.
+#[macro_export]
+macro_rules! bail_located {
     ($msg:literal $(,)?) => {
-        return $crate::private::Err($crate::anyhow!($msg))
+        return $crate::private::Err($crate::anyhow_located!($msg))
+    };
+    ($msg:literal @ $location:expr $(,)?) => {
+        return $crate::private::Err($crate::anyhow_located!($msg @ $location))
     };
     ($err:expr $(,)?) => {
-        return $crate::private::Err($crate::anyhow!($err))
+        return $crate::private::Err($crate::anyhow_located!($err))
     };
     ($fmt:expr, $($arg:tt)*) => {
-        return $crate::private::Err($crate::anyhow!($fmt, $($arg)*))
+        return $crate::private::Err($crate::anyhow_located!($fmt, $($arg)*))
     };
 }
 
@@ -111,6 +157,40 @@ macro_rules! bail {
 /// #     Ok(())
 /// # }
 /// ```
+#[cfg(not(any(feature = "compat", feature = "structured-location")))]
+#[macro_export]
+macro_rules! ensure {
+    ($($tt:tt)*) => {
+        $crate::ensure_located!($($tt)*)
+    };
+}
+
+/// Return early with an error if a condition is not satisfied.
+///
+/// With the "structured-location" feature enabled, a message or error after
+/// the condition is made into an error by [`anyhow!`][crate::anyhow!], which
+/// keeps the location of a message next to it rather than in it. Without
+/// one, this is the same as [`ensure_located!`][crate::ensure_located!].
+#[cfg(all(feature = "structured-location", not(feature = "compat")))]
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow!($($arg)+));
+        }
+    };
+    ($($cond:tt)+) => {
+        $crate::ensure_located!($($cond)+)
+    };
+}
+
+/// Return early with an error if a condition is not satisfied.
+///
+/// With the "compat" feature enabled this is upstream anyhow's `ensure!`,
+/// equivalent to `if !$cond { return
+/// Err(`[`anyhow_basic!($args...)`][anyhow_basic!]`); }`. The located
+/// behavior remains available as [`ensure_located!`].
+#[cfg(feature = "compat")]
 #[macro_export]
 macro_rules! ensure {
     ($cond:expr $(,)?) => {
@@ -120,21 +200,462 @@ macro_rules! ensure {
             ));
         }
     };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow_basic!($($arg)+));
+        }
+    };
+}
+
+/// Return early with a located error if a condition is not satisfied.
+///
+/// This is what [`ensure!`] expands to unless the "compat" feature is
+/// enabled, and it is available under this name regardless of features.
+///
+/// Without a message, a condition comparing two operands with `==` or `!=`
+/// reports their values as [`ensure_eq!`] does, provided both implement
+/// `Debug`: ``Condition failed: `a == b` (left: `1`, right: `2`)``. Other
+/// conditions, and operands without a `Debug` impl, are reported as the
+/// condition alone. The `Condition failed: ` in front can be replaced with
+/// [`set_condition_prefix`][crate::set_condition_prefix].
+#[macro_export]
+macro_rules! ensure_located {
     ($cond:expr, $msg:literal $(,)?) => {
         if !$cond {
-            return $crate::private::Err($crate::anyhow!($msg));
+            return $crate::private::Err($crate::anyhow_located!($msg));
+        }
+    };
+    ($cond:expr, $msg:literal @ $location:expr $(,)?) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow_located!($msg @ $location));
         }
     };
     ($cond:expr, $err:expr $(,)?) => {
         if !$cond {
-            return $crate::private::Err($crate::anyhow!($err));
+            return $crate::private::Err($crate::anyhow_located!($err));
         }
     };
     ($cond:expr, $fmt:expr, $($arg:tt)*) => {
         if !$cond {
-            return $crate::private::Err($crate::anyhow!($fmt, $($arg)*));
+            return $crate::private::Err($crate::anyhow_located!($fmt, $($arg)*));
         }
     };
+    ($($cond:tt)+) => {
+        $crate::__anyhow_ensure!(@scan ($($cond)+) [] [] [] $($cond)+)
+    };
+}
+
+// Splits the condition of a bare ensure_located! at a top-level `==` or `!=`,
+// one token at a time. Conditions with `&&` or `||` at the top level, which
+// bind more loosely than the comparison, and conditions without exactly one
+// comparison are handed back whole.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_ensure {
+    (@plain $cond:expr $(,)?) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow_located!(
+                "{}",
+                $crate::private::ensure::Failed {
+                    condition: $crate::private::stringify!($cond),
+                    left: $crate::private::None,
+                    right: $crate::private::None,
+                },
+            ));
+        }
+    };
+    (@scan ($($cond:tt)*) $left:tt $op:tt $right:tt && $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@plain $($cond)*)
+    };
+    (@scan ($($cond:tt)*) $left:tt $op:tt $right:tt || $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@plain $($cond)*)
+    };
+    (@scan $cond:tt [$($left:tt)+] [] [] == $($rest:tt)+) => {
+        $crate::__anyhow_ensure!(@scan $cond [$($left)+] [==] [] $($rest)+)
+    };
+    (@scan $cond:tt [$($left:tt)+] [] [] != $($rest:tt)+) => {
+        $crate::__anyhow_ensure!(@scan $cond [$($left)+] [!=] [] $($rest)+)
+    };
+    (@scan ($($cond:tt)*) $left:tt [$op:tt] $right:tt == $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@plain $($cond)*)
+    };
+    (@scan ($($cond:tt)*) $left:tt [$op:tt] $right:tt != $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@plain $($cond)*)
+    };
+    (@scan $cond:tt [$($left:tt)+] [$op:tt] [$($right:tt)+] $(,)?) => {
+        match (&($($left)+), &($($right)+)) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    #[allow(unused_imports)]
+                    use $crate::private::ensure::{DebugOperand, PlainOperand};
+                    return $crate::private::Err($crate::anyhow_located!(
+                        "{}",
+                        $crate::private::ensure::Failed {
+                            condition: $crate::private::stringify!($($left)+ $op $($right)+),
+                            left: (&$crate::private::ensure::Operand(left)).anyhow_operand(),
+                            right: (&$crate::private::ensure::Operand(right)).anyhow_operand(),
+                        },
+                    ));
+                }
+            }
+        }
+    };
+    (@scan $cond:tt [$($left:tt)*] [] [] $next:tt $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@scan $cond [$($left)* $next] [] [] $($rest)*)
+    };
+    (@scan $cond:tt $left:tt [$op:tt] [$($right:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@scan $cond $left [$op] [$($right)* $next] $($rest)*)
+    };
+    (@scan ($($cond:tt)*) $($rest:tt)*) => {
+        $crate::__anyhow_ensure!(@plain $($cond)*)
+    };
+}
+
+/// Return early with a located error if a condition is not satisfied,
+/// describing the failure with a lazily computed detail.
+///
+/// The message is the condition as written, as in `ensure!($cond)`, followed
+/// by the `Display` of whatever the closure returns:
+/// ``Condition failed: `$cond`: detail``. The closure is only called if the
+/// condition does not hold, so the detail may be costly to produce.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_ctx, Result};
+/// #
+/// fn check_sorted(values: &[u32]) -> Result<()> {
+///     ensure_ctx!(values.windows(2).all(|w| w[0] <= w[1]), || {
+///         format!("got {:?}", values)
+///     });
+///     Ok(())
+/// }
+/// #
+/// # assert!(check_sorted(&[1, 2, 3]).is_ok());
+/// # let error = check_sorted(&[2, 1]).unwrap_err();
+/// # assert!(error.to_string().ends_with("emsg(Condition failed: `values.windows(2).all(|w| w[0] <= w[1])`: got [2, 1])]"));
+/// ```
+#[macro_export]
+macro_rules! ensure_ctx {
+    ($cond:expr, $detail:expr $(,)?) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow_located!(
+                "{}: {}",
+                $crate::private::ensure::Failed {
+                    condition: $crate::private::stringify!($cond),
+                    left: $crate::private::None,
+                    right: $crate::private::None,
+                },
+                ($detail)(),
+            ));
+        }
+    };
+}
+
+/// Return early with a located error if a condition is not satisfied, with a
+/// lazily computed message.
+///
+/// The closure is only called if the condition does not hold, the way
+/// [`with_context`][crate::Context::with_context] only calls its closure on
+/// an error, and the `Display` of what it returns is the whole message,
+/// tagged with the location of the `ensure_with!` call. Use [`ensure_ctx!`]
+/// to keep the condition in the message.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_with, Result};
+/// #
+/// # #[derive(Debug)]
+/// # struct Table {
+/// #     rows: Vec<u32>,
+/// # }
+/// #
+/// fn check_table(table: &Table) -> Result<()> {
+///     ensure_with!(!table.rows.is_empty(), || format!("empty table: {:#?}", table));
+///     Ok(())
+/// }
+/// #
+/// # assert!(check_table(&Table { rows: vec![1] }).is_ok());
+/// # let error = check_table(&Table { rows: vec![] }).unwrap_err();
+/// # assert!(error.to_string().contains("emsg(empty table: Table {"));
+/// ```
+#[macro_export]
+macro_rules! ensure_with {
+    ($cond:expr, $msg:expr $(,)?) => {
+        if !$cond {
+            return $crate::private::Err($crate::anyhow_located!("{}", ($msg)()));
+        }
+    };
+}
+
+/// Return early with a located error if two expressions are not equal.
+///
+/// Like `assert_eq!`, but returning an error instead of panicking. The
+/// operands are evaluated once each and must implement `PartialEq` and
+/// `Debug`. Without a message the error reads ``Condition failed: `$left ==
+/// $right` (left: `..`, right: `..`)``; with one, the message takes the place
+/// of the condition and is followed by the two values. The message arms are
+/// the same as those of [`ensure!`].
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_eq, Result};
+/// #
+/// fn check_header(magic: &[u8]) -> Result<()> {
+///     ensure_eq!(magic, b"\x7fELF", "not an ELF file");
+///     Ok(())
+/// }
+/// #
+/// # assert!(check_header(b"\x7fELF").is_ok());
+/// # let error = check_header(b"MZ").unwrap_err();
+/// # assert!(error.to_string().ends_with("emsg(not an ELF file (left: `[77, 90]`, right: `[127, 69, 76, 70]`))]"));
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__anyhow_ensure_cmp!(==, $left, $right)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__anyhow_ensure_cmp!(==, $left, $right, $($arg)+)
+    };
+}
+
+/// Return early with a located error if two expressions are equal.
+///
+/// The counterpart of [`ensure_eq!`], failing when `$left == $right`.
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__anyhow_ensure_cmp!(!=, $left, $right)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__anyhow_ensure_cmp!(!=, $left, $right, $($arg)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_ensure_cmp {
+    (@message $op:tt, $left:expr, $right:expr, $message:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    return $crate::private::Err($crate::anyhow_located!(
+                        "{} (left: `{:?}`, right: `{:?}`)",
+                        $message,
+                        left,
+                        right,
+                    ));
+                }
+            }
+        }
+    };
+    ($op:tt, $left:expr, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    return $crate::private::Err($crate::anyhow_located!(
+                        "{}",
+                        $crate::private::ensure::Failed {
+                            condition: $crate::private::concat!(
+                                $crate::private::stringify!($left),
+                                " ",
+                                $crate::private::stringify!($op),
+                                " ",
+                                $crate::private::stringify!($right),
+                            ),
+                            left: $crate::private::Some(left),
+                            right: $crate::private::Some(right),
+                        },
+                    ));
+                }
+            }
+        }
+    };
+    ($op:tt, $left:expr, $right:expr, $msg:literal $(,)?) => {
+        $crate::__anyhow_ensure_cmp!(@message $op, $left, $right, $crate::private::format_args!($msg))
+    };
+    ($op:tt, $left:expr, $right:expr, $err:expr $(,)?) => {
+        $crate::__anyhow_ensure_cmp!(@message $op, $left, $right, $err)
+    };
+    ($op:tt, $left:expr, $right:expr, $fmt:expr, $($arg:tt)*) => {
+        $crate::__anyhow_ensure_cmp!(@message $op, $left, $right, $crate::private::format_args!($fmt, $($arg)*))
+    };
+}
+
+/// Return early with an error, recording the failure in a metric.
+///
+/// This macro is equivalent to `bail!($args...)` preceded by one call to
+/// [`Counter::increment`][crate::Counter::increment] on `$counter`. The counter
+/// may be any [`Counter`][crate::Counter], including a `FnMut()` closure; a
+/// named counter is borrowed mutably, so the binding needs to be `mut`.
+///
+/// With the `log-integration` feature the failure is also logged at the error
+/// level, as [`anyhow_error!`] logs its message.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{fail_metric, Result};
+/// #
+/// struct Failures(u64);
+///
+/// impl anyhow::Counter for Failures {
+///     fn increment(&mut self) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// fn parse_port(s: &str, failures: &mut Failures) -> Result<u16> {
+///     match s.parse() {
+///         Ok(port) => Ok(port),
+///         Err(_) => fail_metric!(failures, "invalid port {:?}", s),
+///     }
+/// }
+/// #
+/// # let mut failures = Failures(0);
+/// # assert!(parse_port("80", &mut failures).is_ok());
+/// # assert!(parse_port("eighty", &mut failures).is_err());
+/// # assert_eq!(failures.0, 1);
+/// ```
+#[macro_export]
+macro_rules! fail_metric {
+    ($counter:expr, $($arg:tt)+) => {{
+        use $crate::Counter as _;
+        ($counter).increment();
+        return $crate::private::Err($crate::private::log_failure(module_path!(), $crate::__anyhow_site!(), line!(), $crate::anyhow_located!($($arg)+)));
+    }};
+}
+
+/// Unwrap a `Result`, panicking with the full error report if it is an `Err`.
+///
+/// This is meant for tests and their setup code, in place of `.unwrap()`. The
+/// panic message names the location of the `must!` and then gives the error
+/// in its `{:?}` representation, with every cause and the location tags they
+/// carry, so a failure points both at the line of the test that failed and
+/// at where the error came from. The error may be an `anyhow::Error` or any
+/// type that converts into one.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{must, Result};
+/// #
+/// fn load_fixture() -> Result<String> {
+///     Ok("{}".to_owned())
+/// }
+///
+/// let fixture = must!(load_fixture());
+/// let port: u16 = must!("8080".parse());
+/// # assert_eq!((fixture.as_str(), port), ("{}", 8080));
+/// ```
+#[macro_export]
+macro_rules! must {
+    ($result:expr $(,)?) => {
+        match $result {
+            $crate::private::Ok(value) => value,
+            $crate::private::Err(error) => {
+                let error = $crate::Error::from(error);
+                panic!("must! failed at {}:{}: {:?}", file!(), line!(), error)
+            }
+        }
+    };
+}
+
+/// Assert that a `Result` is an error whose outermost frame is tagged with
+/// the given line, for tests of where errors come from.
+///
+/// The error is unwrapped from the `Err` and converted into an
+/// `anyhow::Error` as by [`must!`], and the first frame of its
+/// [`parsed_frames()`][crate::Error::parsed_frames] must carry a location
+/// on `line`; the file is not checked. Otherwise, or if the result is `Ok`,
+/// the macro panics, listing every frame of the error with its location to
+/// show where the error came from instead.
+///
+/// Requires the "testing" feature, which is meant to be enabled for tests
+/// only, as in `[dev-dependencies]`.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "testing")]
+/// # {
+/// use anyhow::{anyhow, assert_error_at, Result};
+///
+/// fn load() -> Result<()> {
+///     Err(anyhow!("no config"))
+/// }
+///
+/// assert_error_at!(load(), line!() - 3);
+/// # }
+/// ```
+#[cfg(feature = "testing")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "testing")))]
+#[macro_export]
+macro_rules! assert_error_at {
+    ($result:expr, $line:expr $(,)?) => {
+        match $result {
+            $crate::private::Ok(_) => {
+                panic!(
+                    "assert_error_at! expected an error from line {}, got Ok",
+                    $line
+                )
+            }
+            $crate::private::Err(error) => {
+                let error = $crate::Error::from(error);
+                if let $crate::private::Err(message) = $crate::private::check_line(&error, $line) {
+                    panic!("assert_error_at! failed: {}", message)
+                }
+            }
+        }
+    };
+}
+
+/// Run a sequence of fallible steps, labeling a failure with the step's name.
+///
+/// Each step is written `name => expression;` where the expression usually
+/// ends in `?`. If the step fails, its error gets the name attached as located
+/// context, exactly as `.map_err(anyhow_context!(name))` would, and is then
+/// returned from the surrounding function. The location recorded is that of
+/// the `?` inside the failing step. Values produced by the steps are
+/// discarded.
+///
+/// The surrounding function's or closure's return value is required to be
+/// `Result<_,`[`anyhow::Error`][crate::Error]`>`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{steps, Result};
+/// #
+/// # struct Session;
+/// #
+/// # fn connect() -> Result<()> { Ok(()) }
+/// # fn authenticate() -> Result<()> { Ok(()) }
+/// # fn fetch() -> Result<()> { Ok(()) }
+/// #
+/// fn sync() -> Result<()> {
+///     steps! {
+///         "connect" => connect()?;
+///         "authenticate" => authenticate()?;
+///         "fetch" => fetch()?;
+///     }
+///     Ok(())
+/// }
+/// #
+/// # sync().unwrap();
+/// ```
+#[macro_export]
+macro_rules! steps {
+    ($($name:expr => $step:expr);* $(;)?) => {
+        $(
+            $crate::private::step($name, file!(), line!(), || -> $crate::private::Result<_, $crate::private::StepError> {
+                $crate::private::Ok($step)
+            })?;
+        )*
+    };
 }
 
 /// Construct an ad-hoc error from a string or existing non-`anyhow` error
@@ -165,39 +686,321 @@ macro_rules! ensure {
 ///     # Ok(())
 /// }
 /// ```
+#[cfg(not(any(feature = "compat", feature = "structured-location")))]
+#[macro_export]
+macro_rules! anyhow {
+    ($($tt:tt)*) => {
+        $crate::anyhow_located!($($tt)*)
+    };
+}
+
+/// Construct an ad-hoc error from a string or existing non-`anyhow` error
+/// value.
+///
+/// With the "structured-location" feature enabled, a message is kept as it
+/// is, and the file, line and column of the call are stored next to it the
+/// way [`Error::msg_at`][crate::Error::msg_at] stores them, instead of being
+/// formatted into a `[file:line emsg(...)]` tag. `Display` shows the message
+/// alone, [`location()`][crate::Error::location] reports the location, and
+/// `{:?}` renders the tag for developers. Given an error value, or no
+/// arguments, this is the same as [`anyhow_located!`][crate::anyhow_located!].
+#[cfg(all(feature = "structured-location", not(feature = "compat")))]
 #[macro_export]
 macro_rules! anyhow {
+    ($msg:literal $(,)?) => {
+        $crate::private::message_at($crate::private::format_args!($msg), file!(), line!(), column!())
+    };
+    ($msg:literal @ $location:expr $(,)?) => {
+        $crate::__anyhow_message_at!(@at [$msg] @ $location)
+    };
+    ($err:expr $(,)?) => {
+        $crate::anyhow_located!($err)
+    };
+    ($fmt:expr, $($arg:tt)*) => {
+        $crate::__anyhow_message_at!(@scan [$fmt,] $($arg)*)
+    };
+    () => {
+        $crate::anyhow_located!()
+    };
+}
+
+// The format arguments of anyhow! with "structured-location", split from a
+// trailing `@ location` as by __anyhow_located_at!.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_message_at {
+    (@scan [$($fmt:tt)*] @ $location:expr $(,)?) => {
+        $crate::__anyhow_message_at!(@at [$($fmt)*] @ $location)
+    };
+    (@scan [$($fmt:tt)*]) => {
+        $crate::private::message_at($crate::private::format_args!($($fmt)*), file!(), line!(), column!())
+    };
+    (@scan [$($fmt:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__anyhow_message_at!(@scan [$($fmt)* $next] $($rest)*)
+    };
+    (@at [$($fmt:tt)*] @ $location:expr) => {
+        match $location {
+            location => $crate::private::message_at(
+                $crate::private::format_args!($($fmt)*),
+                location.file(),
+                location.line(),
+                location.column(),
+            ),
+        }
+    };
+}
+
+/// Construct an ad-hoc error from a string or existing non-`anyhow` error
+/// value.
+///
+/// With the "compat" feature enabled this is upstream anyhow's `anyhow!`,
+/// the same as [`anyhow_basic!`]: no location tag is added, and an argument
+/// implementing `std::error::Error` keeps its source chain, backtrace and
+/// type for downcasting. The located behavior remains available as
+/// [`anyhow_located!`].
+#[cfg(feature = "compat")]
+#[macro_export]
+macro_rules! anyhow {
+    ($($tt:tt)*) => {
+        $crate::anyhow_basic!($($tt)*)
+    };
+}
+
+/// Construct an ad-hoc error tagged with the file and line of the call.
+///
+/// This is what [`anyhow!`] expands to unless the "compat" feature is
+/// enabled, and it is available under this name regardless of features.
+///
+/// Without arguments, `anyhow_located!()` uses the `module_path!()` of the
+/// call as the message.
+///
+/// A message with arguments must be a string literal, as for `format!`, and
+/// is checked against the arguments when the call is compiled: a `{}`
+/// without an argument, or an argument without a `{}`, is a compile error
+/// rather than a malformed tag at runtime.
+///
+/// Given an error, `anyhow_located!(error)` keeps it as it is and adds the
+/// tag on top of it as context, with the error's `Display` as the message.
+/// The result can still be downcast to the error's type, and its source
+/// chain is kept.
+///
+/// Given a value whose `Display` already starts with a location tag, such as
+/// an error made by this macro, `anyhow_located!(value)` does not wrap it in
+/// a second tag. The new tag gets an empty message and is put in front of
+/// the value, joined by the [line separator][crate::line_separator], so that
+/// both locations are reported as separate frames.
+///
+/// A message may be followed by `@ location`, with `location` a
+/// `&core::panic::Location`, to tag the error with that location in place of
+/// the call's: `anyhow_located!("no user {}", id @ caller)`. In a helper
+/// marked `#[track_caller]` that makes or forwards errors on behalf of its
+/// caller, `Location::caller()` attributes the error to the caller's line.
+/// `bail_located!` and `ensure_located!` take the same trailing argument
+/// after their message. The tag has no module path even with the
+/// "module-path" feature, as the location does not record one. For the
+/// closures of `anyhow_error!` and `anyhow_context!`, see [`map_err_at`]
+/// and [`context_at`].
+///
+/// [`map_err_at`]: crate::map_err_at
+/// [`context_at`]: crate::context_at
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow_located, Error};
+/// use std::panic::Location;
+///
+/// #[track_caller]
+/// fn missing(key: &str) -> Error {
+///     anyhow_located!("missing key {}", key @ Location::caller())
+/// }
+///
+/// let error = missing("port");
+/// let line = line!() - 1;
+/// assert!(error.to_string().ends_with(&format!(":{}:13 emsg(missing key port)]", line)));
+/// ```
+#[macro_export]
+macro_rules! anyhow_located {
     ($msg:literal $(,)?) => {
         // Handle $:literal as a special case to make cargo-expanded code more
         // concise in the common case.
         //$crate::Error::msg($msg)
         //$crate::Error::msg($crate::private::format!("@@@{}:{} {}", file!(), line!(), $msg))
-        $crate::Error::msg($crate::private::format!("[{}:{} emsg({})]", file!(), line!(), $crate::private::format!($msg)))
+        //
+        // The literal is formatted in place by format_args! rather than into
+        // a String of its own, so the tagged message costs a single
+        // allocation and formatting pass.
+        $crate::Error::msg($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), "", &$crate::private::format_args!($msg)))
+    };
+    ($msg:literal @ $location:expr $(,)?) => {
+        $crate::__anyhow_located_at!(@tag [$msg] @ $location)
     };
     ($err:expr $(,)?) => ({
         use $crate::private::kind::*;
         match $err {
             //error => (&error).anyhow_kind().new(error),
-            error => $crate::Error::msg($crate::private::format!("[{}:{} emsg({})]", file!(), line!(), error))
+            //
+            // An error keeps its type and source chain under the tag, which is
+            // added as context; see private::kind for the exceptions.
+            error => (&error).anyhow_kind().located(error, $crate::__anyhow_site!(), line!(), column!()),
         }
     });
 
     ($fmt:expr, $($arg:tt)*) => {
         //$crate::Error::msg($crate::private::format!($fmt, $($arg)*))
         //$crate::Error::msg($crate::private::format!($crate::private::concat!("@@@{}:{} ", $fmt), file!(), line!(), $($arg)*))
-        $crate::Error::msg($crate::private::format!("[{}:{} emsg({})]", file!(), line!(), $crate::private::format!($fmt, $($arg)*)))
+        //
+        // The arguments are scanned for a trailing `@ location`.
+        $crate::__anyhow_located_at!(@scan [$fmt,] $($arg)*)
     };
+    // Function names are not available to macros, so the module path stands
+    // in for a message.
     () => {
-        $crate::Error::msg($crate::private::format!("[{}:{} emsg()]", file!(), line!()))
+        $crate::__anyhow_no_message!($crate::Error::msg($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), "", &module_path!())))
+    };
+}
+
+// The format arguments of anyhow_located!, split from a trailing
+// `@ location` if they end with one. The tag of a location given this way has
+// its file, line and column, and no module path.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_located_at {
+    (@scan [$($fmt:tt)*] @ $location:expr $(,)?) => {
+        $crate::__anyhow_located_at!(@tag [$($fmt)*] @ $location)
+    };
+    (@scan [$($fmt:tt)*]) => {
+        $crate::Error::msg($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), "", &$crate::private::format_args!($($fmt)*)))
+    };
+    (@scan [$($fmt:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__anyhow_located_at!(@scan [$($fmt)* $next] $($rest)*)
+    };
+    (@tag [$($fmt:tt)*] @ $location:expr) => {
+        match $location {
+            location => $crate::Error::msg($crate::private::tag(
+                $crate::__anyhow_site_of!(location),
+                location.line(),
+                location.column(),
+                "",
+                &$crate::private::format_args!($($fmt)*),
+            )),
+        }
+    };
+}
+
+/// Construct a located ad-hoc error carrying a machine-readable code.
+///
+/// The message is tagged like that of [`anyhow_located!`], with the code
+/// after the location: `[file:line:column code(E1042) emsg(message)]`. The
+/// code must be a `&'static str`, and is also attached to the error, to be
+/// read back with [`Error::code`][crate::Error::code] for as long as the
+/// error keeps its attachments, so callers can branch on it without
+/// downcasting. The second argument is a message, or a format string and its
+/// arguments.
+///
+/// The parsers that read locations out of messages skip over the code:
+/// [`location()`][crate::Error::location] and
+/// [`parse_frames`][crate::parse_frames] report the file, line and message
+/// of the tag as usual, and the code appears in neither.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_code;
+///
+/// let error = anyhow_code!("E1042", "no user {}", 7);
+/// assert_eq!(error.code(), Some("E1042"));
+/// assert!(error.to_string().ends_with(" code(E1042) emsg(no user 7)]"));
+/// assert_eq!(error.location().unwrap().file(), file!());
+/// ```
+#[macro_export]
+macro_rules! anyhow_code {
+    ($code:expr, $msg:literal $(,)?) => {
+        $crate::private::coded($code, $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($msg))
+    };
+    ($code:expr, $fmt:expr, $($arg:tt)+) => {
+        $crate::private::coded($code, $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($fmt, $($arg)+))
+    };
+    ($code:expr, $msg:expr $(,)?) => {
+        $crate::private::coded($code, $crate::__anyhow_site!(), line!(), column!(), &$msg)
+    };
+}
+
+/// Construct a located ad-hoc error carrying structured key-value pairs,
+/// for fields such as a request id that are read back out of the error
+/// later with [`Error::kv`][crate::Error::kv].
+///
+/// The message is a literal, formatted as by `anyhow_located!("literal")`,
+/// followed by any number of `key = value` pairs with `key` an identifier
+/// and `value` any expression implementing `Display`. The pairs go in the
+/// location tag after the message:
+///
+/// ```text
+/// [src/main.rs:12:17 emsg(upstream timed out) kv(request_id=req-7,retry=2)]
+/// ```
+///
+/// In a value, `%`, `)`, `,` and control characters are written as `%`
+/// followed by the two hex digits of the character, such as `%29` for `)`
+/// and `%2C` for `,`, so that a value never ends the pair or the tag early.
+/// [`Error::kv`][crate::Error::kv] decodes them again. The
+/// [`emsg()`][crate::Error::emsg] of the error and the messages of its
+/// frames do not include the pairs. With a custom
+/// [tag format][crate::set_tag_format] the pairs are left out.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_kv;
+///
+/// let (id, retry) = ("req-7", 2);
+/// let error = anyhow_kv!("upstream timed out", request_id = id, retry = retry);
+///
+/// assert!(error.to_string().ends_with(" emsg(upstream timed out) kv(request_id=req-7,retry=2)]"));
+/// assert_eq!(error.kv("retry"), Some("2"));
+/// assert_eq!(error.emsg(), Some("upstream timed out"));
+/// ```
+#[macro_export]
+macro_rules! anyhow_kv {
+    ($msg:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::Error::msg($crate::private::tag_kv($crate::__anyhow_site!(), line!(), column!(), "", &$crate::private::format_args!($msg), &[$(($crate::private::stringify!($key), &$value)),*]))
     };
 }
 
+/// Construct an ad-hoc error from a string or existing non-`anyhow` error
+/// value, without a location tag.
+///
+/// This is upstream anyhow's `anyhow!`, available under this name regardless
+/// of features, and it is kept as a permanent part of the API for code that
+/// wants plain errors while [`anyhow!`] stays located. It is also available
+/// as `format_err_basic!`.
+///
+/// - `anyhow_basic!("literal")` and `anyhow_basic!("fmt {}", args)` make an
+///   ad-hoc error with the formatted message.
+/// - `anyhow_basic!(value)` keeps an argument implementing `std::error::Error`
+///   as it is, with its source chain and backtrace, so the result can be
+///   downcast to the argument's type. Any other `Display + Debug` value
+///   becomes the message, and an `anyhow::Error` is passed through.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_basic;
+/// use std::io;
+///
+/// let error = anyhow_basic!("no user {}", 7);
+/// assert_eq!(error.to_string(), "no user 7");
+///
+/// let error = anyhow_basic!(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+/// assert_eq!(error.to_string(), "no such file");
+/// assert!(error.is::<io::Error>());
+/// ```
 #[macro_export]
 macro_rules! anyhow_basic {
     ($msg:literal $(,)?) => {
         // Handle $:literal as a special case to make cargo-expanded code more
-        // concise in the common case.
-        $crate::Error::msg($msg)
+        // concise in the common case. A literal with nothing to interpolate
+        // is kept as a &'static str, as upstream keeps it.
+        $crate::private::format_err($crate::private::format_args!($msg))
     };
     ($err:expr $(,)?) => ({
         use $crate::private::kind::*;
@@ -213,50 +1016,491 @@ macro_rules! anyhow_basic {
     };
 }
 
+/// Construct an ad-hoc error from a string or existing non-`anyhow` error
+/// value, without a location tag.
+///
+/// This is upstream anyhow's other name for its `anyhow!`, and as upstream
+/// it adds no location tag, whatever the features: it expands to
+/// [`anyhow_basic!`] rather than to the located [`anyhow!`], so code written
+/// against upstream's `format_err!` keeps its messages as they are.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::format_err;
+///
+/// let error = format_err!("no user {}", 7);
+/// assert_eq!(error.to_string(), "no user 7");
+/// ```
+#[macro_export]
+macro_rules! format_err {
+    ($($tt:tt)*) => {
+        $crate::anyhow_basic!($($tt)*)
+    };
+}
+
+/// Build a closure for `.map_err(...)` that turns any `Debug` error into an
+/// `anyhow::Error` with a located message.
+///
+/// The message is tagged with the location of the call,
+/// `[file:line:column, emsg(...)]`, and followed by the
+/// [line separator][crate::line_separator] and the `{:?}` of the error. It
+/// is given as a literal, as format arguments, or as any `Display` value.
+///
+/// # Threads
+///
+/// The closure never takes ownership of what it formats: a value or format
+/// argument is borrowed from the surrounding code, and nothing is formatted
+/// until the closure is called. The closure is therefore `Send` and `Sync`
+/// exactly when the borrowed values are `Sync`, as is the closure of the
+/// literal form, which borrows nothing. A value that is not `Sync`, such as
+/// an `Rc<str>`, can be formatted into a `String` first for a closure that
+/// is to cross threads.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_error;
+/// use std::thread;
+///
+/// let path = "/nonexistent/config.toml";
+/// let worker = thread::spawn(move || {
+///     std::fs::read_to_string(path).map_err(anyhow_error!("reading {}", path))
+/// });
+///
+/// let error = worker.join().unwrap().unwrap_err();
+/// assert!(error.to_string().contains(", emsg(reading /nonexistent/config.toml)]"));
+/// ```
 #[macro_export]
 macro_rules! anyhow_error {
     // .map_err(anyhow_error!("some static msg"))
     ($msg:literal $(,)?) => {
-        |e| ::anyhow::anyhow_basic!("[{}:{}, emsg({})]{}{:?}", file!(), line!(), $msg, anyhow::END_OF_LINE, e)
+        |e| {
+            use $crate::private::kind::*;
+            (&e).wrap_kind().wrap(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($msg), e)
+        }
     };
 
     // .map_err(anyhow_error!("some format {}", value))
     ($fmt:expr, $($arg:tt)+) => {
-        |e| ::anyhow::anyhow_basic!("[{}:{}, emsg({})]{}{:?}", file!(), line!(), format!($fmt, $($arg)+), anyhow::END_OF_LINE, e)
+        |e| {
+            use $crate::private::kind::*;
+            (&e).wrap_kind().wrap(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($fmt, $($arg)+), e)
+        }
     };
 
     // anyhow_error!(err)
+    //
+    // err is only formatted, so the closure borrows it rather than moving it
+    // in, and the same value can be used by several anyhow_error! calls and
+    // after them.
     ($err:expr $(,)?) => {
         |e| {
-        let err = $err;
-        ::anyhow::anyhow_basic!("[{}:{}, emsg({})]{}{:?}", file!(), line!(), err, anyhow::END_OF_LINE,e)
-    }};
+            use $crate::private::kind::*;
+            (&e).wrap_kind().wrap(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$err, e)
+        }
+    };
     // anyhow_error!()
     () => {
-        |e| ::anyhow::anyhow_basic!("[{}:{}, emsg()]{}{:?}", file!(), line!(), anyhow::END_OF_LINE, e)
+        $crate::__anyhow_no_message!(|e| {
+            use $crate::private::kind::*;
+            (&e).wrap_kind().wrap(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &"", e)
+        })
+    };
+}
+
+/// Like [`anyhow_error!`], with the `{}` of the error in place of its `{:?}`.
+///
+/// Some errors have a Debug representation far bigger than their message,
+/// such as a deserialization error that dumps the whole value it failed on.
+/// The closure of this macro follows the location tag and the
+/// [line separator][crate::line_separator] with only the error's `Display`,
+/// to keep such errors from bloating every level above them. The arms are
+/// those of `anyhow_error!`, and the error only needs to implement
+/// `Display`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_error_brief;
+///
+/// let error = "12a".parse::<u32>().map_err(anyhow_error_brief!("bad port")).unwrap_err();
+/// let message = error.to_string();
+/// assert!(message.contains(", emsg(bad port)]"));
+/// assert!(message.ends_with("invalid digit found in string"));
+/// ```
+#[macro_export]
+macro_rules! anyhow_error_brief {
+    // .map_err(anyhow_error_brief!("some static msg"))
+    ($msg:literal $(,)?) => {
+        |e| $crate::private::wrap_display(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($msg), &e)
+    };
+
+    // .map_err(anyhow_error_brief!("some format {}", value))
+    ($fmt:expr, $($arg:tt)+) => {
+        |e| $crate::private::wrap_display(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$crate::private::format_args!($fmt, $($arg)+), &e)
+    };
+
+    // anyhow_error_brief!(err)
+    ($err:expr $(,)?) => {
+        |e| $crate::private::wrap_display(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &$err, &e)
+    };
+    // anyhow_error_brief!()
+    () => {
+        $crate::__anyhow_no_message!(|e| $crate::private::wrap_display(module_path!(), $crate::__anyhow_site!(), line!(), column!(), &"", &e))
+    };
+}
+
+/// Build a closure for `.map_err(...)` that tags an error with its location
+/// the way [`anyhow_error!`] does, while keeping the error itself.
+///
+/// `anyhow_error!` formats the error it is given into a new message, which
+/// loses its type and its source chain. This macro instead adds the same
+/// `[file:line, emsg(...)]` message as context on top of the error, so the
+/// error stays in the chain underneath: it can still be downcast to, and its
+/// sources are still reported under "Caused by:" in the `{:?}`
+/// representation. The arms are those of `anyhow_error!`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{anyhow_wrap, Result};
+/// # use std::{fs, io};
+/// #
+/// fn read_config(path: &str) -> Result<String> {
+///     let config = fs::read_to_string(path).map_err(anyhow_wrap!("reading {}", path))?;
+///     Ok(config)
+/// }
+///
+/// let error = read_config("/nonexistent").unwrap_err();
+/// assert!(error.downcast_ref::<io::Error>().is_some());
+/// ```
+#[macro_export]
+macro_rules! anyhow_wrap {
+    // .map_err(anyhow_wrap!("some static msg"))
+    ($msg:literal $(,)?) => {
+        |e| $crate::Error::from(e).context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &$crate::private::format_args!($msg)))
+    };
+
+    // .map_err(anyhow_wrap!("some format {}", value))
+    ($fmt:expr, $($arg:tt)+) => {
+        |e| $crate::Error::from(e).context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &$crate::private::format_args!($fmt, $($arg)+)))
+    };
+
+    // anyhow_wrap!(err)
+    //
+    // As with anyhow_error!(err), err is borrowed rather than moved.
+    ($err:expr $(,)?) => {
+        |e| $crate::Error::from(e).context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &$err))
+    };
+    // anyhow_wrap!()
+    () => {
+        $crate::__anyhow_no_message!(|e| $crate::Error::from(e).context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &"")))
     };
 }
 
+/// Build a closure for `.map_err(...)` that adds located context to an
+/// `anyhow::Error`.
+///
+/// The context is a message tagged with the location of the call,
+/// `[file:line:column, emsg(...)]`, and given as a literal, as format
+/// arguments, or as any `Display` value, which is borrowed rather than moved
+/// into the closure.
+///
+/// `anyhow_context!(move value)` keeps `value` itself as context so that it
+/// can be downcast, as [`Context::context`][crate::Context::context] does.
+/// The value is moved into the closure and must be `Display + Send + Sync +
+/// 'static`. It becomes a level of the chain of its own, with the tagged
+/// `Display` of the value as a level above it.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow_context, anyhow_located};
+///
+/// #[derive(Debug, thiserror::Error)]
+/// #[error("loading user {0}")]
+/// struct LoadingUser(u64);
+///
+/// let error = Err::<(), _>(anyhow_located!("no such row"))
+///     .map_err(anyhow_context!(move LoadingUser(7)))
+///     .unwrap_err();
+///
+/// assert!(error.to_string().ends_with(", emsg(loading user 7)]"));
+/// assert_eq!(error.downcast_ref::<LoadingUser>().unwrap().0, 7);
+/// ```
 #[macro_export]
 macro_rules! anyhow_context {
+    // .map_err(anyhow_context!(move context))
+    //
+    // The context is evaluated when the closure is called, and moved in.
+    (move $context:expr $(,)?) => {
+        |e| $crate::private::typed_context(e, $context, $crate::__anyhow_site!(), line!(), column!())
+    };
+
     // .map_err(anyhow_context!("some static msg"))
     ($msg:literal $(,)?) => {
-        |e| e.context(::anyhow::anyhow_basic!("[{}:{}, emsg({})]", file!(), line!(), $msg))
+        |e| e.context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &$crate::private::format_args!($msg)))
     };
 
     // .map_err(anyhow_context!("some format {}", value))
     ($fmt:expr, $($arg:tt)+) => {
-        |e| e.context(::anyhow::anyhow_basic!("[{}:{}, emsg({})]", file!(), line!(), format!($fmt, $($arg)+)))
+        |e| e.context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &$crate::private::format_args!($fmt, $($arg)+)))
     };
 
     // anyhow_context!(err)
+    //
+    // As with anyhow_located!(err), an err that is already tagged gets a
+    // frame of its own in front of it instead of being wrapped. As with
+    // anyhow_error!(err), err is borrowed rather than moved.
     ($err:expr $(,)?) => {
-        |e| {
-        let err = $err;
-        e.context(::anyhow::anyhow_basic!("[{}:{}, emsg({})]", file!(), line!(), err))
-    }};
+        |e| e.context($crate::private::tag_display($crate::__anyhow_site!(), line!(), column!(), ",", &$err))
+    };
     // anyhow_context!()
     () => {
-        |e| e.context(::anyhow::anyhow_basic!("[{}:{}, emsg()]", file!(), line!()))
+        $crate::__anyhow_no_message!(|e| e.context($crate::private::tag($crate::__anyhow_site!(), line!(), column!(), ",", &"")))
+    };
+}
+/// Build a closure for `.map_err(...)` that adds located context to any
+/// error.
+///
+/// The context is tagged with the location of the `ctx!` call, in the same
+/// `[file:line, emsg(...)]` form as [`anyhow_context!`]. Unlike that macro,
+/// the closure accepts any error that converts into `anyhow::Error`, not only
+/// an `anyhow::Error` itself. The format arguments are only evaluated, and the
+/// message only formatted, if the closure is called, so nothing is done on
+/// the success path.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ctx, Result};
+/// # use std::fs;
+/// #
+/// fn read_config(path: &str) -> Result<String> {
+///     let config = fs::read_to_string(path).map_err(ctx!("reading {}", path))?;
+///     Ok(config)
+/// }
+/// #
+/// # let error = read_config("/nonexistent").unwrap_err();
+/// # assert!(error.to_string().ends_with(", emsg(reading /nonexistent)]"));
+/// ```
+#[macro_export]
+macro_rules! ctx {
+    ($fmt:expr $(, $arg:expr)* $(,)?) => {
+        |error| $crate::Error::from(error).context($crate::private::tag(
+            $crate::__anyhow_site!(),
+            line!(),
+            column!(),
+            ",",
+            &$crate::private::format_args!($fmt $(, $arg)*),
+        ))
+    };
+}
+
+/// Add located context to the error of a `Result`, without an early return.
+///
+/// `loc_context!(result, "...", args...)` is `result.map_err(ctx!("...",
+/// args...))`: the `Ok` value is passed through untouched, and an error is
+/// converted into `anyhow::Error` and given context tagged with the location
+/// of the `loc_context!` call. The result can be chained further or returned
+/// as it is.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{loc_context, Result};
+/// # use std::fs;
+/// #
+/// fn read_config(path: &str) -> Result<String> {
+///     loc_context!(fs::read_to_string(path), "reading {}", path)
+/// }
+/// #
+/// # let error = read_config("/nonexistent").unwrap_err();
+/// # assert!(error.to_string().ends_with(", emsg(reading /nonexistent)]"));
+/// ```
+#[macro_export]
+macro_rules! loc_context {
+    ($result:expr, $fmt:expr $(, $arg:expr)* $(,)?) => {
+        $crate::private::Result::map_err($result, $crate::ctx!($fmt $(, $arg)*))
+    };
+}
+
+/// Run a block of fallible code, putting any error it produces in a
+/// category.
+///
+/// The block is run as the body of a closure returning `Result`, so `?`
+/// inside it converts errors into `anyhow::Error` and leaves the block rather
+/// than the enclosing function. The value of the block is the success value.
+/// An error is given the category with
+/// [`Error::with_category`][crate::Error::with_category] before the whole
+/// `Result` is returned.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{categorize, Result};
+/// #
+/// #[derive(Debug, PartialEq)]
+/// enum Category {
+///     Network,
+///     Storage,
+/// }
+///
+/// fn fetch(url: &str) -> Result<String> {
+///     categorize!(Category::Network, {
+///         let port: u16 = url.rsplit(':').next().unwrap().parse()?;
+///         format!("connected on {}", port)
+///     })
+/// }
+///
+/// let error = fetch("example.com:http").unwrap_err();
+/// assert_eq!(error.category::<Category>(), Some(&Category::Network));
+/// # assert!(fetch("example.com:80").is_ok());
+/// ```
+#[macro_export]
+macro_rules! categorize {
+    ($category:expr, $body:block $(,)?) => {
+        (|| -> $crate::Result<_> { $crate::private::Ok($body) })()
+            .map_err(|error| error.with_category($category))
+    };
+}
+
+/// Await a fallible future, returning early with a located error if it
+/// fails.
+///
+/// `try_async!(future, "...", args...)` is `future.await?` with the error
+/// given context tagged with the location of the `try_async!` call, the way
+/// [`ctx!`] tags it. A plain `?` after `.await` converts the error with
+/// `From` and records no location at all, and the backtrace of an error
+/// from a future polled by an executor mostly shows the executor, so the
+/// tag is what says which await in an async block failed. Without a message,
+/// the context is the tag alone.
+///
+/// Like `?`, the macro returns from the innermost function or async block,
+/// which must return `anyhow::Result`. The error of the future may be
+/// anything that converts into `anyhow::Error`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{try_async, Result};
+/// # use std::io;
+/// #
+/// async fn connect(host: &str) -> io::Result<u16> {
+///     Err(io::Error::new(io::ErrorKind::ConnectionRefused, host.to_owned()))
+/// }
+///
+/// async fn deploy() -> Result<()> {
+///     let port = try_async!(connect("db"), "connecting to {}", "db");
+///     println!("connected on {}", port);
+///     Ok(())
+/// }
+/// #
+/// # use futures::FutureExt;
+/// # let error = deploy().now_or_never().unwrap().unwrap_err();
+/// # assert!(error.to_string().ends_with(", emsg(connecting to db)]"));
+/// ```
+#[macro_export]
+macro_rules! try_async {
+    ($future:expr $(,)?) => {
+        $crate::__anyhow_no_message!($crate::try_async!($future, ""))
+    };
+    ($future:expr, $fmt:expr $(, $arg:expr)* $(,)?) => {
+        match $future.await {
+            $crate::private::Ok(value) => value,
+            $crate::private::Err(error) => {
+                return $crate::private::Err($crate::ctx!($fmt $(, $arg)*)(error));
+            }
+        }
+    };
+}
+
+// The file part of a location tag: `file!()`, preceded by `module_path!()`
+// and a space when the "module-path" feature is enabled.
+#[cfg(feature = "module-path")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_raw_site {
+    () => {
+        $crate::private::concat!(module_path!(), " ", file!())
+    };
+}
+
+#[cfg(not(feature = "module-path"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_raw_site {
+    () => {
+        file!()
+    };
+}
+
+// With the "relative-paths" feature, the file is made relative to the
+// directory given by ANYHOW_STRIP_PREFIX, or else to the manifest directory,
+// of the crate being compiled.
+#[cfg(feature = "relative-paths")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_site {
+    () => {
+        &*$crate::private::relative_site(
+            $crate::__anyhow_raw_site!(),
+            option_env!("ANYHOW_STRIP_PREFIX").or(option_env!("CARGO_MANIFEST_DIR")),
+        )
+    };
+}
+
+#[cfg(not(feature = "relative-paths"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_site {
+    () => {
+        $crate::__anyhow_raw_site!()
+    };
+}
+
+// The file part of a location tag for a location given as a
+// `core::panic::Location`, made relative as __anyhow_site! makes it.
+#[cfg(feature = "relative-paths")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_site_of {
+    ($location:expr) => {
+        &*$crate::private::relative_site(
+            $location.file(),
+            option_env!("ANYHOW_STRIP_PREFIX").or(option_env!("CARGO_MANIFEST_DIR")),
+        )
+    };
+}
+
+#[cfg(not(feature = "relative-paths"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_site_of {
+    ($location:expr) => {
+        $location.file()
     };
-}
\ No newline at end of file
+}
+
+// The expansion of the argument-less forms of the tagging macros, or a
+// compile error in their place with "deny-empty-messages".
+#[cfg(not(feature = "deny-empty-messages"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_no_message {
+    ($expansion:expr) => {
+        $expansion
+    };
+}
+
+#[cfg(feature = "deny-empty-messages")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anyhow_no_message {
+    ($expansion:expr) => {
+        compile_error!(
+            "missing error message; the \"deny-empty-messages\" feature of anyhow requires one"
+        )
+    };
+}
@@ -0,0 +1,67 @@
+use crate::{Error, Frame};
+use std::fmt::Write;
+
+impl Error {
+    /// Render this error as Markdown, for pasting into issue trackers and
+    /// chat.
+    ///
+    /// The outermost message becomes a bold heading and each cause a bullet
+    /// nested one level below the one it caused. Recorded locations follow
+    /// their message as inline code, and a captured backtrace is appended in
+    /// a fenced code block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("write failed")
+    ///     .context("saving report");
+    ///
+    /// assert_eq!(
+    ///     error.to_markdown(),
+    ///     "**saving report**\n\n- write failed\n  - disk full\n",
+    /// );
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        for (n, frame) in self.frames().enumerate() {
+            let (message, location) = match frame {
+                Frame::Adhoc { message, location } | Frame::Context { message, location } => {
+                    (message, location)
+                }
+                Frame::Foreign { error } => (error.to_string(), None),
+            };
+            if n == 0 {
+                let _ = write!(markdown, "**{}**", message);
+            } else {
+                let indent = "  ".repeat(n - 1);
+                if n == 1 {
+                    markdown.push('\n');
+                }
+                let _ = write!(markdown, "\n{}- {}", indent, message);
+            }
+            if let Some(location) = location {
+                let _ = write!(markdown, " `{}`", location);
+            }
+        }
+        markdown.push('\n');
+
+        #[cfg(any(backtrace, feature = "backtrace"))]
+        {
+            use crate::backtrace::BacktraceStatus;
+            use crate::error::ErrorImpl;
+
+            let backtrace = unsafe { ErrorImpl::backtrace(self.inner.by_ref()) };
+            if let BacktraceStatus::Captured = backtrace.status() {
+                let backtrace = backtrace.to_string();
+                let _ = write!(markdown, "\n```text\n{}\n```\n", backtrace.trim_end());
+            }
+        }
+
+        markdown
+    }
+}
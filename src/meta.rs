@@ -0,0 +1,167 @@
+use crate::alloc::{Box, Vec};
+use crate::chain::FrameCache;
+use crate::location::Location;
+use crate::Error;
+use core::any::Any;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+// Data that describes the error as a whole rather than any one level of its
+// chain. It lives in the outermost ErrorImpl and is handed up to the new
+// outermost level whenever context is added. What `?` records is kept inline,
+// so that converting an error does not allocate; the rest is boxed.
+#[derive(Default)]
+pub(crate) struct Header {
+    // The first site the error was propagated through, and the length of the
+    // chain at the time.
+    #[cfg(not(anyhow_no_track_caller))]
+    pub site: Option<&'static core::panic::Location<'static>>,
+    #[cfg(not(anyhow_no_track_caller))]
+    pub depth: u32,
+    // The number of the build version in effect when the error was created,
+    // or 0 if none was.
+    pub version: u32,
+    pub meta: MetaBox,
+}
+
+// The boxed part of a Header, which the frames parsed from the error are also
+// cached in. Unlike an Option<Box<Meta>>, it can be filled in through a shared
+// reference, as the cache is on first parse.
+#[derive(Default)]
+pub(crate) struct MetaBox {
+    ptr: AtomicPtr<Meta>,
+}
+
+// A site the error was propagated through, with the length of the chain at the
+// time it was recorded.
+#[cfg(not(anyhow_no_track_caller))]
+#[derive(Copy, Clone)]
+pub(crate) struct Site {
+    pub location: &'static core::panic::Location<'static>,
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub depth: u32,
+}
+
+#[derive(Default)]
+pub(crate) struct Meta {
+    // Sites the error was propagated through after the first, oldest first.
+    #[cfg(not(anyhow_no_track_caller))]
+    pub propagation: Vec<Site>,
+    // Typed values added by Error::attach, oldest first.
+    pub attachments: Vec<Box<dyn Any + Send + Sync>>,
+    // Unrelated errors recorded by Error::also, oldest first.
+    pub suppressed: Vec<Error>,
+    // Number of times Error::bump_attempt was called.
+    pub attempts: u32,
+    // Number of innermost levels of the chain whose location tag is left out
+    // when rendering, set by Error::hide_location.
+    pub hidden_locations: usize,
+    // Where the innermost level was created, for errors made by Error::msg_at,
+    // whose message carries no location tag.
+    pub origin: Option<Location>,
+    // What was parsed out of the outermost level's rendering.
+    pub frames: FrameCache,
+}
+
+impl Header {
+    // Whether nothing has been recorded on the error yet, beyond the build
+    // version every new error is stamped with.
+    pub fn is_fresh(&self) -> bool {
+        #[cfg(not(anyhow_no_track_caller))]
+        {
+            if self.site.is_some() {
+                return false;
+            }
+        }
+        self.meta.get().map_or(true, Meta::is_fresh)
+    }
+
+    // The sites the error was propagated through, oldest first.
+    #[cfg(not(anyhow_no_track_caller))]
+    pub fn propagation(&self) -> impl Iterator<Item = Site> + '_ {
+        let first = self.site.map(|location| Site {
+            location,
+            depth: self.depth,
+        });
+        let rest = match self.meta.get() {
+            Some(meta) => &meta.propagation[..],
+            None => &[],
+        };
+        first.into_iter().chain(rest.iter().cloned())
+    }
+
+    // Records a site the error was propagated through while its chain was
+    // `depth` levels long.
+    #[cfg(not(anyhow_no_track_caller))]
+    pub fn propagate(&mut self, location: &'static core::panic::Location<'static>, depth: u32) {
+        if self.site.is_none() {
+            self.site = Some(location);
+            self.depth = depth;
+        } else {
+            let meta = self.meta.get_or_default();
+            meta.propagation.push(Site { location, depth });
+        }
+    }
+}
+
+impl MetaBox {
+    pub fn get(&self) -> Option<&Meta> {
+        unsafe { self.ptr.load(Ordering::Acquire).as_ref() }
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut Meta> {
+        unsafe { self.ptr.get_mut().as_mut() }
+    }
+
+    pub fn get_or_default(&mut self) -> &mut Meta {
+        let ptr = self.ptr.get_mut();
+        if ptr.is_null() {
+            *ptr = Box::into_raw(Box::default());
+        }
+        unsafe { &mut **ptr }
+    }
+
+    // Two threads may both fill it in; the loser drops its Meta and uses the
+    // one that was stored first.
+    pub fn get_or_init(&self) -> &Meta {
+        if let Some(meta) = self.get() {
+            return meta;
+        }
+        let meta = Box::into_raw(Box::default());
+        match self
+            .ptr
+            .compare_exchange(ptr::null_mut(), meta, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => unsafe { &*meta },
+            Err(stored) => {
+                drop(unsafe { Box::from_raw(meta) });
+                unsafe { &*stored }
+            }
+        }
+    }
+}
+
+impl Drop for MetaBox {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        if !ptr.is_null() {
+            drop(unsafe { Box::from_raw(ptr) });
+        }
+    }
+}
+
+impl Meta {
+    fn is_fresh(&self) -> bool {
+        #[cfg(not(anyhow_no_track_caller))]
+        {
+            if !self.propagation.is_empty() {
+                return false;
+            }
+        }
+        self.attachments.is_empty()
+            && self.suppressed.is_empty()
+            && self.attempts == 0
+            && self.hidden_locations == 0
+            && self.origin.is_none()
+    }
+}
@@ -0,0 +1,37 @@
+/// A metric that is bumped each time [`fail_metric!`] reports a failure.
+///
+/// This is implemented for every `FnMut()`, so a closure can adapt whatever
+/// counter type a metrics library provides:
+///
+/// ```
+/// use anyhow::{fail_metric, Result};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static FAILURES: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn check(n: usize) -> Result<()> {
+///     if n == 0 {
+///         fail_metric!(|| { FAILURES.fetch_add(1, Ordering::Relaxed); }, "n must be nonzero");
+///     }
+///     Ok(())
+/// }
+/// #
+/// # assert!(check(0).is_err());
+/// # assert_eq!(FAILURES.load(Ordering::Relaxed), 1);
+/// ```
+///
+/// Types that own their count, or that wrap a Prometheus-style counter whose
+/// `inc` takes `&self`, can implement the trait directly.
+pub trait Counter {
+    /// Record one failure.
+    fn increment(&mut self);
+}
+
+impl<F> Counter for F
+where
+    F: FnMut(),
+{
+    fn increment(&mut self) {
+        self()
+    }
+}
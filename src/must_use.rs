@@ -0,0 +1,139 @@
+use crate::Error;
+use core::fmt::{self, Debug, Display};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(debug_assertions)]
+use core::sync::atomic::AtomicBool;
+
+#[cfg(debug_assertions)]
+use std::mem;
+
+type UnobservedHook = fn(&Error);
+
+static UNOBSERVED_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// Customize what happens when a [`MustUse`] error is dropped unobserved.
+///
+/// By default a warning naming the error is printed to stderr. The hook is
+/// only ever called in builds with debug assertions enabled. Installing a
+/// hook replaces any previously installed one.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_unobserved_hook(hook: UnobservedHook) {
+    UNOBSERVED_HOOK.store(hook as usize, Ordering::SeqCst);
+}
+
+#[cfg(debug_assertions)]
+fn unobserved_hook() -> UnobservedHook {
+    match UNOBSERVED_HOOK.load(Ordering::SeqCst) {
+        0 => warn,
+        // Safety: the only nonzero values ever stored are fn pointers of this
+        // exact type, by set_unobserved_hook.
+        hook => unsafe { mem::transmute::<usize, UnobservedHook>(hook) },
+    }
+}
+
+#[cfg(debug_assertions)]
+fn warn(error: &Error) {
+    eprintln!("warning: error dropped without being observed: {:#}", error);
+}
+
+/// An error that complains if it is dropped without anyone looking at it,
+/// for finding errors that are swallowed by mistake.
+///
+/// Created with [`Error::must_use`]. The error counts as observed once it is
+/// formatted with `{}` or `{:?}`, accessed through `Deref`, or taken back out
+/// with [`into_inner`][MustUse::into_inner]. If it is dropped before any of
+/// that happened, in a build with debug assertions, the
+/// [unobserved hook][set_unobserved_hook] is called with it.
+///
+/// Without debug assertions the check is compiled out: a `MustUse` is the
+/// same size as an `Error`, and observing or dropping it does no extra work.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, MustUse};
+///
+/// fn flush() -> Result<(), MustUse> {
+///     Err(anyhow!("disk full").must_use())
+/// }
+///
+/// if let Err(error) = flush() {
+///     eprintln!("flush failed: {}", error);
+/// }
+/// ```
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct MustUse {
+    error: Option<Error>,
+    #[cfg(debug_assertions)]
+    observed: AtomicBool,
+}
+
+impl MustUse {
+    /// Wrap the error, to be observed before it is dropped.
+    pub fn new(error: Error) -> Self {
+        MustUse {
+            error: Some(error),
+            #[cfg(debug_assertions)]
+            observed: AtomicBool::new(false),
+        }
+    }
+
+    /// Take the error back out, which counts as observing it.
+    pub fn into_inner(mut self) -> Error {
+        self.error.take().unwrap()
+    }
+
+    fn observe(&self) -> &Error {
+        #[cfg(debug_assertions)]
+        self.observed.store(true, Ordering::Relaxed);
+        self.error.as_ref().unwrap()
+    }
+}
+
+impl Error {
+    /// Wrap this error so that dropping it unobserved is reported, in builds
+    /// with debug assertions. See [`MustUse`].
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn must_use(self) -> MustUse {
+        MustUse::new(self)
+    }
+}
+
+impl From<Error> for MustUse {
+    fn from(error: Error) -> Self {
+        MustUse::new(error)
+    }
+}
+
+impl Deref for MustUse {
+    type Target = Error;
+
+    fn deref(&self) -> &Error {
+        self.observe()
+    }
+}
+
+impl Debug for MustUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.observe(), f)
+    }
+}
+
+impl Display for MustUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.observe(), f)
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for MustUse {
+    fn drop(&mut self) {
+        if let Some(error) = &self.error {
+            if !self.observed.load(Ordering::Relaxed) {
+                unobserved_hook()(error);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::alloc::{Cow, String, Vec};
+
+// The message of a tag is written with its parentheses balanced, so that the
+// `)` closing `emsg(` is the first one not matched inside the message. A `(`
+// or `)` without a partner is written with a backslash in front of it, and a
+// run of backslashes in front of a parenthesis of the message is doubled, so
+// that it cannot be mistaken for one. All other backslashes are left alone.
+
+// Balances the parentheses of the part of `tagged` from `start` on, which is a
+// message just written into a tag.
+pub(crate) fn balance_from(tagged: &mut String, start: usize) {
+    if let Cow::Owned(balanced) = balance(&tagged[start..]) {
+        tagged.truncate(start);
+        tagged.push_str(&balanced);
+    }
+}
+
+// The message with its parentheses balanced, for writing into a tag.
+pub(crate) fn balance(message: &str) -> Cow<'_, str> {
+    let bytes = message.as_bytes();
+    let mut open = Vec::new();
+    let mut unmatched = Vec::new();
+    let mut escapes = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'(' => open.push(i),
+            b')' => match open.pop() {
+                Some(_) => {}
+                None => unmatched.push(i),
+            },
+            _ => continue,
+        }
+        escapes |= i > 0 && bytes[i - 1] == b'\\';
+    }
+    unmatched.extend(open);
+    if unmatched.is_empty() && !escapes {
+        return Cow::Borrowed(message);
+    }
+    unmatched.sort();
+
+    let mut balanced = String::with_capacity(message.len() + unmatched.len());
+    let mut backslashes = 0;
+    for (i, c) in message.char_indices() {
+        match c {
+            '\\' => backslashes += 1,
+            '(' | ')' => {
+                // The run in front of the parenthesis has been written once
+                // already.
+                for _ in 0..backslashes {
+                    balanced.push('\\');
+                }
+                if unmatched.binary_search(&i).is_ok() {
+                    balanced.push('\\');
+                }
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        balanced.push(c);
+    }
+    Cow::Owned(balanced)
+}
+
+// Turns a message balanced by `balance` back into the original.
+pub(crate) fn unbalance(message: &str) -> Cow<'_, str> {
+    if !message.contains("\\(") && !message.contains("\\)") {
+        return Cow::Borrowed(message);
+    }
+    let mut original = String::with_capacity(message.len());
+    let mut backslashes = 0;
+    for c in message.chars() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                continue;
+            }
+            '(' | ')' => backslashes /= 2,
+            _ => {}
+        }
+        for _ in 0..backslashes {
+            original.push('\\');
+        }
+        backslashes = 0;
+        original.push(c);
+    }
+    for _ in 0..backslashes {
+        original.push('\\');
+    }
+    Cow::Owned(original)
+}
+
+// The offset of the first `)` of `body` that is not escaped and not matched by
+// a `(` before it: the end of a balanced message at the start of `body`.
+pub(crate) fn message_end(body: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut backslashes = 0;
+    for (i, b) in body.bytes().enumerate() {
+        match b {
+            b'\\' => {
+                backslashes += 1;
+                continue;
+            }
+            b'(' if backslashes % 2 == 0 => depth += 1,
+            b')' if backslashes % 2 == 0 => {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        backslashes = 0;
+    }
+    None
+}
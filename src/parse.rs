@@ -0,0 +1,877 @@
+//! Parsing the `{:?}` representation of an error back into its frames.
+//!
+//! `anyhow_error!` renders the error it wraps with `{:?}` after its own
+//! location-tagged message, joined by the
+//! [line separator][crate::line_separator], so the Debug string of a chain
+//! built with it is a sequence of `[file:line, emsg(...)]` segments.
+//! [`parse_frames`] splits such a string back up.
+//!
+//! Parentheses in a message are written so that the `)` ending `emsg(` is
+//! the first one not paired up inside the message. A `(` or `)` of the
+//! message without a partner gets a backslash in front of it, as in
+//! `emsg(expected \))`, and a run of backslashes in front of a parenthesis
+//! of the message is doubled. The parsers undo both, so every frame comes
+//! back exactly as it was written, however its parentheses are nested.
+//! Tags with unpaired parentheses that were written some other way are read
+//! up to the first `)]` that can end them.
+
+use crate::alloc::{String, ToString, Vec};
+use crate::chain::next_frame;
+use crate::error::ErrorImpl;
+use crate::location::{extract_one, strip_tags};
+use crate::parens::{balance, balance_from, unbalance};
+use crate::separator::{escape, escape_from, line_separator, unescape};
+use crate::Error;
+use core::fmt::{self, Display, Write};
+use core::iter;
+use core::str::FromStr;
+
+#[cfg(feature = "std")]
+use crate::StdError;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+pub use crate::chain::Frames;
+
+/// One segment of an error's `{:?}` representation.
+///
+/// This is a piece of the rendered text, unlike [`Frame`][crate::Frame],
+/// which is one level of the chain as [`Error::frames`] gives it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedFrame {
+    /// The file of the segment's location tag, or empty if it had none.
+    pub file: String,
+    /// The line of the segment's location tag, or 0 if it had none.
+    pub line: u32,
+    /// The message inside `emsg(...)`, or the whole segment if it had no
+    /// location tag.
+    pub msg: String,
+}
+
+/// Renders the frame as `[file:line emsg(msg)]`, or as just its message if
+/// it has no file.
+impl Display for ParsedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.file.is_empty() {
+            f.write_str(&self.msg)
+        } else {
+            write!(
+                f,
+                "[{}:{} emsg({})]",
+                self.file,
+                self.line,
+                balance(&escape(&self.msg))
+            )
+        }
+    }
+}
+
+/// Parses a single location tag, such as one rendered by the `Display` of a
+/// frame, into a frame.
+///
+/// The whole string must be one `[file:line emsg(msg)]` or
+/// `[file:line, emsg(msg)]` tag, with or without a column after the line.
+/// The column is not kept.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::parse::ParsedFrame;
+///
+/// let frame: ParsedFrame = "[src/db.rs:7:12, emsg(timed out)]".parse().unwrap();
+/// assert_eq!((frame.file.as_str(), frame.line), ("src/db.rs", 7));
+/// assert_eq!(frame.msg, "timed out");
+///
+/// assert!("[src/db.rs:7] timed out".parse::<ParsedFrame>().is_err());
+/// ```
+impl FromStr for ParsedFrame {
+    type Err = ParseFrameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with('[') {
+            return Err(ParseFrameError { _private: () });
+        }
+        match extract_one(s) {
+            Some((file, line, msg, len)) if len == s.len() => Ok(ParsedFrame {
+                file: file.to_string(),
+                line,
+                msg: unescape(&unbalance(msg)).into_owned(),
+            }),
+            _ => Err(ParseFrameError { _private: () }),
+        }
+    }
+}
+
+/// The error returned when a string is not a location tag, by the
+/// [`FromStr`] implementation of [`ParsedFrame`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseFrameError {
+    _private: (),
+}
+
+impl Display for ParseFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid location tag")
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for ParseFrameError {}
+
+/// Split the `{:?}` representation of `err` into frames, outermost first.
+///
+/// Each segment between line separators that starts with a location tag
+/// becomes a frame with that tag's file, line and message. Anything else,
+/// like the Debug representation of a foreign error at the bottom of the
+/// chain, or content following the tag of the last segment such as a
+/// backtrace, becomes a frame of its own with an empty `file` and a `line`
+/// of 0.
+///
+/// The representation split up is the one the default settings give: an
+/// installed [`ErrorFormatter`][crate::ErrorFormatter] or foreign formatter
+/// changes how the error is shown, not the frames read out of it.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, anyhow_error};
+///
+/// let error = Err::<(), _>(anyhow!("no such file"))
+///     .map_err(anyhow_error!("loading config"))
+///     .unwrap_err();
+///
+/// let frames = anyhow::parse_frames(&error);
+/// assert_eq!(frames[0].msg, "loading config");
+/// assert_eq!(frames[1].msg, "no such file");
+/// assert!(frames.iter().all(|frame| frame.file == file!()));
+/// ```
+pub fn parse_frames(err: &Error) -> Vec<ParsedFrame> {
+    err.parsed_frames().collect()
+}
+
+/// Count the errors of `errors` by the file their
+/// [`root_frame()`][Error::root_frame] is in, for a dashboard of where the
+/// errors of a service come from.
+///
+/// Errors without a location in any frame are left out.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, anyhow_error, Error};
+///
+/// let errors = vec![
+///     anyhow!("disk full"),
+///     Err::<(), _>(anyhow!("timed out"))
+///         .map_err(anyhow_error!("loading"))
+///         .unwrap_err(),
+///     Error::msg("no location"),
+/// ];
+///
+/// let histogram = anyhow::file_histogram(&errors);
+/// assert_eq!(histogram.len(), 1);
+/// assert_eq!(histogram[file!()], 2);
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn file_histogram<'a>(errors: impl IntoIterator<Item = &'a Error>) -> HashMap<String, usize> {
+    let mut histogram = HashMap::new();
+    for error in errors {
+        if let Some(root) = error.root_frame() {
+            *histogram.entry(root.file).or_insert(0) += 1;
+        }
+    }
+    histogram
+}
+
+impl Error {
+    /// Whether the message of any frame of
+    /// [`parsed_frames()`][Error::parsed_frames] contains `needle`.
+    ///
+    /// This is a way to classify errors whose original type is gone, such as
+    /// foreign errors rendered into the message by `anyhow_error!`, by what
+    /// their frames say.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow_error;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::TimedOut, "no reply"))
+    ///     .map_err(anyhow_error!("fetching manifest"))
+    ///     .unwrap_err();
+    ///
+    /// assert!(error.frame_contains("TimedOut"));
+    /// assert!(!error.frame_contains("NotFound"));
+    /// ```
+    pub fn frame_contains(&self, needle: &str) -> bool {
+        self.first_frame_with(needle).is_some()
+    }
+
+    /// The outermost frame of [`parsed_frames()`][Error::parsed_frames]
+    /// whose message contains `needle`.
+    pub fn first_frame_with(&self, needle: &str) -> Option<ParsedFrame> {
+        self.parsed_frames()
+            .find(|frame| frame.msg.contains(needle))
+    }
+
+    /// The innermost frame of [`parsed_frames()`][Error::parsed_frames] that
+    /// has a location: where the error first originated, however many
+    /// contexts were layered on top of it since.
+    ///
+    /// Frames without a location tag, such as a backtrace following the
+    /// frames, are skipped. Returns `None` if no frame has a location.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    /// let line = line!() - 3;
+    ///
+    /// let root = error.root_frame().unwrap();
+    /// assert_eq!((root.line, root.msg.as_str()), (line, "disk full"));
+    /// ```
+    pub fn root_frame(&self) -> Option<ParsedFrame> {
+        self.parsed_frames()
+            .filter(|frame| !frame.file.is_empty())
+            .last()
+    }
+
+    /// Whether any frame of [`parsed_frames()`][Error::parsed_frames] is at
+    /// `line` of `file`, for asserting in a test where an error came from.
+    ///
+    /// The files are compared by path suffix, so an absolute path matches the
+    /// relative path of the same file and the other way around: one of them
+    /// has to end with the other, cut at a `/` or `\`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    /// let line = line!() - 3;
+    ///
+    /// assert!(error.matches_location(file!(), line));
+    /// assert!(!error.matches_location(file!(), line + 5));
+    /// ```
+    pub fn matches_location(&self, file: &str, line: u32) -> bool {
+        fn ends_with_path(path: &str, suffix: &str) -> bool {
+            path.ends_with(suffix)
+                && (path.len() == suffix.len()
+                    || path[..path.len() - suffix.len()].ends_with(|c| c == '/' || c == '\\'))
+        }
+
+        self.parsed_frames().any(|frame| {
+            frame.line == line
+                && !frame.file.is_empty()
+                && (ends_with_path(&frame.file, file) || ends_with_path(file, &frame.file))
+        })
+    }
+
+    /// The number of frames of [`all_frames()`][Error::all_frames] that have
+    /// a location: how many times the error was tagged on its way up, by the
+    /// macro that made it and by every located context and wrapper added
+    /// since.
+    ///
+    /// This is not [`chain_len()`][Error::chain_len], which counts the
+    /// errors of the source chain: a frame made by `anyhow_error!` is part of
+    /// the message of a single error, and a source without a tag counts
+    /// there but not here.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_context, anyhow_error, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .map_err(anyhow_context!("rotating logs"))
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error.depth(), 3);
+    /// assert_eq!(error.chain_len(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.all_frames()
+            .filter(|frame| !frame.file.is_empty())
+            .count()
+    }
+
+    /// Whether this error and `other` have the same frames, as far as a
+    /// snapshot test would care.
+    ///
+    /// The frames of [`parsed_frames()`][Error::parsed_frames] are compared
+    /// in order by line and message, and their files by the last component
+    /// of the path only, so that errors made on the same line in checkouts at
+    /// different paths or in different build environments compare equal.
+    /// Backtraces rendered into the frames are left out of the comparison.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let local = anyhow::tagged("/home/dev/app/src/db.rs", 42, "timed out");
+    /// let ci = anyhow::tagged("/build/1234/src/db.rs", 42, "timed out");
+    /// assert!(local.frames_eq(&ci));
+    ///
+    /// let moved = anyhow::tagged("/build/1234/src/db.rs", 43, "timed out");
+    /// assert!(!local.frames_eq(&moved));
+    /// ```
+    pub fn frames_eq(&self, other: &Error) -> bool {
+        fn basename(file: &str) -> &str {
+            file.rsplit(|c| c == '/' || c == '\\')
+                .next()
+                .unwrap_or(file)
+        }
+        fn message(msg: &str) -> &str {
+            match msg.find("Stack backtrace:") {
+                Some(backtrace) => msg[..backtrace].trim_end(),
+                None => msg,
+            }
+        }
+
+        let mut ours = self.parsed_frames();
+        let mut theirs = other.parsed_frames();
+        loop {
+            match (ours.next(), theirs.next()) {
+                (None, None) => return true,
+                (Some(a), Some(b)) => {
+                    if a.line != b.line
+                        || basename(&a.file) != basename(&b.file)
+                        || message(&a.msg) != message(&b.msg)
+                    {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Rebuild an error from its frames, such as those on the far side of a
+    /// serialization boundary.
+    ///
+    /// The message of the new error is the frames joined by the
+    /// [line separator][crate::line_separator], each frame with a file
+    /// rendered as `[file:line, emsg(msg)]` the way `anyhow_error!` renders
+    /// its own, and each frame without one rendered as just its message, so
+    /// that [`parse_frames`] reads the same frames back out of it. Columns
+    /// are not part of a frame and are not restored.
+    ///
+    /// The new error does not capture a backtrace of its own. The backtrace
+    /// of the original error, if its `{:?}` representation included one, is
+    /// among the frames already.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::msg("disk full").prepend_location("src/log.rs", 12, "writing log");
+    ///
+    /// let frames = anyhow::parse_frames(&error);
+    /// let rebuilt = Error::from_frames(frames.clone());
+    /// assert_eq!(anyhow::parse_frames(&rebuilt), frames);
+    /// assert_eq!(format!("{:?}", rebuilt), format!("{:?}", error));
+    /// ```
+    #[cold]
+    pub fn from_frames(frames: Vec<ParsedFrame>) -> Self {
+        let mut message = String::new();
+        for (n, frame) in frames.iter().enumerate() {
+            if n > 0 {
+                message.push_str(line_separator());
+            }
+            if frame.file.is_empty() {
+                let start = message.len();
+                message.push_str(&frame.msg);
+                escape_from(&mut message, start);
+            } else {
+                let _ = write!(message, "[{}:{}, emsg(", frame.file, frame.line);
+                let start = message.len();
+                message.push_str(&frame.msg);
+                escape_from(&mut message, start);
+                balance_from(&mut message, start);
+                message.push_str(")]");
+            }
+        }
+        Error::from_adhoc(message, no_backtrace!())
+    }
+
+    /// Rebuild this error with each of its frames passed through `f`, such as
+    /// to point the locations in generated code back at the templates it
+    /// was generated from.
+    ///
+    /// The frames are those of [`parsed_frames()`][Error::parsed_frames],
+    /// outermost first, and the new error is assembled from what `f`
+    /// returns as [`from_frames`][Error::from_frames] assembles it. `f` may
+    /// change any part of a frame, and gets the frames without a location as
+    /// well, with an empty file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::msg("bad token").prepend_location("out/parser.gen.rs", 812, "parsing");
+    ///
+    /// let error = error.retag(|mut frame| {
+    ///     if frame.file == "out/parser.gen.rs" {
+    ///         frame.file = "grammar/parser.template".to_owned();
+    ///         frame.line = 40;
+    ///     }
+    ///     frame
+    /// });
+    /// assert_eq!(error.location().unwrap().file(), "grammar/parser.template");
+    /// ```
+    #[cold]
+    pub fn retag<F>(self, f: F) -> Self
+    where
+        F: FnMut(ParsedFrame) -> ParsedFrame,
+    {
+        Error::from_frames(self.parsed_frames().map(f).collect())
+    }
+
+    /// A copy of this error with every occurrence of `patterns` in the
+    /// messages of its frames replaced by `***`, for logging an error whose
+    /// messages may have interpolated secrets such as tokens or connection
+    /// strings.
+    ///
+    /// The copy is assembled from the rewritten frames of
+    /// [`parsed_frames()`][Error::parsed_frames] as
+    /// [`from_frames`][Error::from_frames] assembles it, so it is a plain
+    /// message error with the same `{:?}` frames. The files and lines of the
+    /// tags are kept as they are. The messages of untagged frames, such as
+    /// the Debug of a foreign error, are redacted too. Empty patterns match
+    /// nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow_error;
+    ///
+    /// let token = "hunter2";
+    /// let error = Err::<(), _>(anyhow::anyhow!("rejected token {}", token))
+    ///     .map_err(anyhow_error!("logging in with {}", token))
+    ///     .unwrap_err();
+    ///
+    /// let redacted = format!("{:?}", error.redact(&[token]));
+    /// assert!(!redacted.contains(token));
+    /// assert!(redacted.contains("logging in with ***"));
+    /// ```
+    #[cold]
+    pub fn redact(&self, patterns: &[&str]) -> Error {
+        Error::from_frames(
+            self.parsed_frames()
+                .map(|mut frame| {
+                    for pattern in patterns.iter().filter(|pattern| !pattern.is_empty()) {
+                        if frame.msg.contains(pattern) {
+                            frame.msg = frame.msg.replace(pattern, "***");
+                        }
+                    }
+                    frame
+                })
+                .collect(),
+        )
+    }
+
+    /// A copy of this error with at most `max` of its frames, for logging an
+    /// error propagated through so many levels that its `{:?}` would be too
+    /// big to record.
+    ///
+    /// If the error has more than `max` frames of
+    /// [`parsed_frames()`][Error::parsed_frames], the outermost `max - max /
+    /// 2` and the innermost `max / 2` are kept, with an untagged
+    /// `... N frames elided ...` frame between them in place of the `N`
+    /// frames left out. The copy is assembled as
+    /// [`from_frames`][Error::from_frames] assembles it, so it is a plain
+    /// message error like that of [`redact`][Error::redact] even when no
+    /// frame is left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let mut error = Error::msg("disk full");
+    /// for n in 0..10 {
+    ///     error = error.prepend_location("src/retry.rs", 40 + n, "retrying");
+    /// }
+    ///
+    /// let truncated = error.truncate_frames(4);
+    /// let messages: Vec<String> = truncated.parsed_frames().map(|frame| frame.msg).collect();
+    /// assert_eq!(
+    ///     messages,
+    ///     ["retrying", "retrying", "... 7 frames elided ...", "retrying", "disk full"],
+    /// );
+    /// ```
+    #[cold]
+    pub fn truncate_frames(&self, max: usize) -> Error {
+        let mut frames: Vec<ParsedFrame> = self.parsed_frames().collect();
+        if frames.len() > max {
+            let elided = frames.len() - max;
+            let outer = max - max / 2;
+            let marker = ParsedFrame {
+                file: String::new(),
+                line: 0,
+                msg: crate::private::format!("... {} frames elided ...", elided),
+            };
+            frames.splice(outer..outer + elided, iter::once(marker));
+        }
+        Error::from_frames(frames)
+    }
+
+    /// A copy of this error with only the frames that `f` accepts, such as
+    /// to leave out the frames of generated code, retry loops or framework
+    /// glue.
+    ///
+    /// The frames are those of [`parsed_frames()`][Error::parsed_frames],
+    /// outermost first, including those without a location, which have an
+    /// empty file. Unlike [`truncate_frames`][Error::truncate_frames], which
+    /// goes by position, the frames kept are chosen by their contents. The
+    /// ones kept are assembled as [`from_frames`][Error::from_frames]
+    /// assembles them, so the copy is a plain message error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    ///
+    /// let error = Error::msg("connection reset")
+    ///     .prepend_location("vendor/hyper/src/client.rs", 230, "sending request")
+    ///     .prepend_location("src/api.rs", 41, "fetching user");
+    ///
+    /// let filtered = error.with_frame_filter(|frame| !frame.file.contains("vendor/"));
+    /// let messages: Vec<String> = filtered.parsed_frames().map(|frame| frame.msg).collect();
+    /// assert_eq!(messages, ["fetching user", "connection reset"]);
+    /// ```
+    #[cold]
+    pub fn with_frame_filter<F>(&self, f: F) -> Error
+    where
+        F: Fn(&ParsedFrame) -> bool,
+    {
+        Error::from_frames(self.parsed_frames().filter(|frame| f(frame)).collect())
+    }
+
+    /// A copy of this error, for reporting one failure to several sinks, if
+    /// every level of its chain was created by anyhow.
+    ///
+    /// The copy is rebuilt from the text of the levels, innermost first, as
+    /// an ad-hoc message with each outer level added as context, so its
+    /// `{}` and `{:?}` show the same as those of this error, and it keeps the
+    /// propagation sites, attempt count and other bookkeeping of this error.
+    /// What the levels were made from is lost:
+    /// the copy downcasts only to `String`, and it has no backtrace.
+    ///
+    /// Returns `None` if the chain ends in a foreign error, which may not be
+    /// cloneable, or if the error carries attachments, which are typed values
+    /// that cannot be copied. Errors recorded with [`also`][Error::also] are
+    /// copied the same way, and any one of them failing to copy fails the
+    /// whole.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_located};
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    /// let copy = error.try_clone().unwrap();
+    /// assert_eq!(copy.to_string(), error.to_string());
+    ///
+    /// let error = anyhow::Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    /// assert!(error.try_clone().is_none());
+    /// ```
+    #[cold]
+    pub fn try_clone(&self) -> Option<Error> {
+        let this = self.inner.by_ref();
+        if !unsafe { ErrorImpl::has_message_root(this) } {
+            return None;
+        }
+        let header = unsafe { ErrorImpl::header(this) };
+        let meta = header.meta.get();
+        let suppressed = match meta {
+            Some(meta) if !meta.attachments.is_empty() => return None,
+            Some(meta) => meta
+                .suppressed
+                .iter()
+                .map(Error::try_clone)
+                .collect::<Option<Vec<Error>>>()?,
+            None => Vec::new(),
+        };
+
+        let mut levels: Vec<String> = unsafe { ErrorImpl::chain(this) }
+            .map(ToString::to_string)
+            .collect();
+        let mut copy = Error::from_adhoc(levels.pop()?, no_backtrace!());
+        while let Some(level) = levels.pop() {
+            copy = copy.context(level);
+        }
+
+        {
+            let copied = unsafe { ErrorImpl::header_mut(copy.inner.by_mut()) };
+            #[cfg(not(anyhow_no_track_caller))]
+            {
+                copied.site = header.site;
+                copied.depth = header.depth;
+            }
+            copied.version = header.version;
+        }
+        if let Some(meta) = meta {
+            let copied = unsafe { ErrorImpl::meta_mut(copy.inner.by_mut()) };
+            #[cfg(not(anyhow_no_track_caller))]
+            {
+                copied.propagation = meta.propagation.clone();
+            }
+            copied.suppressed = suppressed;
+            copied.attempts = meta.attempts;
+            copied.hidden_locations = meta.hidden_locations;
+            copied.origin = meta.origin.clone();
+        }
+        Some(copy)
+    }
+
+    /// A new error with the messages of this one and none of its locations,
+    /// for handing across a boundary where internal file paths must not be
+    /// shown.
+    ///
+    /// The message of the new error is the non-empty messages of the frames
+    /// of [`parsed_frames()`][Error::parsed_frames] joined by `": "`,
+    /// including any frame without a location tag, such as the Debug
+    /// representation of a foreign error. Backtraces rendered into the
+    /// frames are left out, as they give away paths just like the tags, and
+    /// the new error does not capture one of its own.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    ///
+    /// let stripped = error.strip_locations();
+    /// assert_eq!(stripped.to_string(), "writing log: disk full");
+    /// assert!(!format!("{:?}", stripped).contains(file!()));
+    /// ```
+    #[cold]
+    pub fn strip_locations(&self) -> Self {
+        let mut message = String::new();
+        for frame in self.parsed_frames() {
+            let msg = match frame.msg.find("Stack backtrace:") {
+                Some(backtrace) => frame.msg[..backtrace].trim_end(),
+                None => &frame.msg,
+            };
+            if msg.is_empty() {
+                continue;
+            }
+            if !message.is_empty() {
+                message.push_str(": ");
+            }
+            // A message may hold a tag of its own, as tag_display makes.
+            strip_tags(msg, &mut message);
+        }
+        Error::from_adhoc(message, no_backtrace!())
+    }
+
+    /// This error as upstream anyhow would have made it, for a codebase
+    /// moving off this crate onto upstream anyhow to compare the two during
+    /// the transition.
+    ///
+    /// Every level of the [`chain()`][Error::chain] that this crate made
+    /// becomes a plain message: the non-empty messages of its frames with
+    /// the location tags stripped, joined by `": "`, with any backtrace
+    /// rendered into them left out and the entries of any `Caused by:` list
+    /// joined like the frames. Levels of foreign errors are kept as
+    /// their `Display`. The innermost level is then made with
+    /// [`Error::msg`] and the others added on top of it as context, the
+    /// way `anyhow_basic!` and [`context()`][crate::Context::context] would
+    /// have built the chain, so `{}`, `{:#}` and the layout of `{:?}` come
+    /// out as upstream's do. Types to downcast to are not kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_basic, anyhow_context, anyhow_located};
+    ///
+    /// let error = Err::<(), _>(anyhow_located!("disk full"))
+    ///     .map_err(anyhow_context!("writing log"))
+    ///     .unwrap_err();
+    /// let upstream = anyhow_basic!("disk full").context("writing log");
+    ///
+    /// let plain = error.into_plain();
+    /// assert_eq!(format!("{:#}", plain), format!("{:#}", upstream));
+    /// assert_eq!(plain.chain().count(), upstream.chain().count());
+    /// ```
+    #[cold]
+    pub fn into_plain(self) -> Self {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut levels = Vec::new();
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            let display = crate::private::format!("{:-}", level);
+            if n >= native {
+                levels.push(display);
+                continue;
+            }
+            let mut message = String::new();
+            let (mut offset, mut pending) = (0, None);
+            while let Some(frame) = next_frame(&display, &mut offset, &mut pending) {
+                let msg = match frame.msg.find("Stack backtrace:") {
+                    Some(backtrace) => frame.msg[..backtrace].trim_end(),
+                    None => &frame.msg,
+                };
+                // The Debug of an error wrapped by anyhow_error! may list its
+                // sources, which upstream's `{:#}` would have joined by ": ".
+                let causes: Vec<&str> = if msg.starts_with("Caused by:") {
+                    msg["Caused by:".len()..].lines().map(uncount).collect()
+                } else {
+                    core::iter::once(msg).collect()
+                };
+                for msg in causes.into_iter().filter(|msg| !msg.is_empty()) {
+                    if !message.is_empty() {
+                        message.push_str(": ");
+                    }
+                    strip_tags(msg, &mut message);
+                }
+            }
+            levels.push(message);
+        }
+
+        let mut levels = levels.into_iter().rev();
+        let mut plain = Error::msg(levels.next().unwrap_or_default());
+        for level in levels {
+            plain = plain.context(level);
+        }
+        plain
+    }
+
+    /// Render the frames of this error as an indented tree, for reading a
+    /// deeply nested chain.
+    ///
+    /// Each frame of [`parsed_frames()`][Error::parsed_frames], outermost
+    /// first, is indented two spaces more than the one before it. Its message
+    /// comes first, or `(no message)` if it is empty, and its location
+    /// follows on a line of its own, prefixed by `at`. A message spanning
+    /// several lines has each of them indented.
+    ///
+    /// See [`write_tree()`][Error::write_tree] to write the tree to a sink
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, Error};
+    ///
+    /// let error = Err::<(), _>(Error::msg("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    /// let line = line!() - 2;
+    ///
+    /// assert_eq!(
+    ///     error.render_tree(),
+    ///     format!("writing log\n  at {}:{}\n  disk full\n", file!(), line),
+    /// );
+    /// ```
+    pub fn render_tree(&self) -> String {
+        let mut tree = String::new();
+        let _ = self.write_tree(&mut tree);
+        tree
+    }
+
+    /// Write the tree of [`render_tree()`][Error::render_tree] to `w`, one
+    /// frame at a time, without building the whole of it in a `String`
+    /// first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_error, Error};
+    /// use std::fmt::Write;
+    ///
+    /// let error = Err::<(), _>(Error::msg("disk full"))
+    ///     .map_err(anyhow_error!("writing log"))
+    ///     .unwrap_err();
+    ///
+    /// let mut log = String::from("request failed:\n");
+    /// error.write_tree(&mut log).unwrap();
+    /// assert!(log.ends_with("  disk full\n"));
+    /// ```
+    pub fn write_tree<W>(&self, w: &mut W) -> fmt::Result
+    where
+        W: Write + ?Sized,
+    {
+        for (depth, frame) in self.parsed_frames().enumerate() {
+            let indent = depth * 2;
+            let msg = if frame.msg.is_empty() {
+                "(no message)"
+            } else {
+                &frame.msg
+            };
+            for line in msg.lines() {
+                writeln!(w, "{:indent$}{}", "", line, indent = indent)?;
+            }
+            if !frame.file.is_empty() {
+                let (file, line) = (&frame.file, frame.line);
+                writeln!(w, "{:indent$}  at {}:{}", "", file, line, indent = indent)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`write_tree()`][Error::write_tree], for a byte sink such as a
+    /// file or a socket.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn write_tree_io<W>(&self, w: &mut W) -> std::io::Result<()>
+    where
+        W: std::io::Write + ?Sized,
+    {
+        // Keeps the io::Error that fmt::Error cannot carry.
+        struct Adapter<'a, W: ?Sized> {
+            inner: &'a mut W,
+            error: std::io::Result<()>,
+        }
+
+        impl<W> Write for Adapter<'_, W>
+        where
+            W: std::io::Write + ?Sized,
+        {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.inner.write_all(s.as_bytes()).map_err(|error| {
+                    self.error = Err(error);
+                    fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            inner: w,
+            error: Ok(()),
+        };
+        match self.write_tree(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => adapter.error,
+        }
+    }
+}
+
+// An entry of a `Caused by:` list without its indentation and, in a list of
+// several, its number.
+fn uncount(entry: &str) -> &str {
+    let entry = entry.trim();
+    let digits = entry.len() - entry.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && entry[digits..].starts_with(": ") {
+        &entry[digits + 2..]
+    } else {
+        entry
+    }
+}
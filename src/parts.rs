@@ -0,0 +1,148 @@
+use crate::alloc::{String, ToString, Vec};
+use crate::error::ErrorImpl;
+use crate::location::{untag, Location};
+use crate::template::render;
+use crate::Error;
+use core::iter;
+
+/// The pieces of an error that survive conversion to and from an
+/// application's own report type.
+///
+/// Produced from an [`Error`] for [`FromError::from_parts`], and turned back
+/// into one by [`FromError::into_error`]. Messages have their location tags
+/// removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportParts {
+    /// The outermost message.
+    pub message: String,
+    /// The message of every level below the outermost one, outermost first.
+    pub causes: Vec<String>,
+    /// Where the outermost level was created, if that was recorded.
+    pub location: Option<Location>,
+}
+
+impl ReportParts {
+    /// Take apart the chain of `error`.
+    pub fn new(error: &Error) -> Self {
+        let native = unsafe { ErrorImpl::native_len(error.inner.by_ref()) };
+        let mut parts = ReportParts {
+            message: String::new(),
+            causes: Vec::new(),
+            location: None,
+        };
+        for (n, level) in unsafe { ErrorImpl::chain(error.inner.by_ref()) }.enumerate() {
+            let tagged = level.to_string();
+            let (location, message) = match untag(&tagged) {
+                Some((location, message)) if n < native => (Some(location), message),
+                _ => (None, tagged),
+            };
+            if n == 0 {
+                parts.message = message;
+                parts.location = location;
+            } else {
+                parts.causes.push(message);
+            }
+        }
+        parts
+    }
+
+    /// Build an error with these messages as its chain.
+    ///
+    /// The innermost message becomes an ad-hoc error and the others context
+    /// on top of it. If there is a location, the outermost message is tagged
+    /// with it the way `anyhow_located!` would have, so that
+    /// [`locations()`][Error::locations] reports it again.
+    pub fn into_error(self) -> Error {
+        let outermost = match &self.location {
+            Some(location) => render(
+                location.file(),
+                location.line(),
+                location.column(),
+                "",
+                &self.message,
+            ),
+            None => self.message,
+        };
+        let mut levels = self.causes.into_iter().rev().chain(iter::once(outermost));
+        let mut error = Error::msg(levels.next().unwrap());
+        for level in levels {
+            error = error.context(level);
+        }
+        error
+    }
+}
+
+/// An application's own report type, convertible to and from
+/// `anyhow::Error`.
+///
+/// Implementations only say how to build the report from [`ReportParts`] and
+/// how to take it apart again. Walking the chain is left to anyhow, through
+/// [`ToReport::to_report`] and [`FromError::into_error`].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, FromError, ReportParts, ToReport};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct ApiError {
+///     title: String,
+///     details: Vec<String>,
+/// }
+///
+/// impl FromError for ApiError {
+///     fn from_parts(parts: ReportParts) -> Self {
+///         ApiError {
+///             title: parts.message,
+///             details: parts.causes,
+///         }
+///     }
+///
+///     fn into_parts(self) -> ReportParts {
+///         ReportParts {
+///             message: self.title,
+///             causes: self.details,
+///             location: None,
+///         }
+///     }
+/// }
+///
+/// let error = anyhow!("connection reset").context("fetching user");
+/// let report: ApiError = error.to_report();
+/// assert_eq!(report.title, "fetching user");
+///
+/// let error = report.into_error();
+/// assert_eq!(format!("{:#}", error), "fetching user: connection reset");
+/// ```
+pub trait FromError: Sized {
+    /// Build the report from the pieces of an error.
+    fn from_parts(parts: ReportParts) -> Self;
+
+    /// Take the report apart, for turning it back into an error.
+    fn into_parts(self) -> ReportParts;
+
+    /// Build the report from an error.
+    fn from_error(error: &Error) -> Self {
+        Self::from_parts(ReportParts::new(error))
+    }
+
+    /// Turn the report back into an error, by way of
+    /// [`ReportParts::into_error`].
+    fn into_error(self) -> Error {
+        self.into_parts().into_error()
+    }
+}
+
+/// Conversion of an error into an application's report type.
+///
+/// This is implemented for `anyhow::Error`. See [`FromError`] for an example.
+pub trait ToReport {
+    /// Convert into the report type `R`.
+    fn to_report<R: FromError>(&self) -> R;
+}
+
+impl ToReport for Error {
+    fn to_report<R: FromError>(&self) -> R {
+        R::from_error(self)
+    }
+}
@@ -0,0 +1,90 @@
+//! Protobuf records of errors, for embedding in gRPC error details.
+//!
+//! The types in this module are those prost generates from the schema in
+//! `proto/anyhow.proto`, which is part of the published crate for use by
+//! services written in other languages. Records are produced by
+//! [`Error::to_proto`] and encoded or decoded through [`prost::Message`].
+
+use crate::alloc::{String, ToString, Vec};
+use crate::error::ErrorImpl;
+use crate::location::untag;
+use crate::Error;
+
+/// One `anyhow::Error`, as a protobuf message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ErrorRecord {
+    /// The outermost message, with its location tag removed.
+    #[prost(string, tag = "1")]
+    pub message: String,
+    /// Where the outermost level was created, if that was recorded.
+    #[prost(message, optional, tag = "2")]
+    pub location: Option<Location>,
+    /// The message of every level of the chain, outermost first, with
+    /// location tags removed.
+    #[prost(string, repeated, tag = "3")]
+    pub chain: Vec<String>,
+    /// The code given to [`Context::context_coded`][crate::Context::context_coded],
+    /// or empty if there was none.
+    #[prost(string, tag = "4")]
+    pub code: String,
+}
+
+/// A source location, as a protobuf message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Location {
+    #[prost(string, tag = "1")]
+    pub file: String,
+    #[prost(uint32, tag = "2")]
+    pub line: u32,
+}
+
+impl Error {
+    /// Describe this error as a protobuf [`ErrorRecord`].
+    ///
+    /// This is the binary counterpart of
+    /// [`to_compact_json`][Error::to_compact_json], keeping every message of
+    /// the chain separately.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use prost::Message;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context_coded("write_failed", "write failed")
+    ///     .unwrap_err();
+    ///
+    /// let bytes = error.to_proto().encode_to_vec();
+    /// let record = anyhow::proto::ErrorRecord::decode(&bytes[..]).unwrap();
+    /// assert_eq!(record.chain, ["write failed", "disk full"]);
+    /// assert_eq!(record.code, "write_failed");
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "prost")))]
+    pub fn to_proto(&self) -> ErrorRecord {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let mut record = ErrorRecord {
+            message: String::new(),
+            location: None,
+            chain: Vec::new(),
+            code: self.code().unwrap_or("").to_string(),
+        };
+        for (n, level) in unsafe { ErrorImpl::chain(self.inner.by_ref()) }.enumerate() {
+            let tagged = level.to_string();
+            let (location, message) = match untag(&tagged) {
+                Some((location, message)) if n < native => (Some(location), message),
+                _ => (None, tagged),
+            };
+            if n == 0 {
+                record.message = message.clone();
+                record.location = location.map(|location| Location {
+                    file: location.file().to_string(),
+                    line: location.line(),
+                });
+            }
+            record.chain.push(message);
+        }
+        record
+    }
+}
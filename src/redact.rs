@@ -0,0 +1,83 @@
+use crate::error::ErrorImpl;
+use crate::fingerprint::mask;
+use crate::fmt::Indented;
+use crate::location::{line_range, split_tag};
+use crate::Error;
+use std::env;
+use std::fmt::Write;
+
+impl Error {
+    /// Render this error in a form that stays the same from one run to the
+    /// next, for snapshot tests.
+    ///
+    /// The layout is that of `{:?}`, with the message followed by a `Caused
+    /// by:` list, but never with a backtrace. Each message is rewritten so
+    /// that nothing incidental to the run remains:
+    ///
+    /// - The line number of every location tag is replaced by `LINE`, so
+    ///   `[src/db.rs:71 emsg(...)]` becomes `[src/db.rs:LINE emsg(...)]`.
+    /// - Paths under the current directory, in tags and in messages alike,
+    ///   are made relative to it.
+    /// - Numbers, UUIDs and hex ids are replaced by `#`, following the rules
+    ///   described under [`fingerprint()`][Error::fingerprint]. File names in
+    ///   tags are left alone.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow_located, Context};
+    ///
+    /// let error = anyhow_located!("no session {}", 4521).context("resuming");
+    ///
+    /// let redacted = error.redacted();
+    /// assert!(redacted.starts_with("resuming\n\nCaused by:\n    ["));
+    /// assert!(redacted.ends_with(":LINE emsg(no session #)]"));
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn redacted(&self) -> String {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let cwd = env::current_dir()
+            .ok()
+            .and_then(|dir| dir.to_str().map(|dir| format!("{}/", dir.trim_end_matches('/'))));
+        let relativize = |text: String| match &cwd {
+            Some(cwd) => text.replace(cwd.as_str(), ""),
+            None => text,
+        };
+
+        let levels: Vec<String> = self
+            .chain()
+            .enumerate()
+            .map(|(n, level)| {
+                let tagged = level.to_string();
+                match split_tag(&tagged) {
+                    Some((_, range)) if n < native => {
+                        let mut head = tagged[..range.start].to_owned();
+                        if let Some(line) = line_range(&tagged) {
+                            head.replace_range(line, ":LINE");
+                        }
+                        let body = mask(&tagged[range.clone()]);
+                        let tail = mask(&tagged[range.end..]);
+                        relativize(head) + &relativize(body) + &relativize(tail)
+                    }
+                    _ => relativize(mask(&tagged)),
+                }
+            })
+            .collect();
+
+        let mut redacted = levels[0].clone();
+        if levels.len() > 1 {
+            redacted.push_str("\n\nCaused by:");
+            let multiple = levels.len() > 2;
+            for (n, level) in levels[1..].iter().enumerate() {
+                redacted.push('\n');
+                let mut indented = Indented {
+                    inner: &mut redacted,
+                    number: if multiple { Some(n) } else { None },
+                    started: false,
+                };
+                let _ = indented.write_str(level);
+            }
+        }
+        redacted
+    }
+}
@@ -0,0 +1,81 @@
+use crate::error::ErrorImpl;
+use crate::Error;
+use core::fmt::{self, Debug, Display, Write};
+
+/// Renders an error in full: its message, every cause, and the backtrace if
+/// one was captured.
+///
+/// Returned by [`Error::report`]. The `Display` output is identical to the
+/// `{:?}` representation of the error.
+pub struct Report<'a> {
+    error: &'a Error,
+}
+
+impl Error {
+    /// Wrap a reference to this error for one-shot pretty printing.
+    ///
+    /// `println!("{}", error.report())` prints the same thing as
+    /// `println!("{:?}", error)`, for places where a `Display` impl is
+    /// required or where `{:?}` would read as a mistake.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+    ///     .context("failed to read config")
+    ///     .unwrap_err();
+    ///
+    /// eprintln!("{}", error.report());
+    /// ```
+    pub fn report(&self) -> Report<'_> {
+        Report { error: self }
+    }
+
+    /// The length in bytes of the `{:?}` representation of this error.
+    ///
+    /// The output is measured as it is produced rather than collected into a
+    /// `String`, so this does not allocate. It is meant for sizing a buffer
+    /// before writing the error into it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use std::fmt::Write;
+    ///
+    /// let error = anyhow!("oh no!").context("failed to read config");
+    /// let mut buffer = String::with_capacity(error.formatted_len());
+    /// write!(buffer, "{:?}", error).unwrap();
+    /// assert_eq!(buffer.len(), error.formatted_len());
+    /// ```
+    pub fn formatted_len(&self) -> usize {
+        let mut counter = ByteCount(0);
+        let _ = write!(counter, "{:?}", self);
+        counter.0
+    }
+}
+
+impl Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug(self.error.inner.by_ref(), f) }
+    }
+}
+
+impl Debug for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+// A fmt::Write sink that only counts the bytes written to it.
+struct ByteCount(usize);
+
+impl Write for ByteCount {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
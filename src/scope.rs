@@ -0,0 +1,79 @@
+use crate::Error;
+use core::fmt::Display;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Add context naming an async scope to any error produced by a future.
+///
+/// The returned future resolves to the same output as `future`. If that is
+/// an error, it is given a located context message consisting of `name`,
+/// tagged with the location of the call to `scoped` just as `anyhow_context!`
+/// would tag it, as `[file:line:column, emsg(name)]`, so that the message says
+/// which phase of the work failed.
+/// Successful results are passed through untouched, and `name` is not
+/// formatted unless there is an error.
+///
+/// Requires Rust 1.46 or newer, which is the first to support
+/// `#[track_caller]`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Result};
+///
+/// async fn fetch_manifest() -> Result<String> {
+///     Err(anyhow!("connection reset"))
+/// }
+///
+/// async fn deploy() -> Result<()> {
+///     let manifest = anyhow::scoped("fetching manifest", fetch_manifest()).await?;
+///     println!("{}", manifest);
+///     Ok(())
+/// }
+/// ```
+#[track_caller]
+pub fn scoped<C, F, T>(name: C, future: F) -> Scoped<C, F>
+where
+    C: Display,
+    F: Future<Output = Result<T, Error>>,
+{
+    Scoped {
+        future,
+        name,
+        caller: core::panic::Location::caller(),
+    }
+}
+
+/// Future returned by [`scoped`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Scoped<C, F> {
+    future: F,
+    name: C,
+    caller: &'static core::panic::Location<'static>,
+}
+
+impl<C, F, T> Future for Scoped<C, F>
+where
+    C: Display,
+    F: Future<Output = Result<T, Error>>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // Safety: `future` is structurally pinned and never moved out of; the
+        // other fields are not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match future.poll(cx) {
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error.context(crate::template::tag(
+                this.caller.file(),
+                this.caller.line(),
+                this.caller.column(),
+                ",",
+                &this.name,
+            )))),
+            poll => poll,
+        }
+    }
+}
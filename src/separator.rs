@@ -0,0 +1,117 @@
+use crate::alloc::{Box, Cow, String};
+use crate::END_OF_LINE;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+// The installed separator, or null for END_OF_LINE. Each separator installed
+// is leaked so that line_separator can hand out a 'static reference to it.
+static LINE_SEPARATOR: AtomicPtr<&'static str> = AtomicPtr::new(ptr::null_mut());
+
+/// Replace [`END_OF_LINE`] as the separator that `anyhow_error!` puts between
+/// its location-tagged message and the `{:?}` of the error it wraps.
+///
+/// This is for programs whose error messages can legitimately contain
+/// `<<EOL>>`, which would otherwise be mistaken for the end of a tagged
+/// message when locations are read back out of it. The separator is read by
+/// the macros each time they create an error, and by
+/// [`locations()`][crate::Error::locations] and [`extract_all`] when they
+/// parse one, so it should be set once at startup, before any error is
+/// created.
+///
+/// An empty separator could not be told apart from the text around it and is
+/// ignored, leaving the separator as it was.
+///
+/// [`extract_all`]: crate::extract_all
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow_error;
+/// use std::io;
+///
+/// anyhow::set_line_separator(" | ");
+///
+/// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+///     .map_err(anyhow_error!("write failed"))
+///     .unwrap_err();
+/// assert!(error.to_string().contains("emsg(write failed)] | "));
+/// ```
+pub fn set_line_separator(separator: &'static str) {
+    if separator.is_empty() {
+        return;
+    }
+    let separator = Box::into_raw(Box::new(separator));
+    LINE_SEPARATOR.store(separator, Ordering::SeqCst);
+}
+
+/// The separator set by [`set_line_separator`], or [`END_OF_LINE`] if none
+/// was.
+///
+/// The separator never appears inside the message of a location tag: the
+/// tagging macros write each occurrence of it in a message with a backslash
+/// before its last character, so `<<EOL>>` becomes `<<EOL>\>`, and the
+/// parsers turn it back. A separator of a single character is not escaped.
+pub fn line_separator() -> &'static str {
+    let separator = LINE_SEPARATOR.load(Ordering::SeqCst);
+    if separator.is_null() {
+        END_OF_LINE
+    } else {
+        unsafe { *separator }
+    }
+}
+
+// Tells the installed separators apart, for the caches of what was parsed
+// under one: each one installed is a new allocation.
+pub(crate) fn separator_id() -> usize {
+    LINE_SEPARATOR.load(Ordering::SeqCst) as usize
+}
+
+// The form that the separator takes inside a tagged message, or None for a
+// separator of one character, which cannot be escaped by inserting into it.
+fn escaped_separator(separator: &str) -> Option<String> {
+    let (last, _) = separator.char_indices().last()?;
+    if last == 0 {
+        return None;
+    }
+    let mut escaped = String::with_capacity(separator.len() + 1);
+    escaped.push_str(&separator[..last]);
+    escaped.push('\\');
+    escaped.push_str(&separator[last..]);
+    Some(escaped)
+}
+
+// Escapes every line separator in the part of `tagged` from `start` on, which
+// is a message just written into a tag.
+pub(crate) fn escape_from(tagged: &mut String, start: usize) {
+    let separator = line_separator();
+    if !tagged[start..].contains(separator) {
+        return;
+    }
+    if let Some(escaped) = escaped_separator(separator) {
+        let message = tagged[start..].replace(separator, &escaped);
+        tagged.truncate(start);
+        tagged.push_str(&message);
+    }
+}
+
+// The message with its line separators escaped, for writing into a tag.
+pub(crate) fn escape(message: &str) -> Cow<'_, str> {
+    let separator = line_separator();
+    match escaped_separator(separator) {
+        Some(ref escaped) if message.contains(separator) => {
+            Cow::Owned(message.replace(separator, escaped))
+        }
+        _ => Cow::Borrowed(message),
+    }
+}
+
+// Turns the escaped line separators of a tagged message back into the
+// separator.
+pub(crate) fn unescape(message: &str) -> Cow<'_, str> {
+    match escaped_separator(line_separator()) {
+        Some(ref escaped) if message.contains(escaped.as_str()) => {
+            Cow::Owned(message.replace(escaped.as_str(), line_separator()))
+        }
+        _ => Cow::Borrowed(message),
+    }
+}
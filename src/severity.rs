@@ -0,0 +1,87 @@
+use crate::alloc::String;
+use crate::Error;
+use core::fmt::Write;
+
+/// How serious an error is, for the benefit of logging and alerting.
+///
+/// Set with [`Error::with_severity`]. An error that was never given one is
+/// of severity [`Error`][Severity::Error].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The program cannot continue.
+    Fatal,
+    /// The operation failed.
+    Error,
+    /// The operation succeeded in part, or something looks wrong.
+    Warning,
+    /// Informational only.
+    Info,
+    /// Only of interest while debugging.
+    Debug,
+}
+
+impl Severity {
+    /// The syslog priority this severity maps to, from 0 (emergency) to 7
+    /// (debug).
+    pub fn syslog_priority(self) -> u8 {
+        match self {
+            Severity::Fatal => 2,
+            Severity::Error => 3,
+            Severity::Warning => 4,
+            Severity::Info => 6,
+            Severity::Debug => 7,
+        }
+    }
+}
+
+impl Default for Severity {
+    fn default() -> Self {
+        Severity::Error
+    }
+}
+
+// Wrapper that keeps the severity apart from an attachment of type Severity.
+struct Tagged(Severity);
+
+impl Error {
+    /// Give this error a severity, replacing any it had before.
+    ///
+    /// Like an attachment, the severity belongs to the error as a whole and
+    /// stays with it as context is added.
+    pub fn with_severity(self, severity: Severity) -> Self {
+        self.attach(Tagged(severity))
+    }
+
+    /// The severity given to [`with_severity`][Error::with_severity], or
+    /// [`Severity::Error`] if there was none.
+    pub fn severity(&self) -> Severity {
+        self.attachment::<Tagged>()
+            .map_or_else(Severity::default, |tagged| tagged.0)
+    }
+
+    /// Render this error as a single line for syslog or journald.
+    ///
+    /// The line starts with the `<N>` priority prefix understood by journald
+    /// and by syslog daemons reading from a stream, where `N` is the
+    /// [syslog priority][Severity::syslog_priority] of the error's
+    /// [severity][Error::severity]. It is followed by the chain as rendered by
+    /// `{:#}`, with any line breaks inside messages turned into spaces.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Error, Severity};
+    /// use std::io;
+    ///
+    /// let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+    ///     .context("write failed")
+    ///     .with_severity(Severity::Warning);
+    ///
+    /// assert_eq!(error.to_syslog(), "<4>write failed: disk full");
+    /// ```
+    pub fn to_syslog(&self) -> String {
+        let mut line = String::new();
+        let _ = write!(line, "<{}>{:#}", self.severity().syslog_priority(), self);
+        line.replace(|c| c == '\r' || c == '\n', " ")
+    }
+}
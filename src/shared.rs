@@ -0,0 +1,91 @@
+use crate::{Error, StdError};
+use core::fmt::{self, Debug, Display};
+use core::ops::Deref;
+use std::sync::Arc;
+
+/// An `anyhow::Error` that can be cloned and shared, for handing one failure
+/// to several consumers at once.
+///
+/// `Error` is not `Clone`. Wrapping it in a `SharedError` puts it behind an
+/// `Arc` so every clone observes the same error, while still giving access to
+/// all of `Error`'s methods through `Deref`: chains, downcasts, locations and
+/// attachments all work as they do on the original. A `SharedError` is also
+/// a `std::error::Error` itself, so it can become the source of a new
+/// `anyhow::Error`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, SharedError};
+/// use std::thread;
+///
+/// let error = SharedError::new(anyhow!("upstream unavailable"));
+///
+/// let handles: Vec<_> = (0..3)
+///     .map(|_| {
+///         let error = error.clone();
+///         thread::spawn(move || eprintln!("request failed: {:#}", error))
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// ```
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone)]
+pub struct SharedError {
+    inner: Arc<Error>,
+}
+
+impl SharedError {
+    /// Share the given error.
+    pub fn new(error: Error) -> Self {
+        SharedError {
+            inner: Arc::new(error),
+        }
+    }
+
+    /// Get back the error if this is the only handle to it, or else this
+    /// handle unchanged.
+    pub fn try_unwrap(self) -> Result<Error, Self> {
+        Arc::try_unwrap(self.inner).map_err(|inner| SharedError { inner })
+    }
+}
+
+impl From<Error> for SharedError {
+    fn from(error: Error) -> Self {
+        SharedError::new(error)
+    }
+}
+
+impl Deref for SharedError {
+    type Target = Error;
+
+    fn deref(&self) -> &Error {
+        &self.inner
+    }
+}
+
+impl AsRef<Error> for SharedError {
+    fn as_ref(&self) -> &Error {
+        &self.inner
+    }
+}
+
+impl Debug for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.inner, f)
+    }
+}
+
+impl Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&*self.inner, f)
+    }
+}
+
+impl StdError for SharedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner.source()
+    }
+}
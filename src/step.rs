@@ -0,0 +1,62 @@
+use crate::Error;
+use core::fmt::Display;
+
+#[cfg(feature = "std")]
+use crate::StdError;
+
+// The error type returned from the closure around each step of steps!. The
+// conversion into it is where `?` inside the step is seen, so that is where
+// the location of the failing step is recorded.
+pub struct StepError {
+    error: Error,
+    #[cfg(not(anyhow_no_track_caller))]
+    location: &'static core::panic::Location<'static>,
+}
+
+impl StepError {
+    #[allow(clippy::incompatible_msrv)] // gated by anyhow_no_track_caller
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn new(error: Error) -> Self {
+        StepError {
+            error,
+            #[cfg(not(anyhow_no_track_caller))]
+            location: core::panic::Location::caller(),
+        }
+    }
+}
+
+impl From<Error> for StepError {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(error: Error) -> Self {
+        StepError::new(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E> From<E> for StepError
+where
+    E: StdError + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(error: E) -> Self {
+        StepError::new(Error::from(error))
+    }
+}
+
+// Runs one step, labeling its error with the step's name. The file and line
+// of the steps! invocation are used only on compilers without track_caller.
+pub fn run<N, T, F>(name: N, file: &'static str, line: u32, step: F) -> Result<T, Error>
+where
+    N: Display,
+    F: FnOnce() -> Result<T, StepError>,
+{
+    step().map_err(|step| {
+        #[cfg(not(anyhow_no_track_caller))]
+        let (file, line) = {
+            let _ = (file, line);
+            (step.location.file(), step.location.line())
+        };
+        let tag = crate::template::render(file, line, None, ",", &name);
+        step.error.context(tag)
+    })
+}
@@ -0,0 +1,250 @@
+use crate::alloc::{Box, Cow, String};
+use crate::parens::balance_from;
+use crate::separator::escape_from;
+use core::fmt::{Display, Write};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+#[cfg(feature = "std")]
+use std::cell::Cell;
+
+// The installed template, or null for the built-in `[file:line:col emsg(msg)]`
+// format. It can be installed only once, and is leaked so that it stays valid
+// for as long as tags are rendered.
+static TAG_FORMAT: AtomicPtr<Template> = AtomicPtr::new(ptr::null_mut());
+
+/// A format for the location tags that the macros put on error messages,
+/// installed with [`set_tag_format`].
+///
+/// The template is copied through as-is, except for these placeholders:
+///
+/// - `{file}`: the file of the location, or under the `module-path` feature
+///   the module path and file, as the built-in format shows it.
+/// - `{line}`: the line of the location.
+/// - `{column}`: the column of the location, or nothing when the location
+///   does not have one.
+/// - `{msg}`: the message being tagged.
+///
+/// Any other `{` is copied through like the rest of the template.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    format: &'static str,
+}
+
+impl Template {
+    /// A template rendering tags as described by `format`.
+    pub const fn new(format: &'static str) -> Self {
+        Template { format }
+    }
+
+    /// The format string this template was created from.
+    pub fn as_str(&self) -> &'static str {
+        self.format
+    }
+}
+
+/// Replace the built-in `[file:line:col emsg(msg)]` format of the location
+/// tags put on messages by `anyhow_located!`, `anyhow_error!`,
+/// `anyhow_context!` and the other tagging macros and methods.
+///
+/// The format can be set only once, and should be set at startup before any
+/// error is created. A second call leaves the installed format in place and
+/// hands `template` back as the error, so that two threads racing to set it
+/// can never leave the format half-changed. To use another format on one
+/// thread for a while, see `with_tag_format`.
+///
+/// Only the built-in format can be parsed back: with a custom template,
+/// [`location()`][crate::Error::location], [`parse_frames`] and the other
+/// methods reading locations out of messages no longer find them.
+///
+/// [`parse_frames`]: crate::parse_frames
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow_located, Template};
+///
+/// anyhow::set_tag_format(Template::new("{file}:{line}: {msg}")).unwrap();
+///
+/// let error = anyhow_located!("disk full");
+/// let expected = format!("{}:{}: disk full", file!(), line!() - 1);
+/// assert!(error.to_string().ends_with(&expected));
+///
+/// assert!(anyhow::set_tag_format(Template::new("{msg}")).is_err());
+/// ```
+pub fn set_tag_format(template: Template) -> Result<(), Template> {
+    let installed = Box::into_raw(Box::new(template));
+    match TAG_FORMAT.compare_exchange(
+        ptr::null_mut(),
+        installed,
+        Ordering::SeqCst,
+        Ordering::SeqCst,
+    ) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(*unsafe { Box::from_raw(installed) }),
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    // The template installed on this thread by with_tag_format, if any.
+    static SCOPED_FORMAT: Cell<Option<Template>> = Cell::new(None);
+}
+
+/// Render location tags created on this thread with `template` for as long
+/// as `f` runs, in place of the format set by [`set_tag_format`] or the
+/// built-in one.
+///
+/// The previous format of the thread is restored when `f` returns, and also
+/// when it panics. Scopes can be nested, each restoring the format of the
+/// one around it. Other threads keep rendering tags in their own format the
+/// whole time.
+///
+/// As with [`set_tag_format`], only tags in the built-in format can be
+/// parsed back.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow_located, Template};
+///
+/// let compact = Template::new("{msg} @{line}");
+/// let error = anyhow::with_tag_format(compact, || anyhow_located!("disk full"));
+/// assert_eq!(error.to_string(), format!("disk full @{}", line!() - 1));
+///
+/// assert!(anyhow_located!("disk full").to_string().ends_with(" emsg(disk full)]"));
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn with_tag_format<F, R>(template: Template, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Restore(Option<Template>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_FORMAT.with(|scoped| scoped.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SCOPED_FORMAT.with(|scoped| scoped.replace(Some(template))));
+    f()
+}
+
+// The template tags are rendered with on this thread, or None for the
+// built-in format.
+fn current() -> Option<Template> {
+    #[cfg(feature = "std")]
+    {
+        // The thread-local is gone while the thread is shutting down;
+        // errors created then use the global format.
+        if let Ok(Some(template)) = SCOPED_FORMAT.try_with(Cell::get) {
+            return Some(template);
+        }
+    }
+    let template = TAG_FORMAT.load(Ordering::SeqCst);
+    if template.is_null() {
+        None
+    } else {
+        Some(unsafe { *template })
+    }
+}
+
+// Whether tags are rendered in the built-in format.
+pub(crate) fn builtin() -> bool {
+    current().is_none()
+}
+
+// The message of a location tag for the macros: `msg` tagged with the site,
+// line and column, with `punct` (empty or ",") after the site in the built-in
+// format.
+#[doc(hidden)]
+pub fn tag(site: &str, line: u32, column: u32, punct: &str, msg: &dyn Display) -> String {
+    render(site, line, Some(column), &span_punct(punct), msg)
+}
+
+// The `punct` of a tag made by a macro. With "tracing-integration", the
+// target and name of the span the thread is in are put after it as
+// ` span(target::name)`, when it is in one.
+pub(crate) fn span_punct(punct: &str) -> Cow<'_, str> {
+    #[cfg(feature = "tracing-integration")]
+    {
+        if let Some(metadata) = tracing::Span::current().metadata() {
+            return Cow::Owned(crate::private::format!(
+                "{} span({}::{})",
+                punct,
+                metadata.target(),
+                metadata.name(),
+            ));
+        }
+    }
+    Cow::Borrowed(punct)
+}
+
+// Like tag, for locations that may not have a column.
+pub(crate) fn render(
+    site: &str,
+    line: u32,
+    column: Option<u32>,
+    punct: &str,
+    msg: &dyn Display,
+) -> String {
+    // Room for the tag around a short message, so that rendering it takes a
+    // single allocation.
+    let mut tagged = String::with_capacity(site.len() + 64);
+    let template = match current() {
+        Some(template) => template,
+        None => {
+            let _ = write!(tagged, "[{}:{}", site, line);
+            if let Some(column) = column {
+                let _ = write!(tagged, ":{}", column);
+            }
+            let _ = write!(tagged, "{} emsg(", punct);
+            write_msg(&mut tagged, msg);
+            tagged.push_str(")]");
+            return tagged;
+        }
+    };
+
+    let mut rest = template.format;
+    while let Some(open) = rest.find('{') {
+        tagged.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let placeholder = match ["{file}", "{line}", "{column}", "{msg}"]
+            .iter()
+            .find(|placeholder| rest.starts_with(*placeholder))
+        {
+            Some(placeholder) => *placeholder,
+            None => {
+                tagged.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let _ = match placeholder {
+            "{file}" => write!(tagged, "{}", site),
+            "{line}" => write!(tagged, "{}", line),
+            "{column}" => match column {
+                Some(column) => write!(tagged, "{}", column),
+                None => Ok(()),
+            },
+            _ => {
+                write_msg(&mut tagged, msg);
+                Ok(())
+            }
+        };
+        rest = &rest[placeholder.len()..];
+    }
+    tagged.push_str(rest);
+    tagged
+}
+
+// Writes the message of a tag, with the line separators in it escaped and
+// its parentheses balanced.
+fn write_msg(tagged: &mut String, msg: &dyn Display) {
+    let start = tagged.len();
+    let _ = write!(tagged, "{}", msg);
+    escape_from(tagged, start);
+    balance_from(tagged, start);
+}
@@ -0,0 +1,34 @@
+use crate::alloc::String;
+use crate::Error;
+use core::fmt::Write;
+
+// The check of assert_error_at!: the message to panic with if the outermost
+// frame of `error` is not tagged with `line`, listing all of its frames.
+#[doc(hidden)]
+pub fn check_line(error: &Error, line: u32) -> Result<(), String> {
+    let mut message = match error.parsed_frames().next() {
+        Some(ref frame) if !frame.file.is_empty() && frame.line == line => return Ok(()),
+        Some(ref frame) if !frame.file.is_empty() => crate::private::format!(
+            "expected the error to come from line {}, but its outermost frame is at {}:{}",
+            line,
+            frame.file,
+            frame.line,
+        ),
+        _ => crate::private::format!(
+            "expected the error to come from line {}, but its outermost frame has no location",
+            line,
+        ),
+    };
+    message.push_str("\nframes:");
+    for frame in error.parsed_frames() {
+        if frame.file.is_empty() {
+            // Such as the Debug of a foreign error or a backtrace; its first
+            // line is enough to recognize it.
+            let first = frame.msg.lines().next().unwrap_or("");
+            let _ = write!(message, "\n    (no location) {}", first);
+        } else {
+            let _ = write!(message, "\n    {}:{} {}", frame.file, frame.line, frame.msg);
+        }
+    }
+    Err(message)
+}
@@ -0,0 +1,86 @@
+use crate::alloc::Box;
+use crate::error::ErrorImpl;
+use crate::meta::Header;
+use crate::Error;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+// The installed build version, or null. Each version installed is leaked in a
+// node that links to the one installed before it, so that errors can keep the
+// number of the version they were created under rather than the version.
+static BUILD_VERSION: AtomicPtr<Version> = AtomicPtr::new(ptr::null_mut());
+
+struct Version {
+    version: &'static str,
+    // Counts up from 1 for the first version installed.
+    number: u32,
+    previous: *mut Version,
+}
+
+/// Stamp every error created from now on with the given build version.
+///
+/// This is meant to be called once at startup with an identifier of the
+/// running build, such as a git commit hash embedded at compile time, so
+/// that a reported error can be traced back to the release that produced it.
+/// Errors keep the version that was set when they were created; it is read
+/// back with [`Error::build_version`].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::anyhow;
+///
+/// anyhow::set_build_version(option_env!("GIT_SHA").unwrap_or("dev"));
+///
+/// let error = anyhow!("oh no!");
+/// eprintln!("{:#} (build {})", error, error.build_version().unwrap());
+/// ```
+pub fn set_build_version(version: &'static str) {
+    let mut current = BUILD_VERSION.load(Ordering::SeqCst);
+    let node = Box::into_raw(Box::new(Version {
+        version,
+        number: 0,
+        previous: ptr::null_mut(),
+    }));
+    loop {
+        unsafe {
+            (*node).number = current.as_ref().map_or(1, |current| current.number + 1);
+            (*node).previous = current;
+        }
+        match BUILD_VERSION.compare_exchange(current, node, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return,
+            Err(stored) => current = stored,
+        }
+    }
+}
+
+// Metadata for a new error, stamped with the number of the build version if
+// one is set.
+pub(crate) fn stamp() -> Header {
+    let version = BUILD_VERSION.load(Ordering::SeqCst);
+    Header {
+        version: unsafe { version.as_ref() }.map_or(0, |version| version.number),
+        ..Header::default()
+    }
+}
+
+// The version with the given number, looked up from the latest one back.
+fn lookup(number: u32) -> Option<&'static str> {
+    let mut version = unsafe { BUILD_VERSION.load(Ordering::SeqCst).as_ref() }?;
+    while version.number > number {
+        version = unsafe { version.previous.as_ref() }?;
+    }
+    if version.number == number {
+        Some(version.version)
+    } else {
+        None
+    }
+}
+
+impl Error {
+    /// The build version that was set by [`set_build_version`] when this
+    /// error was created, if any.
+    pub fn build_version(&self) -> Option<&'static str> {
+        lookup(unsafe { ErrorImpl::header(self.inner.by_ref()) }.version)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::error::ErrorImpl;
+use crate::location::{untag, Location};
+use crate::Error;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+
+// The serialized form of an error, shared by Error and DeserializedError.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Record {
+    message: String,
+    location: Option<Site>,
+    chain: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Site {
+    file: String,
+    line: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    column: Option<u32>,
+}
+
+impl Site {
+    fn new(location: &Location) -> Self {
+        Site {
+            file: location.file().to_owned(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+
+    fn into_location(self) -> Location {
+        let location = Location::new(self.file, self.line);
+        match self.column {
+            Some(column) => location.with_column(column),
+            None => location,
+        }
+    }
+}
+
+/// Serialized as an object with these fields:
+///
+/// - `"message"`: the outermost message, with its location tag removed.
+/// - `"location"`: where the outermost level was created, as an object with
+///   `"file"`, `"line"` and, if it was recorded, `"column"`, or null if the
+///   location was not recorded.
+/// - `"chain"`: the message of every level of the chain, outermost first,
+///   exactly as displayed, location tags included.
+///
+/// The result can be read back as a [`DeserializedError`].
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let native = unsafe { ErrorImpl::native_len(self.inner.by_ref()) };
+        let chain: Vec<String> = unsafe { ErrorImpl::chain(self.inner.by_ref()) }
+            .map(ToString::to_string)
+            .collect();
+        let (location, message) = match untag(&chain[0]) {
+            Some((location, message)) if native > 0 => (Some(location), message),
+            _ => (None, chain[0].clone()),
+        };
+        Record {
+            message,
+            location: location.as_ref().map(Site::new),
+            chain,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// An error read back from the serialized form of an [`Error`], for errors
+/// received over the wire.
+///
+/// The types of the original errors do not survive serialization, so this
+/// keeps only their messages and the location of the outermost one. It is
+/// an error in its own right, whose [`source()`][StdError::source] chain has
+/// one level for each level of the original chain, so that converting it
+/// into an `anyhow::Error` with `?` or `From` gives an error with the same
+/// `Display` and chain as the one that was serialized.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, DeserializedError, Error};
+///
+/// let error = anyhow!("connection reset").context("fetching user");
+/// let json = serde_json::to_string(&error).unwrap();
+///
+/// let received: DeserializedError = serde_json::from_str(&json).unwrap();
+/// assert_eq!(received.message(), "fetching user");
+///
+/// let error = Error::from(received);
+/// assert_eq!(error.chain().count(), 2);
+/// ```
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+#[derive(Debug)]
+pub struct DeserializedError {
+    display: String,
+    message: String,
+    location: Option<Location>,
+    source: Option<Box<DeserializedError>>,
+}
+
+impl DeserializedError {
+    /// The message of this level, with its location tag removed.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where this level was created, if that was recorded.
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    fn level(display: String, source: Option<Box<DeserializedError>>) -> Self {
+        let (location, message) = match untag(&display) {
+            Some((location, message)) => (Some(location), message),
+            None => (None, display.clone()),
+        };
+        DeserializedError {
+            display,
+            message,
+            location,
+            source,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DeserializedError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Record {
+            message,
+            location,
+            chain,
+        } = Record::deserialize(deserializer)?;
+        let mut levels = chain.into_iter().rev();
+        let mut source = None;
+        let outermost = levels.next_back();
+        for display in levels.by_ref() {
+            source = Some(Box::new(DeserializedError::level(display, source)));
+        }
+        Ok(DeserializedError {
+            display: outermost.unwrap_or_else(|| message.clone()),
+            message,
+            location: location.map(Site::into_location),
+            source,
+        })
+    }
+}
+
+impl Serialize for DeserializedError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut chain = Vec::new();
+        let mut level = Some(self);
+        while let Some(error) = level {
+            chain.push(error.display.clone());
+            level = error.source.as_ref().map(|source| &**source);
+        }
+        Record {
+            message: self.message.clone(),
+            location: self.location.as_ref().map(Site::new),
+            chain,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Display for DeserializedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+impl StdError for DeserializedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.source {
+            Some(source) => Some(&**source),
+            None => None,
+        }
+    }
+}
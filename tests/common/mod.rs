@@ -1,3 +1,6 @@
+// Not every test file that includes this module uses all of it.
+#![allow(dead_code)]
+
 use anyhow::{bail, Result};
 use std::io;
 
@@ -12,3 +15,17 @@ pub fn bail_fmt() -> Result<()> {
 pub fn bail_error() -> Result<()> {
     bail!(io::Error::new(io::ErrorKind::Other, "oh no!"));
 }
+
+// The file part of the tags made by the macros in the file this is expanded
+// in, which under "module-path" starts with the module path. It is a macro so
+// that `file!()` and `module_path!()` are those of the caller.
+#[macro_export]
+macro_rules! site {
+    () => {
+        if cfg!(feature = "module-path") {
+            concat!(module_path!(), " ", file!())
+        } else {
+            file!()
+        }
+    };
+}
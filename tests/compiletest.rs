@@ -4,4 +4,15 @@
 fn ui() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/ui/pass/*.rs");
+}
+
+#[cfg(feature = "deny-empty-messages")]
+#[rustversion::attr(not(nightly), ignore)]
+#[cfg_attr(miri, ignore)]
+#[test]
+fn deny_empty_messages() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/deny-empty-messages/*.rs");
+    t.pass("tests/ui/deny-empty-messages/pass/*.rs");
 }
@@ -0,0 +1,5 @@
+// Included by tests/test_relative_paths.rs through an absolute path, so that
+// file!() in here is absolute.
+fn tagged() -> anyhow::Error {
+    anyhow::anyhow_located!("oh no!")
+}
@@ -0,0 +1,55 @@
+//! Uses every tagging macro through a renamed dependency, for
+//! tests/test_renamed.rs to check that their expansions refer to the crate
+//! through `$crate` only.
+
+#![deny(warnings)]
+
+use anyhow2::{
+    anyhow, anyhow_code, anyhow_context, anyhow_error, anyhow_located, anyhow_wrap, bail,
+    bail_located, ctx, ensure, ensure_ctx, ensure_eq, ensure_located, ensure_ne, ensure_with,
+    loc_context, Error, Result,
+};
+use std::io;
+
+fn io_error() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+}
+
+pub fn errors() -> Vec<Error> {
+    let value = 1;
+    let mut errors = vec![
+        anyhow!("oh no!"),
+        anyhow_located!("oh no {}", value),
+        anyhow_located!(),
+        anyhow_code!("E1", "oh no!"),
+        io_error().map_err(anyhow_wrap!("writing")).unwrap_err(),
+        io_error().map_err(ctx!("writing {}", value)).unwrap_err(),
+        loc_context!(io_error(), "writing").unwrap_err(),
+    ];
+    for error in errors.drain(..).collect::<Vec<_>>() {
+        errors.push(
+            Err::<(), _>(error)
+                .map_err(anyhow_error!("again"))
+                .unwrap_err(),
+        );
+    }
+    errors.push(
+        Err::<(), _>(anyhow!("x"))
+            .map_err(anyhow_context!())
+            .unwrap_err(),
+    );
+    errors
+}
+
+pub fn checks(value: i32) -> Result<()> {
+    ensure!(value > 0);
+    ensure_located!(value > 1, "too small: {}", value);
+    ensure_ctx!(value > 2, || "detail");
+    ensure_with!(value > 3, || "lazy");
+    ensure_eq!(value, 4);
+    ensure_ne!(value, 5);
+    if value > 6 {
+        bail_located!("too big");
+    }
+    bail!("done")
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use anyhow::{Context, Error};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt::{self, Display};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let value = f();
+    (value, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_static_context_allocates_once() {
+    let error = Error::msg("oh no!");
+    let (error, n) = allocations(|| error.context("loading config"));
+    assert_eq!(n, 1);
+
+    let context: Cow<'static, str> = Cow::Borrowed("starting up");
+    let (error, n) = allocations(|| error.context(context));
+    assert_eq!(n, 1);
+
+    assert_eq!(
+        format!("{:#}", error),
+        "starting up: loading config: oh no!",
+    );
+}
+
+#[test]
+fn test_context_args_allocates_once() {
+    let result = Err::<(), _>(Error::msg("oh no!"));
+    let (result, n) = allocations(|| result.with_context_args(|| format_args!("loading config")));
+    assert_eq!(n, 1);
+
+    // The same context as an owned String costs an extra allocation, and
+    // with "with-location" a tagged level of its own on top.
+    let (result, n) = allocations(|| result.with_context(|| "starting up".to_owned()));
+    assert_eq!(
+        n,
+        if cfg!(feature = "with-location") {
+            4
+        } else {
+            2
+        }
+    );
+
+    let (error, n) = allocations(|| None::<()>.with_context_args(|| format_args!("no config")));
+    assert_eq!(n, 1);
+    assert_eq!(error.unwrap_err().to_string(), "no config");
+
+    let rendered = format!("{:#}", result.unwrap_err());
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(rendered, "starting up: loading config: oh no!");
+    #[cfg(feature = "with-location")]
+    assert!(rendered.ends_with(", emsg(starting up)]: starting up: loading config: oh no!"));
+}
+
+#[test]
+fn test_literal_allocates_once() {
+    let (error, n) = allocations(|| anyhow::anyhow_located!("oh no!"));
+    let line = line!() - 1;
+    // One allocation for the tagged message and one for the error itself.
+    assert_eq!(n, 2);
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:37 emsg(oh no!)]", site!(), line),
+    );
+
+    // Formatting the literal into a String of its own first, as anyhow!
+    // used to do, costs an extra allocation for the same output.
+    #[allow(clippy::format_in_format_args, clippy::useless_format)]
+    let (old, n) = allocations(|| {
+        Error::msg(format!(
+            "[{}:{}:37 emsg({})]",
+            file!(),
+            line,
+            format!("oh no!"),
+        ))
+    });
+    assert_eq!(n, 3);
+    assert_eq!(old.to_string(), error.to_string().replace(site!(), file!()));
+}
+
+#[test]
+fn test_location_ref_does_not_allocate() {
+    let error = anyhow::anyhow_located!("oh no!");
+    let line = line!() - 1;
+    let (location, n) = allocations(|| error.location_ref());
+    assert_eq!(n, 0);
+    let location = location.unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+    assert_eq!(location.column(), Some(17));
+
+    let error = Err::<(), _>(error)
+        .map_err(anyhow::anyhow_context!("loading config"))
+        .unwrap_err();
+    let line = line!() - 2;
+    let (location, n) = allocations(|| error.location_ref());
+    assert_eq!(n, 0);
+    assert_eq!(location.unwrap().line(), line);
+    assert_eq!(
+        location.unwrap().to_string(),
+        error.location().unwrap().to_string()
+    );
+
+    let error = Error::msg_at("oh no!", "src/db.rs", 7);
+    let (location, n) = allocations(|| error.location_ref());
+    assert_eq!(n, 0);
+    assert_eq!(location.unwrap().file(), "src/db.rs");
+
+    let error = Error::msg("oh no!");
+    let (location, n) = allocations(|| error.location_ref());
+    assert_eq!(n, 0);
+    assert!(location.is_none());
+}
+
+#[test]
+fn test_build_version_does_not_allocate() {
+    // The version is process-wide, but no other test in this file reads it.
+    anyhow::set_build_version("1.2.3-abcdef0");
+    let (error, n) = allocations(|| Error::msg("oh no!"));
+    assert_eq!(n, 1);
+    assert_eq!(error.build_version(), Some("1.2.3-abcdef0"));
+    let (error, n) = allocations(|| error.context("loading config"));
+    assert_eq!(n, 1);
+    assert_eq!(error.build_version(), Some("1.2.3-abcdef0"));
+}
+
+#[derive(Debug)]
+struct Unit;
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("oh no!")
+    }
+}
+
+impl std::error::Error for Unit {}
+
+#[test]
+fn test_question_mark_allocates_once() {
+    fn fail() -> anyhow::Result<()> {
+        Err(Unit)?;
+        Ok(())
+    }
+
+    // The site `?` records is kept inline, next to the error.
+    let (result, n) = allocations(fail);
+    assert_eq!(n, 1);
+    let error = result.unwrap_err();
+    let line = line!() - 8;
+    let sites = error.propagations();
+    assert_eq!(sites.len(), 1);
+    assert_eq!((sites[0].file(), sites[0].line()), (file!(), line));
+
+    let error = error.propagated();
+    assert_eq!(error.propagations()[1].line(), line!() - 1);
+}
+
+#[test]
+fn test_formatted_len_does_not_allocate() {
+    let error = Err::<(), _>(Error::msg("oh no!"))
+        .map_err(anyhow::anyhow_error!("loading config"))
+        .context("starting up")
+        .unwrap_err();
+    let (len, n) = allocations(|| error.formatted_len());
+    assert_eq!(n, 0);
+    assert_eq!(len, format!("{:?}", error).len());
+}
+
+#[test]
+fn test_deep_chain_is_linear() {
+    fn wrap(error: Error, n: usize) -> Error {
+        Err::<(), _>(error)
+            .map_err(anyhow::anyhow_error!("level {}", n))
+            .unwrap_err()
+    }
+
+    // Every level past the first adds its tag to the list of those below it
+    // rather than wrapping the error again, which costs the tag's allocation
+    // and, now and then, the growth of the list.
+    let mut error = wrap(Error::msg("disk full"), 0);
+    let mut costs = Vec::new();
+    for n in 1..50 {
+        let (wrapped, n) = allocations(|| wrap(error, n));
+        error = wrapped;
+        costs.push(n);
+    }
+    assert!(costs[24..].iter().sum::<usize>() <= 26, "{:?}", costs);
+
+    // All 50 levels are one message, rendered in one pass.
+    assert_eq!(error.chain().count(), 1);
+    assert_eq!(error.emsg(), Some("level 49"));
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!(frames.len(), 51);
+    assert_eq!(frames[49].msg, "level 0");
+    assert_eq!(frames[50].msg, "disk full");
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Context, Error, Result};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+struct StatusCode(u16);
+
+#[derive(Debug, PartialEq)]
+struct RequestId(u64);
+
+fn find_user() -> Result<()> {
+    Err(anyhow!("no such user").with_status(StatusCode(404)))
+}
+
+#[test]
+fn test_status() {
+    let error = find_user()
+        .context("loading profile")
+        .context("rendering page")
+        .unwrap_err();
+    assert_eq!(error.status(), Some(&StatusCode(404)));
+    let levels = if cfg!(feature = "with-location") {
+        5
+    } else {
+        3
+    };
+    assert_eq!(error.chain().count(), levels);
+
+    // A status is not an attachment of the same type, and vice versa.
+    assert_eq!(error.attachment::<StatusCode>(), None);
+    let error = error.attach(StatusCode(200));
+    assert_eq!(error.status(), Some(&StatusCode(404)));
+    assert_eq!(error.attachment(), Some(&StatusCode(200)));
+
+    let error = error.with_status(StatusCode(503));
+    assert_eq!(error.status(), Some(&StatusCode(503)));
+    assert_eq!(error.status::<u16>(), None);
+}
+
+#[test]
+fn test_attachment() {
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert_eq!(error.attachment::<RequestId>(), None);
+
+    let error = error
+        .attach(RequestId(1))
+        .attach("tag")
+        .attach(RequestId(2));
+    let error = Err::<(), _>(error).context("outer").unwrap_err();
+    assert_eq!(error.get(), Some(&RequestId(2)));
+    assert_eq!(error.get(), Some(&"tag"));
+    assert_eq!(error.attachment(), Some(&RequestId(2)));
+    assert_eq!(error.get::<u32>(), None);
+    assert!(error.root_cause().is::<io::Error>());
+}
+
+#[test]
+fn test_suppressed() {
+    let error = Error::msg("write failed");
+    assert!(error.suppressed().is_empty());
+
+    let error = error.also(Error::msg("cleanup failed"));
+    let error = error.also(Error::msg("unlock failed").context("releasing lock"));
+    let error = Err::<(), _>(error).context("saving").unwrap_err();
+
+    let suppressed: Vec<_> = error
+        .suppressed()
+        .iter()
+        .map(|e| format!("{:#}", e))
+        .collect();
+    assert_eq!(
+        suppressed,
+        ["cleanup failed", "releasing lock: unlock failed"]
+    );
+
+    // Display is that of the primary error alone.
+    #[cfg(not(feature = "with-location"))]
+    {
+        assert_eq!(format!("{:#}", error), "saving: write failed");
+
+        let expected = "\
+saving
+
+Caused by:
+    write failed
+
+Suppressed:
+    0: cleanup failed
+    1: releasing lock: unlock failed";
+        assert_eq!(format!("{:?}", error), expected);
+    }
+
+    let error = Error::msg("write failed").also(Error::msg("cleanup failed"));
+    let expected = "write failed\n\nSuppressed:\n    cleanup failed";
+    assert_eq!(format!("{:?}", error), expected);
+}
+
+#[test]
+fn test_context_coded() {
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "oh no!"))
+        .context_coded("not_found", "loading profile")
+        .context("rendering page")
+        .unwrap_err();
+    assert_eq!(error.code(), Some("not_found"));
+    #[cfg(not(feature = "with-location"))]
+    {
+        let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+        assert_eq!(chain, ["rendering page", "loading profile", "oh no!"]);
+    }
+    assert!(error.root_cause().is::<io::Error>());
+
+    // A later code replaces an earlier one.
+    let error = Err::<(), _>(error)
+        .context_coded("unavailable", "serving request")
+        .unwrap_err();
+    assert_eq!(error.code(), Some("unavailable"));
+    assert_eq!(error.to_string(), "serving request");
+
+    let error = None::<()>.context_coded("missing", "no value").unwrap_err();
+    assert_eq!(error.code(), Some("missing"));
+    assert_eq!(format!("{:#}", error), "no value");
+
+    assert_eq!(Error::msg("uncoded").code(), None);
+}
+
+#[test]
+fn test_attempts() {
+    let mut error = Error::msg("connection refused");
+    assert_eq!(error.attempts(), 0);
+    for attempt in 1..=5 {
+        error = error.bump_attempt();
+        assert_eq!(error.attempts(), attempt);
+        error = Err::<(), _>(error)
+            .with_context(|| format!("attempt {}", attempt))
+            .unwrap_err();
+    }
+    assert_eq!(error.attempts(), 5);
+    let levels = if cfg!(feature = "with-location") {
+        11
+    } else {
+        6
+    };
+    assert_eq!(error.chain().count(), levels);
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(error.to_string(), "attempt 5");
+}
+
+#[test]
+fn test_fields() {
+    let fields = vec![
+        ("port".to_owned(), "70000".to_owned()),
+        ("host".to_owned(), String::new()),
+        ("retries".to_owned(), "-1".to_owned()),
+    ];
+    let error = Error::from_fields("invalid config", fields.clone());
+    let line = line!() - 1;
+    assert_eq!(error.fields(), &fields[..]);
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17 emsg(invalid config)]", file!(), line),
+    );
+
+    let error = error.context("starting server");
+    assert_eq!(error.fields(), &fields[..]);
+
+    assert!(Error::msg("no fields").fields().is_empty());
+}
+
+#[test]
+fn test_url() {
+    let error = Error::msg("unsupported version");
+    assert_eq!(error.url(), None);
+    assert!(!format!("{:?}", error).contains("See:"));
+
+    let error = error
+        .with_url("https://example.com/errors/E1")
+        .context("loading config")
+        .with_url("https://example.com/errors/E2")
+        .context("starting up");
+    assert_eq!(error.url(), Some("https://example.com/errors/E2"));
+    assert_eq!(error.to_string(), "starting up");
+
+    let debug = format!("{:?}", error);
+    assert!(debug.starts_with("starting up\n\nCaused by:\n"));
+    assert!(debug.contains("\n\nSee: https://example.com/errors/E2"));
+    assert!(!debug.contains("E1"));
+    assert_eq!(error.report().to_string(), debug);
+}
+
+#[test]
+fn test_attach_printable() {
+    struct RequestId(u64);
+
+    impl std::fmt::Display for RequestId {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "request id: {}", self.0)
+        }
+    }
+
+    let error = Error::msg("upstream timed out")
+        .attach(StatusCode(504))
+        .attach_printable(RequestId(7))
+        .context("rendering page")
+        .attach_printable("tenant: acme\nregion: eu");
+    assert_eq!(error.attachment::<RequestId>().unwrap().0, 7);
+    assert_eq!(
+        error.attachment::<&str>(),
+        Some(&"tenant: acme\nregion: eu")
+    );
+    assert_eq!(error.attachment::<StatusCode>(), Some(&StatusCode(504)));
+    assert_eq!(error.to_string(), "rendering page");
+
+    let debug = format!("{:?}", error);
+    let expected = "\
+rendering page
+
+Caused by:
+    upstream timed out
+
+Attachments:
+    request id: 7
+    tenant: acme
+    region: eu";
+    assert!(debug.starts_with(expected), "{}", debug);
+    assert!(!format!("{:?}", Error::msg("oh no!")).contains("Attachments:"));
+}
+
+#[test]
+fn test_all_fields() {
+    let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(field, value)| (field.to_owned(), value.to_owned()))
+            .collect()
+    };
+
+    assert!(Error::msg("oh no!").all_fields().is_empty());
+
+    let error = Error::from_fields("query failed", pairs(&[("table", "users"), ("retry", "0")]))
+        .context("loading profile")
+        .with_fields(pairs(&[("user", "7"), ("retry", "2")]))
+        .context("handling request");
+    assert_eq!(error.fields(), &pairs(&[("user", "7"), ("retry", "2")])[..]);
+    assert_eq!(
+        error.all_fields(),
+        [("user", "7"), ("retry", "2"), ("table", "users")],
+    );
+}
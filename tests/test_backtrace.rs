@@ -11,3 +11,92 @@ fn test_backtrace() {
     let error = anyhow!("oh no!");
     let _ = error.backtrace();
 }
+
+#[test]
+fn test_backtrace_captured_once() {
+    use anyhow::{anyhow_context, anyhow_located, Context};
+    use std::io;
+
+    let root = anyhow_located!("oh no!");
+    let has_backtrace = cfg!(any(backtrace, feature = "backtrace"));
+    assert_eq!(root.has_own_backtrace(), has_backtrace);
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    let origin: *const _ = root.backtrace();
+
+    let error = Err::<(), _>(root)
+        .context("first")
+        .map_err(anyhow_context!("second"))
+        .with_context(|| "third")
+        .unwrap_err()
+        .context("fourth");
+    let levels = if cfg!(feature = "with-location") {
+        7
+    } else {
+        5
+    };
+    assert_eq!(error.chain().count(), levels);
+    assert!(!error.has_own_backtrace());
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    assert_eq!(error.backtrace() as *const _, origin);
+
+    // A foreign error without a backtrace gets one where it is first wrapped.
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io).context("write failed").unwrap_err();
+    assert_eq!(error.has_own_backtrace(), has_backtrace);
+    assert!(!error.context("saving").has_own_backtrace());
+}
+
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[test]
+fn test_backtrace_starts_at_tag() {
+    use anyhow::{anyhow_located, Error};
+    use std::io;
+
+    // The first frame of the backtrace that is not inside anyhow is where
+    // the tag says the error was created.
+    fn first_frame(error: &Error) -> String {
+        let backtrace = error.backtrace().to_string();
+        let line = backtrace
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("at ") && !line.starts_with("at ./src/"))
+            .unwrap_or_else(|| panic!("{}", backtrace));
+        line.trim_start_matches("at ./").to_owned()
+    }
+
+    let error = anyhow_located!("oh no!");
+    if error.backtrace().to_string() == "disabled backtrace" {
+        return;
+    }
+    assert_eq!(first_frame(&error), error.location().unwrap().to_string());
+
+    let error = anyhow_located!(String::from("oh no!"));
+    assert_eq!(first_frame(&error), error.location().unwrap().to_string());
+
+    let error = anyhow_located!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert_eq!(first_frame(&error), error.location().unwrap().to_string());
+
+    let error = Error::msg_at("oh no!", file!(), line!());
+    let line = line!() - 1;
+    assert_eq!(first_frame(&error), format!("{}:{}:17", file!(), line));
+}
+
+#[test]
+fn test_deep_chain_one_backtrace() {
+    use anyhow::{anyhow_error, anyhow_located, Result};
+
+    // A 50-deep chain built like examples/result_error.rs carries the
+    // backtrace of its root and no other, so its message grows by a frame
+    // per level rather than by a backtrace per level.
+    let mut result: Result<()> = Err(anyhow_located!("root"));
+    for n in 0..50 {
+        result = result.map_err(anyhow_error!("level {}", n));
+    }
+    let error = result.unwrap_err();
+    let message = error.to_string();
+    assert!(message.matches("Stack backtrace:").count() <= 1);
+    assert_eq!(message.matches(" emsg(").count(), 51);
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!(frames[0].msg, "level 49");
+    assert_eq!(frames[50].msg, "root");
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use anyhow::{anyhow, anyhow_basic, format_err, format_err_basic, Error};
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+use std::io;
+
+#[derive(Debug)]
+struct ConfigError {
+    cause: io::Error,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("reading config")
+    }
+}
+
+impl StdError for ConfigError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+#[test]
+fn test_literal() {
+    assert_eq!(anyhow_basic!("oh no!").to_string(), "oh no!");
+    assert_eq!(anyhow_basic!("oh no!",).to_string(), "oh no!");
+    assert_eq!(format_err_basic!("oh no!").to_string(), "oh no!");
+}
+
+#[test]
+fn test_fmt() {
+    let user = 7;
+    assert_eq!(anyhow_basic!("no user {}", user).to_string(), "no user 7");
+    assert_eq!(format_err_basic!("{}-{}", 1, 2).to_string(), "1-2");
+}
+
+#[test]
+fn test_format_err() {
+    assert_eq!(format_err!("x").to_string(), "x");
+    assert_eq!(format_err!("{}-{}", 1, 2).to_string(), "1-2");
+    let cause = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    assert!(format_err!(ConfigError { cause }).is::<ConfigError>());
+
+    #[cfg(not(any(feature = "compat", feature = "structured-location")))]
+    {
+        let error = anyhow!("x");
+        let line = line!() - 1;
+        assert_eq!(
+            error.to_string(),
+            format!("[{}:{}:21 emsg(x)]", site!(), line)
+        );
+    }
+}
+
+#[test]
+fn test_expr() {
+    let cause = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    let error = anyhow_basic!(ConfigError { cause });
+    assert_eq!(error.to_string(), "reading config");
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["reading config", "no such file"]);
+    assert_eq!(
+        error
+            .root_cause()
+            .downcast_ref::<io::Error>()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::NotFound,
+    );
+    let error = error.downcast::<ConfigError>().unwrap();
+    assert_eq!(error.cause.kind(), io::ErrorKind::NotFound);
+
+    // A value without an Error impl becomes the message.
+    let message = String::from("oh no!");
+    assert_eq!(anyhow_basic!(message).to_string(), "oh no!");
+
+    // An anyhow::Error is passed through untouched, tag included.
+    let located = anyhow!("oh no!");
+    let tagged = located.to_string();
+    let error: Error = anyhow_basic!(located);
+    assert_eq!(error.to_string(), tagged);
+    assert!(error.source().is_none());
+}
+
+#[test]
+fn test_into_plain() {
+    let debug = |error: &Error| {
+        let debug = format!("{:?}", error);
+        debug
+            .split("\n\nStack backtrace:")
+            .next()
+            .unwrap()
+            .to_owned()
+    };
+
+    let plain = anyhow!("x").into_plain();
+    let upstream = anyhow_basic!("x");
+    assert_eq!(plain.to_string(), upstream.to_string());
+    assert_eq!(debug(&plain), debug(&upstream));
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+        .map_err(anyhow::anyhow_wrap!("reading {}", "config"))
+        .map_err(anyhow::anyhow_error!("starting up"))
+        .map_err(anyhow::anyhow_context!("running"))
+        .unwrap_err();
+    let plain = error.into_plain();
+    assert_eq!(plain.chain().count(), 2);
+    assert_eq!(
+        format!("{:#}", plain),
+        "running: starting up: reading config: no such file",
+    );
+    assert!(!debug(&plain).contains(file!()));
+
+    let error = anyhow::anyhow_located!(ConfigError {
+        cause: io::Error::new(io::ErrorKind::NotFound, "no such file"),
+    });
+    let plain = error.into_plain();
+    let chain: Vec<_> = plain.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["reading config", "reading config", "no such file"]);
+}
@@ -0,0 +1,50 @@
+use anyhow::ErrorBuilder;
+
+#[test]
+fn test_three_contexts() {
+    let mut problems = ErrorBuilder::new();
+    assert!(problems.is_empty());
+    problems
+        .push_context("src/config.rs", 10, "port out of range")
+        .push_context(
+            "src/config.rs",
+            14,
+            format_args!("unknown key {:?}", "hots"),
+        );
+    problems.push_context("src/users.rs", 3, "no admin user");
+    assert_eq!(problems.len(), 3);
+
+    let error = problems.build();
+    let frames: Vec<_> = error
+        .parsed_frames()
+        .take_while(|frame| !frame.file.is_empty())
+        .map(|frame| (frame.file, frame.line, frame.msg))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            (
+                "src/config.rs".to_owned(),
+                10,
+                "port out of range".to_owned()
+            ),
+            (
+                "src/config.rs".to_owned(),
+                14,
+                "unknown key \"hots\"".to_owned()
+            ),
+            ("src/users.rs".to_owned(), 3, "no admin user".to_owned()),
+        ],
+    );
+    assert_eq!(error.chain().count(), 1);
+    assert_eq!(
+        error.to_string().matches(anyhow::line_separator()).count(),
+        2,
+    );
+}
+
+#[test]
+fn test_empty() {
+    let error = ErrorBuilder::new().build();
+    assert_eq!(error.to_string(), "");
+}
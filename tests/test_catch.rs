@@ -0,0 +1,33 @@
+use std::panic;
+
+#[test]
+fn test_catch_ok() {
+    assert_eq!(anyhow::catch(|| 1 + 1).unwrap(), 2);
+}
+
+#[test]
+fn test_catch_panic() {
+    let line = line!() + 1;
+    let error = anyhow::catch(|| -> () { panic!("bad input: {}", 7) }).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17 emsg(bad input: 7)]", file!(), line),
+    );
+
+    let location = error.location().unwrap();
+    assert_eq!(location.line(), line);
+    assert!(location.file().ends_with("test_catch.rs"));
+}
+
+#[test]
+fn test_catch_payloads() {
+    let error = anyhow::catch(|| -> () { panic!("static") }).unwrap_err();
+    assert!(error.to_string().ends_with(" emsg(static)]"), "{}", error);
+
+    let error = anyhow::catch(|| -> () { panic::panic_any(5_u8) }).unwrap_err();
+    assert!(
+        error.to_string().ends_with(" emsg(Box<dyn Any>)]"),
+        "{}",
+        error
+    );
+}
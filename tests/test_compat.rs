@@ -0,0 +1,44 @@
+#![cfg(feature = "compat")]
+
+mod common;
+
+use self::common::*;
+use anyhow::{anyhow, anyhow_located, bail, ensure, Result};
+use std::io;
+
+#[test]
+fn test_plain_messages() {
+    assert_eq!("oh no!", bail_literal().unwrap_err().to_string());
+    assert_eq!("oh no!", bail_fmt().unwrap_err().to_string());
+    assert_eq!("oh no!", bail_error().unwrap_err().to_string());
+}
+
+#[test]
+fn test_downcast() {
+    let error = anyhow!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert_eq!(
+        error.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::Other,
+    );
+
+    let error = bail_error().unwrap_err();
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    let f = || -> Result<()> {
+        ensure!(false, io::Error::new(io::ErrorKind::Other, "oh no!"));
+        Ok(())
+    };
+    assert!(f().unwrap_err().is::<io::Error>());
+}
+
+#[test]
+fn test_located() {
+    let error = anyhow_located!("oh no!");
+    let expected = format!("[{}:{}:17 emsg(oh no!)]", file!(), line!() - 1);
+    assert_eq!(error.to_string(), expected);
+
+    let f = || -> Result<()> {
+        bail!("oh no!");
+    };
+    assert_eq!(f().unwrap_err().to_string(), "oh no!");
+}
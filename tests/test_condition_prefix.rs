@@ -0,0 +1,51 @@
+use anyhow::{ensure_ctx, ensure_eq, ensure_located, Result};
+
+fn check(depth: u32) -> Result<()> {
+    ensure_located!(depth < 8);
+    Ok(())
+}
+
+fn check_eq(depth: u32) -> Result<()> {
+    ensure_eq!(depth, 8);
+    Ok(())
+}
+
+fn check_ctx(depth: u32) -> Result<()> {
+    ensure_ctx!(depth < 8, || format!("got {}", depth));
+    Ok(())
+}
+
+// The prefix is process-wide, so everything that sets it lives in this
+// single test.
+#[test]
+fn test_condition_prefix() {
+    let error = check(9).unwrap_err();
+    assert_eq!(error.emsg(), Some("Condition failed: `depth < 8`"));
+
+    let error = anyhow::with_condition_prefix("check: ", || check(9)).unwrap_err();
+    assert_eq!(error.emsg(), Some("check: `depth < 8`"));
+    let location = error.location().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), 4));
+
+    assert_eq!(anyhow::set_condition_prefix("invariant "), Ok(()));
+
+    let error = check(9).unwrap_err();
+    assert_eq!(error.emsg(), Some("invariant `depth < 8`"));
+    assert_eq!(error.location().unwrap().line(), 4);
+    assert!(error.to_string().starts_with('['), "{}", error);
+    assert_eq!(
+        check_eq(9).unwrap_err().emsg(),
+        Some("invariant `depth == 8` (left: `9`, right: `8`)"),
+    );
+    assert_eq!(
+        check_ctx(9).unwrap_err().emsg(),
+        Some("invariant `depth < 8`: got 9"),
+    );
+
+    // A scope still takes precedence over the installed prefix.
+    let error = anyhow::with_condition_prefix("", || check(9)).unwrap_err();
+    assert_eq!(error.emsg(), Some("`depth < 8`"));
+
+    assert_eq!(anyhow::set_condition_prefix("later "), Err("later "));
+    assert_eq!(check(9).unwrap_err().emsg(), Some("invariant `depth < 8`"));
+}
@@ -3,11 +3,13 @@
     clippy::nonstandard_macro_braces,
 )]
 
+mod common;
 mod drop;
 
 use crate::drop::{DetectDrop, Flag};
-use anyhow::{Context, Error, Result};
+use anyhow::{anyhow_context, anyhow_located, ctx, loc_context, Context, Error, Result};
 use std::fmt::{self, Display};
+use std::io;
 use thiserror::Error;
 
 // https://github.com/dtolnay/anyhow/issues/18
@@ -97,8 +99,12 @@ fn make_chain() -> (Error, Dropped) {
 fn test_downcast_ref() {
     let (err, dropped) = make_chain();
 
-    assert!(!err.is::<String>());
-    assert!(err.downcast_ref::<String>().is_none());
+    // With "with-location" the outermost level is the tagged message.
+    #[cfg(not(feature = "with-location"))]
+    {
+        assert!(!err.is::<String>());
+        assert!(err.downcast_ref::<String>().is_none());
+    }
 
     assert!(err.is::<HighLevel>());
     let high = err.downcast_ref::<HighLevel>().unwrap();
@@ -153,6 +159,7 @@ fn test_downcast_low() {
     assert!(dropped.all());
 }
 
+#[cfg(not(feature = "with-location"))]
 #[test]
 fn test_unsuccessful_downcast() {
     let (err, dropped) = make_chain();
@@ -170,3 +177,408 @@ fn test_root_cause() {
 
     assert_eq!(err.root_cause().to_string(), "no such file or directory");
 }
+
+#[derive(Error, Debug)]
+#[error("permission denied")]
+struct Reroot;
+
+#[test]
+fn test_reroot() {
+    let (err, dropped) = make_chain();
+
+    let err = err.reroot(Reroot);
+    assert!(dropped.low.get());
+    assert!(!dropped.mid.get() && !dropped.high.get());
+
+    #[cfg(not(feature = "with-location"))]
+    {
+        let chain: Vec<_> = err.chain().map(ToString::to_string).collect();
+        assert_eq!(
+            chain,
+            [
+                "failed to start server",
+                "failed to load config",
+                "permission denied"
+            ],
+        );
+    }
+    assert!(err.is::<HighLevel>());
+    assert!(err.is::<MidLevel>());
+    assert!(!err.is::<LowLevel>());
+    assert!(err.root_cause().is::<Reroot>());
+
+    drop(err);
+    assert!(dropped.all());
+
+    // Without context the whole error is replaced.
+    let err = Error::msg("oh no!").reroot(Reroot);
+    assert_eq!(err.chain().count(), 1);
+    assert!(err.downcast::<Reroot>().is_ok());
+}
+
+#[cfg(not(feature = "with-location"))]
+#[test]
+fn test_replace_message_if() {
+    let (err, dropped) = make_chain();
+
+    let err = err.replace_message_if(|message| message.contains("client"), || "unused");
+    assert_eq!(err.to_string(), "failed to start server");
+    assert!(err.is::<HighLevel>());
+
+    let err = err.replace_message_if(|message| message.contains("server"), || "startup failed");
+    assert_eq!(
+        format!("{:#}", err),
+        "startup failed: failed to load config: no such file or directory"
+    );
+    assert!(dropped.high.get());
+    assert!(!dropped.mid.get() && !dropped.low.get());
+    assert!(err.is::<MidLevel>());
+    assert!(err.is::<LowLevel>());
+
+    // Foreign errors are left alone.
+    let err = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let err = err.replace_message_if(|_| true, || "replaced");
+    assert_eq!(err.to_string(), "oh no!");
+}
+
+#[test]
+fn test_map_chain() {
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io)
+        .context("write failed")
+        .map_err(anyhow_context!("saving report"))
+        .unwrap_err()
+        .context("shutting down");
+    let line = line!() - 3;
+
+    let error = error.map_chain(|message| message.to_uppercase());
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    let tagged = format!("[{}:{}:18, emsg(SAVING REPORT)]", site!(), line);
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(
+        chain,
+        ["SHUTTING DOWN", &tagged, "WRITE FAILED", "disk full"]
+    );
+    #[cfg(feature = "with-location")]
+    {
+        let write = format!("[{}:{}:10, emsg(WRITE FAILED)]", file!(), line - 1);
+        assert_eq!(
+            chain,
+            [
+                "SHUTTING DOWN",
+                &tagged,
+                &write,
+                "WRITE FAILED",
+                "disk full"
+            ]
+        );
+    }
+    assert!(error.root_cause().is::<io::Error>());
+
+    let error = anyhow_located!("oh no!").context("outer");
+    let error = error.map_chain(|message| message.to_uppercase());
+    assert_eq!(error.to_string(), "OUTER");
+    assert!(error.root_cause().to_string().ends_with(" emsg(OH NO!)]"));
+
+    // A foreign error is left alone, even at the top.
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    let error = error.map_chain(|message| message.to_uppercase());
+    assert_eq!(error.to_string(), "disk full");
+}
+
+#[cfg(not(feature = "with-location"))]
+#[test]
+fn test_split_off_context() {
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io)
+        .context("write failed")
+        .map_err(anyhow_context!("saving report"))
+        .unwrap_err()
+        .attach(7u8);
+
+    let (headline, rest) = error.split_off_context();
+    assert_eq!(headline, "saving report");
+    let rest = rest.unwrap();
+    assert_eq!(format!("{:#}", rest), "write failed: disk full");
+    assert_eq!(rest.attachment::<u8>(), Some(&7));
+
+    let (headline, rest) = rest.split_off_context();
+    assert_eq!(headline, "write failed");
+    let rest = rest.unwrap();
+    assert_eq!(rest.to_string(), "disk full");
+    assert!(rest.downcast_ref::<io::Error>().is_some());
+    assert_eq!(rest.attachment::<u8>(), Some(&7));
+
+    let (headline, rest) = rest.split_off_context();
+    assert_eq!(headline, "disk full");
+    assert!(rest.is_none());
+
+    let (headline, rest) = anyhow_located!("oh no!").split_off_context();
+    assert_eq!(headline, "oh no!");
+    assert!(rest.is_none());
+}
+
+#[test]
+fn test_or_else_context() {
+    let primary = || Err::<u8, _>(io::Error::new(io::ErrorKind::NotFound, "cache miss"));
+
+    let value = primary().or_else_context(|| Ok(1)).unwrap();
+    assert_eq!(value, 1);
+    let value = Ok::<u8, io::Error>(2)
+        .or_else_context(|| -> Result<u8> { panic!("fallback called") })
+        .unwrap();
+    assert_eq!(value, 2);
+
+    let fallback =
+        || -> Result<u8> { Err(Error::msg("origin unreachable")).context("fetching from origin") };
+    let error = primary()
+        .context("reading cache")
+        .or_else_context(fallback)
+        .unwrap_err();
+    #[cfg(not(feature = "with-location"))]
+    {
+        let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+        assert_eq!(
+            chain,
+            [
+                "fetching from origin",
+                "origin unreachable",
+                "reading cache",
+                "cache miss",
+            ],
+        );
+    }
+    assert!(error.root_cause().is::<io::Error>());
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    let error = None::<u8>
+        .or_else_context(|| Err(Error::msg("no default")))
+        .unwrap_err();
+    assert_eq!(format!("{:#}", error), "no default");
+}
+
+#[test]
+fn test_ctx() {
+    use std::cell::Cell;
+
+    let evaluated = Cell::new(0);
+    let url = || {
+        evaluated.set(evaluated.get() + 1);
+        "https://example.com"
+    };
+
+    let result = Ok::<(), io::Error>(()).map_err(ctx!("fetching {}", url()));
+    assert!(result.is_ok());
+    assert_eq!(evaluated.get(), 0);
+
+    let line = line!() + 2;
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "timed out"))
+        .map_err(ctx!("fetching {}", url()))
+        .unwrap_err();
+    assert_eq!(evaluated.get(), 1);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[{}:{}:18, emsg(fetching https://example.com)]",
+            site!(),
+            line
+        ),
+    );
+    assert!(error.root_cause().is::<io::Error>());
+
+    let error = Err::<(), _>(Error::msg("oh no!"))
+        .map_err(ctx!("static"))
+        .unwrap_err();
+    assert_eq!(error.chain().count(), 2);
+    assert!(error.to_string().ends_with(", emsg(static)]"));
+}
+
+#[test]
+fn test_context_if_enabled() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let detail = || {
+        calls.set(calls.get() + 1);
+        "expensive detail"
+    };
+    let io = || Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"));
+
+    let error = io().context_if_enabled(false, detail).unwrap_err();
+    assert_eq!(calls.get(), 0);
+    assert_eq!(error.chain().count(), 1);
+    assert!(error.is::<io::Error>());
+
+    let error = Err::<(), _>(Error::msg("oh no!"))
+        .context_if_enabled(false, detail)
+        .unwrap_err();
+    assert_eq!(calls.get(), 0);
+    assert_eq!(format!("{:#}", error), "oh no!");
+
+    let error = io().context_if_enabled(true, detail).unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(format!("{:#}", error), "expensive detail: disk full");
+
+    assert_eq!(
+        Ok::<_, io::Error>(1)
+            .context_if_enabled(true, detail)
+            .unwrap(),
+        1
+    );
+    assert_eq!(calls.get(), 1);
+
+    let error = None::<()>.context_if_enabled(false, detail).unwrap_err();
+    assert_eq!(calls.get(), 2);
+    assert_eq!(error.to_string(), "expensive detail");
+}
+
+#[test]
+fn test_loc_context() {
+    let ok = Ok::<u32, io::Error>(1);
+    let ok = loc_context!(ok, "unused").map(|n| n + 1);
+    assert_eq!(ok.unwrap(), 2);
+
+    let result = Err::<u32, _>(io::Error::new(io::ErrorKind::Other, "timed out"));
+    let result = loc_context!(result, "fetching {}", "https://example.com");
+    let line = line!() - 1;
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[{}:{}:18, emsg(fetching https://example.com)]",
+            site!(),
+            line,
+        ),
+    );
+    assert!(error.root_cause().is::<io::Error>());
+}
+
+#[test]
+fn test_map_context() {
+    let parse = |s: &str| s.parse::<u16>();
+
+    let port = parse("80").map_context(|port| port + 1, "invalid port");
+    assert_eq!(port.unwrap(), 81);
+
+    let result = parse("eighty").map_context(|port| port + 1, "invalid port");
+    let line = line!() - 1;
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:34, emsg(invalid port)]", file!(), line),
+    );
+    assert!(error.root_cause().is::<std::num::ParseIntError>());
+    assert_eq!(error.locations().next().unwrap().unwrap().line(), line);
+
+    let len = Some("abc").map_context(str::len, "missing").unwrap();
+    assert_eq!(len, 3);
+    let error = None::<&str>.map_context(str::len, "missing").unwrap_err();
+    assert!(error.to_string().ends_with(", emsg(missing)]"));
+    assert_eq!(error.chain().count(), 1);
+}
+
+#[test]
+fn test_with_location_context() {
+    let calls = std::cell::Cell::new(0);
+    let context = || {
+        calls.set(calls.get() + 1);
+        "invalid port"
+    };
+
+    let port = "80".parse::<u16>().with_location_context(context);
+    assert_eq!(port.unwrap(), 80);
+    assert_eq!(calls.get(), 0);
+
+    let result = "eighty".parse::<u16>().with_location_context(context);
+    let line = line!() - 1;
+    let error = result.unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:42, emsg(invalid port)]", file!(), line),
+    );
+    assert!(error.root_cause().is::<std::num::ParseIntError>());
+
+    let error = None::<()>.with_location_context(|| "missing").unwrap_err();
+    let line = line!() - 1;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:28, emsg(missing)]", file!(), line),
+    );
+}
+
+#[test]
+fn test_typed_context() {
+    #[derive(Error, Debug, PartialEq)]
+    #[error("loading user {id}")]
+    struct LoadingUser {
+        id: u64,
+    }
+
+    let context = LoadingUser { id: 7 };
+    let error = Err::<(), _>(anyhow_located!("no such row"))
+        .map_err(anyhow_context!(move context))
+        .unwrap_err();
+    let line = line!() - 2;
+
+    let expected = format!("[{}:{}:18, emsg(loading user 7)]", site!(), line);
+    assert_eq!(error.to_string(), expected);
+    assert_eq!(
+        error.downcast_ref::<LoadingUser>(),
+        Some(&LoadingUser { id: 7 }),
+    );
+    assert_eq!(error.chain().nth(1).unwrap().to_string(), "loading user 7");
+    assert_eq!(error.location().unwrap().line(), line);
+
+    let error = error.downcast::<LoadingUser>().unwrap();
+    assert_eq!(error.id, 7);
+}
+
+#[derive(Error, Debug)]
+#[error("???")]
+struct SuspiciousError;
+
+fn suspicious() -> Result<()> {
+    anyhow::bail_located!(SuspiciousError);
+}
+
+#[test]
+fn test_bail_keeps_type() {
+    // The downcasting example of the crate docs.
+    let error = suspicious()
+        .context("Failed to complete the work")
+        .unwrap_err();
+    assert!(error.downcast_ref::<SuspiciousError>().is_some());
+    // With "with-location" the context is a tagged level and a typed one.
+    let tagged = if cfg!(feature = "with-location") {
+        2
+    } else {
+        1
+    };
+    assert!(error
+        .chain()
+        .nth(tagged)
+        .unwrap()
+        .to_string()
+        .ends_with(" emsg(???)]"));
+
+    // Tagging an already tagged error again keeps the type underneath too,
+    // while a lone tagged message is still flattened into one level.
+    let rebailed = (|| -> Result<()> {
+        let error = suspicious().unwrap_err();
+        anyhow::bail_located!(error);
+    })()
+    .unwrap_err();
+    assert!(rebailed.downcast_ref::<SuspiciousError>().is_some());
+    let frames: Vec<_> = rebailed
+        .to_string()
+        .split(anyhow::line_separator())
+        .map(str::to_owned)
+        .collect();
+    assert_eq!(frames.len(), 2);
+    assert!(frames[0].ends_with(" emsg()]"));
+    assert!(frames[1].ends_with(" emsg(???)]"));
+
+    let message = anyhow_located!(anyhow_located!("oh no!"));
+    assert_eq!(message.chain().count(), 1);
+}
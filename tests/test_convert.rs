@@ -44,3 +44,21 @@ fn test_question_mark() -> Result<(), Box<dyn StdError>> {
     f()?;
     Ok(())
 }
+
+#[test]
+fn test_into_err() {
+    let lookup = |key: &str| -> Result<u32> {
+        key.parse::<u32>().or_else(|error| {
+            Error::new(error)
+                .context(format!("bad key {:?}", key))
+                .into_err()
+        })
+    };
+    assert_eq!(lookup("7").unwrap(), 7);
+    let error = lookup("x").unwrap_err();
+    assert_eq!(error.to_string(), "bad key \"x\"");
+    assert!(error.root_cause().is::<std::num::ParseIntError>());
+
+    let result: Result<String> = Error::msg("oh no!").into_err();
+    assert_eq!(result.unwrap_err().to_string(), "oh no!");
+}
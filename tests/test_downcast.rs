@@ -121,3 +121,46 @@ fn test_unsuccessful_downcast() {
     assert!(error.downcast_mut::<&str>().is_none());
     assert!(error.downcast::<&str>().is_err());
 }
+
+#[test]
+fn test_downcast_ref_or() {
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let kind = error.downcast_ref_or(|e: &io::Error| Some(e.kind()), |_| None);
+    assert_eq!(kind, Some(io::ErrorKind::Other));
+
+    let error = Error::msg("oh no!");
+    let message = error.downcast_ref_or(|_: &io::Error| String::new(), str::to_owned);
+    assert_eq!(message, "oh no!");
+}
+
+#[test]
+fn test_downcast_located() {
+    #[derive(thiserror::Error, Debug)]
+    enum MyError {
+        #[error("not found")]
+        NotFound,
+        #[error("io")]
+        Io(#[source] io::Error),
+    }
+
+    let error = anyhow::anyhow_located!(MyError::NotFound);
+    let line = line!() - 1;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17 emsg(not found)]", site!(), line),
+    );
+    assert_eq!(error.locations().next().unwrap().unwrap().line(), line);
+    match error.downcast::<MyError>() {
+        Ok(MyError::NotFound) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = anyhow::anyhow_located!(MyError::Io(io));
+    assert!(matches!(
+        error.downcast_ref::<MyError>(),
+        Some(MyError::Io(_))
+    ));
+    assert_eq!(error.root_cause().to_string(), "oh no!");
+    assert_eq!(error.chain().count(), 3);
+}
@@ -0,0 +1,69 @@
+use anyhow::{anyhow_context, anyhow_located, Context, Error, Result};
+use std::io;
+
+fn lookup(id: &str) -> Result<()> {
+    Err(anyhow_located!("no session {}", id))
+}
+
+fn resume(id: &str, attempt: u32) -> Error {
+    lookup(id)
+        .map_err(anyhow_context!("resuming after {} attempts", attempt))
+        .unwrap_err()
+}
+
+#[test]
+fn test_fingerprint_masks_ids() {
+    let a = resume("550e8400-e29b-41d4-a716-446655440000", 3);
+    let b = resume("6ba7b810-9dad-11d1-80b4-00c04fd430c8", 12);
+    assert_ne!(a.to_string(), b.to_string());
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint_without_lines(), b.fingerprint_without_lines());
+
+    let a = Error::msg("request 42 failed after 1.5 seconds");
+    let b = Error::msg("request 9001 failed after 30.25 seconds");
+    assert_eq!(a.fingerprint(), b.fingerprint());
+
+    let a = Error::msg("commit deadbeef0 not found");
+    let b = Error::msg("commit 0123abcd9 not found");
+    assert_eq!(a.fingerprint(), b.fingerprint());
+}
+
+#[test]
+fn test_fingerprint_distinguishes_shapes() {
+    let a = Error::msg("no user 7");
+    assert_ne!(a.fingerprint(), Error::msg("no group 7").fingerprint());
+    assert_ne!(a.fingerprint(), a.context("loading").fingerprint());
+    assert_ne!(
+        Error::msg("utf8 decoding").fingerprint(),
+        Error::msg("utf16 decoding").fingerprint(),
+    );
+
+    let io = |message| io::Error::new(io::ErrorKind::Other, message);
+    let a = Err::<(), _>(io("disk 1 full"))
+        .context("write")
+        .unwrap_err();
+    let b = Err::<(), _>(io("disk 2 full"))
+        .context("write")
+        .unwrap_err();
+    let c = Err::<(), _>(io("disk 2 gone"))
+        .context("write")
+        .unwrap_err();
+    // With "with-location" the contexts are tagged with lines of their own.
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    #[cfg(feature = "with-location")]
+    assert_eq!(a.fingerprint_without_lines(), b.fingerprint_without_lines());
+    assert_ne!(b.fingerprint(), c.fingerprint());
+}
+
+#[test]
+fn test_fingerprint_lines() {
+    let a = anyhow_located!("oh no!");
+    let b = anyhow_located!("oh no!");
+    assert_ne!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint_without_lines(), b.fingerprint_without_lines());
+    assert_ne!(
+        a.fingerprint_without_lines(),
+        Error::msg("oh no!").fingerprint_without_lines(),
+    );
+}
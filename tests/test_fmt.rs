@@ -1,4 +1,6 @@
-use anyhow::{bail, Context, Result};
+mod common;
+
+use anyhow::{anyhow, bail, Context, Result};
 use std::io;
 
 fn f() -> Result<()> {
@@ -66,11 +68,31 @@ Error {
 }\
 ";
 
+#[cfg(not(feature = "with-location"))]
 #[test]
 fn test_display() {
     assert_eq!("g failed", h().unwrap_err().to_string());
 }
 
+// With "with-location" each context sits under a tag of its own, which is
+// what `{}` shows of the outermost.
+#[cfg(feature = "with-location")]
+#[test]
+fn test_display() {
+    let tag = |line, context| format!("[{}:{}:9, emsg({})]", file!(), line, context);
+    let error = h().unwrap_err();
+    assert_eq!(error.to_string(), tag(15, "g failed"));
+    assert_eq!(
+        format!("{:#}", error),
+        format!(
+            "{}: g failed: {}: f failed: [{}:7:5 emsg(oh no!)]: oh no!",
+            tag(15, "g failed"),
+            tag(11, "f failed"),
+            site!(),
+        ),
+    );
+}
+
 #[test]
 fn test_altdisplay() {
     assert_eq!(EXPECTED_ALTDISPLAY_F, format!("{:#}", f().unwrap_err()));
@@ -92,3 +114,21 @@ fn test_altdebug() {
     assert_eq!(EXPECTED_ALTDEBUG_G, format!("{:#?}", g().unwrap_err()));
     assert_eq!(EXPECTED_ALTDEBUG_H, format!("{:#?}", h().unwrap_err()));
 }
+
+#[test]
+fn test_report() {
+    for error in vec![f().unwrap_err(), g().unwrap_err(), h().unwrap_err()] {
+        assert_eq!(format!("{:?}", error), error.report().to_string());
+        assert_eq!(format!("{:?}", error), format!("{:?}", error.report()));
+    }
+}
+
+#[test]
+fn test_formatted_len() {
+    for error in vec![f().unwrap_err(), g().unwrap_err(), h().unwrap_err()] {
+        assert_eq!(format!("{:?}", error).len(), error.formatted_len());
+    }
+
+    let error = anyhow!("naïve — ünïcödé").context("ça échoue");
+    assert_eq!(format!("{:?}", error).len(), error.formatted_len());
+}
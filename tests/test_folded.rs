@@ -0,0 +1,21 @@
+use anyhow::{anyhow_context, anyhow_located, Error};
+
+#[test]
+fn test_folded() {
+    let error = Err::<(), _>(anyhow_located!("no such file"))
+        .map_err(anyhow_context!("loading config"))
+        .unwrap_err()
+        .context("starting up");
+    assert_eq!(
+        error.to_folded(),
+        "no such file;loading config;starting up 1",
+    );
+
+    assert_eq!(Error::msg("oh no!").to_folded(), "oh no! 1");
+}
+
+#[test]
+fn test_folded_escapes_separators() {
+    let error = Error::msg("expected ';'\nfound EOF").context("parsing a; b");
+    assert_eq!(error.to_folded(), "expected ',' found EOF;parsing a, b 1",);
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Error};
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+
+#[derive(Debug)]
+struct SqlError {
+    query: String,
+}
+
+impl Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "syntax error in query: {}", self.query)
+    }
+}
+
+impl StdError for SqlError {}
+
+fn truncate_sql(error: &(dyn StdError + 'static)) -> Option<String> {
+    let sql = error.downcast_ref::<SqlError>()?;
+    Some(format!("syntax error in query: {}...", &sql.query[..12]))
+}
+
+fn verbose() -> SqlError {
+    SqlError {
+        query: format!("SELECT * FROM users WHERE id IN ({})", "1, ".repeat(100)),
+    }
+}
+
+#[test]
+fn test_foreign_formatter() {
+    let error = Err::<(), _>(verbose()).context("query failed").unwrap_err();
+    assert!(format!("{:#}", error).len() > 300);
+
+    anyhow::set_foreign_formatter(truncate_sql);
+
+    let expected = ": syntax error in query: SELECT * FRO...";
+    assert!(format!("{:#}", error).ends_with(expected));
+    #[cfg(not(feature = "with-location"))]
+    {
+        let expected = "query failed: syntax error in query: SELECT * FRO...";
+        assert_eq!(expected, format!("{:#}", error));
+
+        let expected = "query failed\n\nCaused by:\n    syntax error in query: SELECT * FRO...";
+        assert_eq!(expected, format!("{:?}", error));
+    }
+
+    // Frames are parsed from the chain as rendered without the formatter.
+    let frames = anyhow::parse_frames(&error);
+    assert!(frames.iter().any(|frame| frame.msg.contains(&verbose().query)));
+    assert!(frames.iter().all(|frame| !frame.msg.contains("FRO...")));
+
+    // The head of the chain is foreign too when there is no context.
+    let error = Error::new(verbose());
+    let expected = "syntax error in query: SELECT * FRO...";
+    assert_eq!(expected, error.to_string());
+
+    // Context attached through anyhow is never handed to the formatter.
+    let error = Error::new(verbose()).context(verbose());
+    assert!(error.to_string().len() > 300);
+    assert!(format!("{:#}", error).ends_with(": syntax error in query: SELECT * FRO..."));
+
+    // Returning None falls back to the error's own Display.
+    let error = Err::<(), _>(std::fmt::Error).context("write failed").unwrap_err();
+    let rendered = format!("{:#}", error);
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(
+        "write failed: an error occurred when formatting an argument",
+        rendered,
+    );
+    #[cfg(feature = "with-location")]
+    assert!(rendered.ends_with(": write failed: an error occurred when formatting an argument"));
+}
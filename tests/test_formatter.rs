@@ -0,0 +1,77 @@
+mod common;
+
+use anyhow::{anyhow_error, anyhow_located, Context, Error, ErrorFormatter};
+
+// Formatter settings are process-wide, so everything that installs one lives
+// in this single test.
+#[test]
+fn test_max_message_width() {
+    let long = "x".repeat(100);
+    let error = Error::msg(long.clone()).context("failed to parse");
+    let (tagged, line) = (anyhow_located!("{}", long), line!());
+
+    assert_eq!(ErrorFormatter::current(), ErrorFormatter::new());
+    ErrorFormatter::new().max_message_width(Some(10)).install();
+    assert_eq!(
+        ErrorFormatter::current().max_message_width(None),
+        ErrorFormatter::new()
+    );
+
+    let expected = "failed to…: xxxxxxxxx…";
+    assert_eq!(expected, format!("{:#}", error));
+    let expected = "failed to…\n\nCaused by:\n    xxxxxxxxx…";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!(format!("{:?}", error), error.report().to_string());
+
+    // The location is kept whole.
+    let expected = format!("[{}:{}:27 emsg(xxxxxxxxx…)]", site!(), line);
+    assert_eq!(expected, tagged.to_string());
+
+    // Messages that fit are left alone.
+    let error = Err::<(), _>(Error::msg("ten chars!"))
+        .context("short")
+        .unwrap_err();
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!("short: ten chars!", format!("{:#}", error));
+    #[cfg(feature = "with-location")]
+    assert!(format!("{:#}", error).ends_with(", emsg(short)]: short: ten chars!"));
+
+    // With no room even for the `…` only the locations are left.
+    ErrorFormatter::new().max_message_width(Some(0)).install();
+    assert_eq!(Error::msg("oh no!").to_string(), "");
+    let expected = format!("[{}:{}:27 emsg()]", site!(), line);
+    assert_eq!(expected, tagged.to_string());
+
+    // Frames are parsed from the error as the default settings render it.
+    let nested = Err::<(), _>(anyhow_located!("{}", long))
+        .map_err(anyhow_error!("reading {}", long))
+        .unwrap_err();
+    let nested_line = line!() - 2;
+    ErrorFormatter::new()
+        .omit_line_numbers(true)
+        .max_message_width(Some(5))
+        .install();
+    let frames: Vec<_> = nested
+        .parsed_frames()
+        .map(|frame| (frame.line, frame.msg))
+        .collect();
+    let expected = [
+        (nested_line, format!("reading {}", long)),
+        (nested_line - 1, long.clone()),
+    ];
+    assert_eq!(frames[..2], expected);
+    assert_eq!(nested.root_frame().unwrap().msg, long);
+    let levels: Vec<_> = nested
+        .all_frames()
+        .map(|frame| (frame.line, frame.msg))
+        .collect();
+    assert_eq!(levels, expected);
+    assert_eq!(nested.depth(), 2);
+    assert_eq!(nested.location().unwrap().line(), nested_line);
+
+    ErrorFormatter::new().install();
+    assert_eq!(
+        format!("failed to parse: {}", long),
+        format!("{:#}", Error::msg(long).context("failed to parse"))
+    );
+}
@@ -0,0 +1,32 @@
+use anyhow::{anyhow, ErrorFormatter};
+
+// Formatter settings are process-wide, so everything that installs one lives
+// in this single test.
+#[test]
+fn test_include_backtrace() {
+    // Must be set before the first backtrace is captured in this process.
+    #[cfg(feature = "backtrace")]
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = anyhow!("oh no!").context("outer");
+    #[cfg(feature = "backtrace")]
+    assert!(format!("{:?}", error).contains("\n\nStack backtrace:\n"));
+
+    ErrorFormatter::new().include_backtrace(false).install();
+    assert_eq!(
+        ErrorFormatter::current().include_backtrace(true),
+        ErrorFormatter::new()
+    );
+
+    let debug = format!("{:?}", error);
+    assert!(!debug.contains("Stack backtrace:"), "{}", debug);
+    assert!(debug.starts_with("outer\n\nCaused by:\n    "));
+    assert_eq!(debug, error.report().to_string());
+
+    #[cfg(feature = "backtrace")]
+    assert!(!error.backtrace().to_string().is_empty());
+
+    ErrorFormatter::new().install();
+    #[cfg(feature = "backtrace")]
+    assert!(format!("{:?}", error).contains("\n\nStack backtrace:\n"));
+}
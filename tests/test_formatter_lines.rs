@@ -0,0 +1,43 @@
+mod common;
+
+use anyhow::{anyhow_context, anyhow_located, ErrorFormatter};
+
+// Formatter settings are process-wide, so everything that installs one lives
+// in this single test.
+#[test]
+fn test_omit_line_numbers() {
+    let error = Err::<(), _>(anyhow_located!("no such file"))
+        .map_err(anyhow_context!("loading {}", "config.toml"))
+        .unwrap_err()
+        .context("starting up");
+
+    ErrorFormatter::new()
+        .omit_line_numbers(true)
+        .include_backtrace(false)
+        .install();
+
+    let expected = format!(
+        "\
+starting up
+
+Caused by:
+    0: [{0}, emsg(loading config.toml)]
+    1: [{0} emsg(no such file)]",
+        site!(),
+    );
+    assert_eq!(expected, format!("{:?}", error));
+
+    let expected = format!(
+        "starting up: [{0}, emsg(loading config.toml)]: [{0} emsg(no such file)]",
+        site!(),
+    );
+    assert_eq!(expected, format!("{:#}", error));
+
+    // Locations are still available in full.
+    let location = error.locations().nth(2).unwrap().unwrap();
+    assert!(location.line() > 0);
+
+    ErrorFormatter::new().install();
+    let with_lines = format!("{:#}", error);
+    assert!(with_lines.contains(&format!("{}:{}", file!(), location.line())));
+}
@@ -0,0 +1,70 @@
+use anyhow::{anyhow_context, anyhow_located, Context, Error, Frame};
+use std::io;
+
+#[test]
+fn test_frames() {
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io)
+        .context("write failed")
+        .map_err(anyhow_context!("saving {}", "report"))
+        .unwrap_err();
+    let line = line!() - 2;
+
+    let frames: Vec<_> = error.frames().collect();
+    assert_eq!(frames.len(), error.chain().count());
+    match &frames[0] {
+        Frame::Context { message, location } => {
+            assert_eq!(message, "saving report");
+            let location = location.as_ref().unwrap();
+            assert_eq!((location.file(), location.line()), (file!(), line));
+        }
+        frame => panic!("expected context, got {:?}", frame),
+    }
+    match &frames[1] {
+        Frame::Context { message, location } => {
+            assert_eq!(message, "write failed");
+            #[cfg(not(feature = "with-location"))]
+            assert_eq!(*location, None);
+            #[cfg(feature = "with-location")]
+            assert_eq!(location.as_ref().unwrap().line(), line - 1);
+        }
+        frame => panic!("expected context, got {:?}", frame),
+    }
+    // With "with-location" the context is kept typed in a level of its own.
+    let foreign = if cfg!(feature = "with-location") {
+        3
+    } else {
+        2
+    };
+    match &frames[foreign] {
+        Frame::Foreign { error } => assert!(error.is::<io::Error>()),
+        frame => panic!("expected foreign, got {:?}", frame),
+    }
+}
+
+#[test]
+fn test_adhoc_frames() {
+    let error = anyhow_located!("oh no!").context("outer");
+    let frames: Vec<_> = error.frames().collect();
+    assert_eq!(frames.len(), 2);
+    match &frames[0] {
+        Frame::Context { message, location } => {
+            assert_eq!(message, "outer");
+            assert_eq!(*location, None);
+        }
+        frame => panic!("expected context, got {:?}", frame),
+    }
+    match &frames[1] {
+        Frame::Adhoc { message, location } => {
+            assert_eq!(message, "oh no!");
+            assert!(location.is_some());
+        }
+        frame => panic!("expected ad-hoc message, got {:?}", frame),
+    }
+
+    // A foreign error with nothing on top is a single foreign frame.
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let frames: Vec<_> = error.frames().collect();
+    assert_eq!(frames.len(), 1);
+    assert!(matches!(frames[0], Frame::Foreign { .. }));
+}
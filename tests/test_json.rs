@@ -0,0 +1,45 @@
+use anyhow::{anyhow_context, anyhow_located, Context, Error};
+use serde_json::Value;
+use std::io;
+
+#[test]
+fn test_compact_json() {
+    let line = line!() + 1;
+    let error = Err::<(), _>(anyhow_located!("no such \"file\""))
+        .map_err(anyhow_context!("loading\tconfig"))
+        .unwrap_err()
+        .context("starting up");
+    let json = error.to_compact_json();
+    assert!(!json.contains('\n'));
+    assert!(json.starts_with("{\"msg\":"));
+
+    let value: Value = serde_json::from_str(&json).unwrap();
+    let object = value.as_object().unwrap();
+    assert_eq!(object.len(), 3);
+    assert_eq!(
+        object["msg"],
+        "starting up: loading\tconfig: no such \"file\"",
+    );
+    assert_eq!(object["loc"], format!("{}:{}", file!(), line));
+    assert_eq!(object["depth"], 3);
+}
+
+#[test]
+fn test_compact_json_unlocated() {
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .context("write failed")
+        .unwrap_err();
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(
+        error.to_compact_json(),
+        r#"{"msg":"write failed: disk full","depth":2}"#,
+    );
+    // With "with-location" the context is located.
+    #[cfg(feature = "with-location")]
+    assert!(error.to_compact_json().contains(r#","loc":""#));
+
+    let error = Error::msg("bell\u{7}");
+    let value: Value = serde_json::from_str(&error.to_compact_json()).unwrap();
+    assert_eq!(value["msg"], "bell\u{7}");
+    assert_eq!(value["depth"], 1);
+}
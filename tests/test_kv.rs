@@ -0,0 +1,62 @@
+mod common;
+
+use anyhow::{anyhow_error, anyhow_kv, Error};
+
+#[test]
+fn test_kv() {
+    let (id, retry) = (String::from("req-7"), 2);
+    let error = anyhow_kv!("upstream timed out", request_id = id, retry = retry);
+    let line = line!() - 1;
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[{}:{}:17 emsg(upstream timed out) kv(request_id=req-7,retry=2)]",
+            site!(),
+            line,
+        ),
+    );
+    assert_eq!(error.kv("request_id"), Some("req-7"));
+    assert_eq!(error.kv("retry"), Some("2"));
+    assert_eq!(error.kv("request"), None);
+    assert_eq!(error.emsg(), Some("upstream timed out"));
+    assert_eq!(error.location().unwrap().line(), line);
+
+    // The pairs are read through the layers added on top.
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_error!("rendering page"))
+        .unwrap_err()
+        .context("serving request");
+    assert_eq!(error.kv("request_id"), Some("req-7"));
+    assert_eq!(error.kv("retry"), Some("2"));
+    let root = error.root_frame().unwrap();
+    assert_eq!((root.line, root.msg.as_str()), (line, "upstream timed out"));
+
+    assert_eq!(Error::msg("plain").kv("retry"), None);
+}
+
+#[test]
+fn test_kv_escaping() {
+    let value = "a,b) c=d%e\nf";
+    let error = anyhow_kv!("bad input", input = value, empty = "");
+    assert!(
+        error
+            .to_string()
+            .ends_with(" emsg(bad input) kv(input=a%2Cb%29 c=d%25e%0Af,empty=)]"),
+        "{}",
+        error,
+    );
+    assert_eq!(error.kv("input"), Some(value));
+    assert_eq!(error.kv("empty"), Some(""));
+    assert_eq!(error.emsg(), Some("bad input"));
+
+    // A message mentioning kv( is not mistaken for the pairs.
+    let error = anyhow_kv!("called (f) kv(x=1) here", x = 2);
+    assert_eq!(error.emsg(), Some("called (f) kv(x=1) here"));
+    assert_eq!(error.kv("x"), Some("2"));
+
+    let error = anyhow_kv!("no pairs");
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17 emsg(no pairs)]", site!(), line!() - 3),
+    );
+}
@@ -0,0 +1,38 @@
+use anyhow::Error;
+
+// The limits are process-wide, so everything that sets one lives in this
+// single test.
+#[test]
+fn test_limits() {
+    let mut error = Error::msg("oh no!");
+    for n in 0..10u32 {
+        error = error.attach(n).also(Error::msg(n));
+    }
+    assert_eq!(error.suppressed().len(), 10);
+
+    anyhow::set_max_attachments(3);
+    anyhow::set_max_suppressed(2);
+
+    // Existing errors are trimmed on their next addition.
+    let error = error.attach(10u32).also(Error::msg(10));
+    assert_eq!(error.attachment::<u32>(), Some(&10));
+    let suppressed: Vec<_> = error.suppressed().iter().map(ToString::to_string).collect();
+    assert_eq!(suppressed, ["9", "10"]);
+
+    let mut error = Error::msg("oh no!").attach("kept?");
+    for n in 0..5u32 {
+        error = error.attach(n).also(Error::msg(n));
+    }
+    assert_eq!(error.attachment::<u32>(), Some(&4));
+    assert_eq!(error.attachment::<&str>(), None);
+    let suppressed: Vec<_> = error.suppressed().iter().map(ToString::to_string).collect();
+    assert_eq!(suppressed, ["3", "4"]);
+
+    let error = error.with_url("https://example.com/errors/E1");
+    assert_eq!(error.url(), Some("https://example.com/errors/E1"));
+    assert_eq!(error.attachment::<u32>(), Some(&4));
+
+    anyhow::set_max_suppressed(0);
+    let error = Error::msg("primary").also(Error::msg("dropped"));
+    assert!(error.suppressed().is_empty());
+}
@@ -0,0 +1,827 @@
+mod common;
+
+use anyhow::{
+    anyhow, anyhow_code, anyhow_context, anyhow_error, anyhow_error_brief, anyhow_located, Context,
+    Error, Location, Result, END_OF_LINE,
+};
+use std::io;
+
+#[test]
+fn test_locations() {
+    let (error, low) = (anyhow_located!("oh no!"), line!());
+    let (error, mid) = (Err::<(), _>(error).map_err(anyhow_context!("mid")), line!());
+    let error = error.unwrap_err();
+    let (error, high) = (Err::<(), _>(error).map_err(anyhow_context!("top")), line!());
+    let error = error.unwrap_err();
+
+    let locations: Vec<_> = error.locations().collect();
+    assert_eq!(locations.len(), error.chain().count());
+
+    let lines: Vec<_> = locations
+        .iter()
+        .map(|location| location.as_ref().unwrap().line())
+        .collect();
+    assert_eq!(lines, [high, mid, low]);
+
+    for location in locations {
+        let location = location.unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(
+            location.to_string(),
+            format!(
+                "{}:{}:{}",
+                file!(),
+                location.line(),
+                location.column().unwrap(),
+            ),
+        );
+    }
+}
+
+#[test]
+fn test_untagged() {
+    let error = Error::msg("oh no!").context("untagged");
+    let locations: Vec<_> = error.locations().collect();
+    assert_eq!(locations, [None, None]);
+
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "[file.rs:1 emsg(x)]"));
+    let error = error.context(anyhow_located!("tagged"));
+    let locations: Vec<_> = error.locations().collect();
+    assert_eq!(locations.len(), 2);
+    assert!(locations[0].is_some());
+    assert_eq!(locations[1], None);
+}
+
+#[test]
+fn test_msg_located() {
+    let mut errors = Vec::new();
+    for name in ["alpha", "beta"].iter() {
+        errors.push((Error::msg_located(format!("missing {}", name)), line!()));
+    }
+
+    for ((error, line), name) in errors.into_iter().zip(&["alpha", "beta"]) {
+        let location = error.locations().next().unwrap().unwrap();
+        assert_eq!(location.file(), file!());
+        assert_eq!(location.line(), line);
+        assert_eq!(
+            error.to_string(),
+            format!("[{}:{}:22 emsg(missing {})]", file!(), line, name)
+        );
+        assert_eq!(error.plain().to_string(), format!("missing {}", name));
+    }
+}
+
+fn parse(s: &str) -> (Result<u32>, u32) {
+    let f = || -> Result<u32> { Ok(s.parse::<u32>()?) };
+    (f(), line!() - 1)
+}
+
+#[test]
+fn test_propagations() {
+    let (result, first) = parse("x");
+    let g = || -> Result<u32> {
+        let n = result.map_err(|e| e.propagated())?;
+        Ok(n)
+    };
+    let (result, second) = (g(), line!() - 3);
+    let error = result.unwrap_err();
+
+    let lines: Vec<_> = error.propagations().iter().map(|l| l.line()).collect();
+    assert_eq!(lines, [first, second]);
+    assert!(error.propagations().iter().all(|l| l.file() == file!()));
+
+    // Carried up when context is added.
+    let error = error.context("outer");
+    assert_eq!(error.propagations().len(), 2);
+
+    assert!(anyhow!("oh no!").propagations().is_empty());
+}
+
+#[test]
+fn test_plain() {
+    let error = anyhow!("oh no!").plain();
+    assert_eq!(error.to_string(), "oh no!");
+
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io)
+        .context("write failed")
+        .map_err(anyhow_context!("saving {}", "report"))
+        .unwrap_err();
+    let error = error.plain();
+    let mut chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    // With "with-location" the context has a tagged level of its own, and
+    // only the outermost level is stripped.
+    if cfg!(feature = "with-location") {
+        assert!(chain.remove(1).ends_with(", emsg(write failed)]"));
+    }
+    assert_eq!(chain, ["saving report", "write failed", "disk full"]);
+    assert!(error.root_cause().is::<io::Error>());
+
+    let error = Err::<(), _>("inner")
+        .map_err(anyhow_error!("outer"))
+        .unwrap_err();
+    let expected = format!("outer{}\"inner\"", END_OF_LINE);
+    assert_eq!(error.plain().to_string(), expected);
+
+    let error = Error::msg("untagged").context("also untagged").plain();
+    assert_eq!(format!("{:#}", error), "also untagged: untagged");
+}
+
+fn origin() -> (Result<()>, u32) {
+    (Err(anyhow_located!("oh no!")), line!())
+}
+
+fn hop() -> (Result<()>, Vec<u32>) {
+    let (result, first) = origin();
+    let result = result.map_err(|e| e.propagated());
+    let second = line!() - 1;
+    let result = result.map_err(anyhow_context!("hop"));
+    let third = line!() - 1;
+    let result = result.map_err(|e| e.propagated());
+    (result, vec![first, second, third, line!() - 1])
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let (result, lines) = hop();
+    let breadcrumbs = result.unwrap_err().breadcrumbs();
+    let sites: Vec<_> = breadcrumbs.sites().iter().map(|l| l.line()).collect();
+    assert_eq!(sites, lines);
+
+    let expected = format!(
+        "origin {0}:{1}:10 -> {0}:{2}:39 -> {0}:{3}:33 -> {0}:{4}:39",
+        file!(),
+        lines[0],
+        lines[1],
+        lines[2],
+        lines[3],
+    );
+    assert_eq!(breadcrumbs.to_string(), expected);
+
+    assert_eq!(
+        Error::msg("untagged").breadcrumbs().to_string(),
+        "origin unknown"
+    );
+}
+
+#[test]
+fn test_origin_and_latest_location() {
+    let (result, lines) = hop();
+    let error = result.unwrap_err();
+    assert_eq!(error.origin_location().unwrap().line(), lines[0]);
+    assert_eq!(error.latest_location().unwrap().line(), lines[3]);
+
+    let (result, line) = origin();
+    let error = result.unwrap_err();
+    assert_eq!(error.origin_location().unwrap().line(), line);
+    assert_eq!(error.latest_location(), error.origin_location());
+
+    let error = error.context("unlocated context");
+    assert_eq!(error.latest_location().unwrap().line(), line);
+
+    let error = Error::msg("untagged");
+    assert_eq!(error.origin_location(), None);
+    assert_eq!(error.latest_location(), None);
+}
+
+#[test]
+fn test_hide_location() {
+    let error = Err::<(), _>(anyhow_located!("username taken"))
+        .map_err(anyhow_context!("signing up"))
+        .unwrap_err()
+        .hide_location();
+    assert_eq!(error.to_string(), "signing up");
+    assert_eq!(format!("{:#}", error), "signing up: username taken");
+    assert_eq!(
+        format!("{:?}", error).lines().take(3).collect::<Vec<_>>(),
+        ["signing up", "", "Caused by:"],
+    );
+    assert!(format!("{:?}", error).contains("\n    username taken"));
+    assert!(error.locations().all(|location| location.is_some()));
+
+    let error = error.context("request failed");
+    assert_eq!(
+        format!("{:#}", error),
+        "request failed: signing up: username taken",
+    );
+
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("handling"))
+        .unwrap_err();
+    let rendered = format!("{:#}", error);
+    assert!(rendered.starts_with(&format!("[{}:", site!())));
+    assert!(rendered.ends_with(" emsg(handling)]: request failed: signing up: username taken"));
+}
+
+#[test]
+fn test_extract_all() {
+    let line = format!(
+        "2024-05-01T12:00:00Z [INFO] req=42 handler failed: \
+         [src/api.rs:40, emsg(request failed (code 7))]{}[src/db.rs:7 emsg(timed out)] \
+         (took 3ms) [not a tag] [src/x.rs:y emsg(bad line)] [src/c.rs:9 emsg(last)]",
+        END_OF_LINE,
+    );
+    let expected = vec![
+        (
+            "src/api.rs".to_owned(),
+            40,
+            "request failed (code 7)".to_owned(),
+        ),
+        ("src/db.rs".to_owned(), 7, "timed out".to_owned()),
+        ("src/c.rs".to_owned(), 9, "last".to_owned()),
+    ];
+    assert_eq!(anyhow::extract_all(&line), expected);
+
+    let error = Err::<(), _>(anyhow_located!("no such file"))
+        .map_err(anyhow_error!("loading config"))
+        .unwrap_err();
+    let extracted = anyhow::extract_all(&format!("error: {:#}", error));
+    let messages: Vec<_> = extracted.iter().map(|(_, _, m)| m.as_str()).collect();
+    assert_eq!(messages, ["loading config", "no such file"]);
+    assert!(extracted.iter().all(|(file, _, _)| file == file!()));
+
+    assert!(anyhow::extract_all("nothing to see [here]").is_empty());
+}
+
+#[test]
+fn test_bail_location() {
+    let io_error = || io::Error::new(io::ErrorKind::Other, "oh no!");
+
+    let line = line!() + 1;
+    let literal = (|| -> Result<()> { anyhow::bail_located!("oh no!") })().unwrap_err();
+    let line_fmt = line!() + 1;
+    let fmt = (|| -> Result<()> { anyhow::bail_located!("oh {}!", "no") })().unwrap_err();
+    let line_error = line!() + 1;
+    let error = (|| -> Result<()> { anyhow::bail_located!(io_error()) })().unwrap_err();
+
+    let errors = [
+        (literal, line, 39),
+        (fmt, line_fmt, 35),
+        (error, line_error, 37),
+    ];
+    for (error, line, column) in errors {
+        let expected = format!("[{}:{}:{} emsg(oh no!)]", site!(), line, column);
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[cfg(not(feature = "compat"))]
+    {
+        let line = line!() + 1;
+        let error = (|| -> Result<()> { anyhow::bail!(io_error()) })().unwrap_err();
+        assert_eq!(error.locations().next().unwrap().unwrap().line(), line);
+    }
+}
+
+#[test]
+fn test_location() {
+    let (error, line) = (anyhow_located!("oh no!"), line!());
+    let location = error.location().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+
+    let error = Err::<(), _>(error).map_err(anyhow_context!("outer"));
+    let (error, line) = (error.unwrap_err(), line!() - 1);
+    assert_eq!(error.location().unwrap().line(), line);
+
+    assert_eq!(Error::msg("oh no!").location(), None);
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "[a.rs:1 emsg(x)]"));
+    assert_eq!(error.location(), None);
+}
+
+#[test]
+fn test_msg_at() {
+    let error = Error::msg_at("permission denied", "src/fs.rs", 12);
+    assert_eq!(error.to_string(), "permission denied");
+    assert_eq!(error.location(), Some(Location::new("src/fs.rs", 12)));
+    assert_eq!(
+        format!("{:?}", error),
+        "[src/fs.rs:12 emsg(permission denied)]",
+    );
+
+    let error = error.context("opening config");
+    assert_eq!(format!("{:#}", error), "opening config: permission denied");
+    assert_eq!(error.location(), None);
+    let locations: Vec<_> = error.locations().collect();
+    assert_eq!(locations, [None, Some(Location::new("src/fs.rs", 12))]);
+    assert_eq!(
+        format!("{:?}", error),
+        "opening config\n\nCaused by:\n    [src/fs.rs:12 emsg(permission denied)]",
+    );
+}
+
+#[test]
+fn test_anyhow_wrap() {
+    let wrap = anyhow::anyhow_wrap!("reading {}", "config");
+    let line = line!() - 1;
+    let result = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+    let error = result.map_err(wrap).unwrap_err();
+
+    let io_error = error.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(error.location().unwrap().line(), line);
+    assert_eq!(
+        format!("{:?}", error),
+        format!(
+            "[{}:{}:16, emsg(reading config)]\n\nCaused by:\n    no such file",
+            site!(),
+            line,
+        ),
+    );
+}
+
+#[derive(Debug)]
+struct DecodeError {
+    offset: usize,
+    value: Vec<u32>,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let len = self.value.len();
+        write!(f, "invalid value at offset {} of {}", self.offset, len)
+    }
+}
+
+#[test]
+fn test_anyhow_error_brief() {
+    let decode = || -> std::result::Result<(), DecodeError> {
+        Err(DecodeError {
+            offset: 3,
+            value: (0..100).collect(),
+        })
+    };
+
+    let error = decode().map_err(anyhow_error!("decoding")).unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("DecodeError { offset: 3, value: [0, 1, 2,"));
+    assert!(!message.contains("invalid value"));
+
+    let brief = anyhow_error_brief!("decoding");
+    let line = line!() - 1;
+    let error = decode().map_err(brief).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[{}:{}:17, emsg(decoding)]{}invalid value at offset 3 of 100",
+            site!(),
+            line,
+            anyhow::line_separator(),
+        ),
+    );
+    assert_eq!(error.location().unwrap().line(), line);
+}
+
+#[test]
+fn test_module_path() {
+    let (error, line) = (anyhow_located!("oh no!"), line!());
+    let context = Err::<(), _>(error).map_err(anyhow_context!("outer"));
+    let error = context.unwrap_err();
+
+    let expected = if cfg!(feature = "module-path") {
+        format!("[test_location {}:{}:47, emsg(outer)]", file!(), line + 1)
+    } else {
+        format!("[{}:{}:47, emsg(outer)]", file!(), line + 1)
+    };
+    assert_eq!(error.to_string(), expected);
+
+    let locations: Vec<_> = error.locations().map(Option::unwrap).collect();
+    assert_eq!(
+        locations,
+        [
+            Location::new(file!(), line + 1).with_column(47),
+            Location::new(file!(), line).with_column(26),
+        ],
+    );
+}
+
+// Two fallible calls on one line, which rustfmt would split: the first fails
+// if `first` is false, the second always does.
+#[rustfmt::skip]
+fn pair(first: bool) -> (Result<(u32, u32)>, u32) {
+    let f = |ok| if ok { Ok(1) } else { Err(io::Error::new(io::ErrorKind::Other, "bad")) };
+    let g = || -> Result<(u32, u32)> {
+        Ok((f(first).map_err(anyhow_error!("a"))?, f(false).map_err(anyhow_error!("b"))?))
+    };
+    (g(), line!() - 2)
+}
+
+#[test]
+fn test_column() {
+    let (first, line) = pair(false);
+    let (second, _) = pair(true);
+    let first = first.unwrap_err().location().unwrap();
+    let second = second.unwrap_err().location().unwrap();
+    assert_eq!((first.line(), second.line()), (line, line));
+    assert_eq!(first.column(), Some(30));
+    assert_eq!(second.column(), Some(69));
+    assert_eq!(first.to_string(), format!("{}:{}:30", file!(), line));
+
+    assert_eq!(Location::new("a.rs", 1).column(), None);
+    assert_eq!(Location::new("a.rs", 1).to_string(), "a.rs:1");
+}
+
+#[test]
+fn test_user_message() {
+    let error = Err::<(), _>(anyhow_located!("no such file"))
+        .map_err(anyhow_error!("loading {}", "config"))
+        .unwrap_err()
+        .context("starting up");
+    let error = Err::<(), _>(error).map_err(anyhow_context!("running [{}]", "main"));
+    let error = error.unwrap_err();
+
+    let raw = format!("{:#}", error);
+    assert!(raw.contains(" emsg(running [main])]: starting up: ["));
+    assert!(raw.contains(END_OF_LINE));
+    assert_eq!(
+        error.user_message(),
+        "running [main]: starting up: loading config: no such file",
+    );
+    assert!(format!("{:?}", error).starts_with(&format!("[{}:", site!())));
+
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "[a.rs:1 emsg(x)]"));
+    let error = error.context(anyhow_located!("tagged"));
+    assert_eq!(error.user_message(), "tagged: [a.rs:1 emsg(x)]");
+}
+
+#[test]
+fn test_emsg() {
+    assert_eq!(anyhow_located!("literal").emsg(), Some("literal"));
+    assert_eq!(anyhow_located!("format {}", 1).emsg(), Some("format 1"));
+    assert_eq!(anyhow_located!(String::from("expr")).emsg(), Some("expr"));
+    let io_error = io::Error::new(io::ErrorKind::Other, "io");
+    assert_eq!(anyhow_located!(io_error).emsg(), Some("io"));
+    #[cfg(not(feature = "deny-empty-messages"))]
+    assert_eq!(anyhow_located!().emsg(), Some("test_location"));
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing {}", "log"))
+        .unwrap_err();
+    assert_eq!(error.emsg(), Some("writing log"));
+
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("flushing"))
+        .unwrap_err();
+    assert_eq!(error.emsg(), Some("flushing"));
+
+    assert_eq!(error.context("untagged").emsg(), None);
+    assert_eq!(Error::msg("untagged").emsg(), None);
+    assert_eq!(Error::msg_at("origin", "a.rs", 1).emsg(), None);
+}
+
+#[test]
+fn test_context_twice() {
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    let line = line!() + 2;
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("writing log"))
+        .map_err(anyhow_context!("shutting down"))
+        .unwrap_err();
+    let lines: Vec<_> = error.locations().map(|l| l.map(|l| l.line())).collect();
+    assert_eq!(lines, [Some(line + 1), Some(line), None]);
+    let debug = format!("{:?}", error);
+    let outer = debug.find(&format!(":{}:18, emsg(shutting down)]", line + 1));
+    let inner = debug.find(&format!(":{}:18, emsg(writing log)]", line));
+    assert!(outer.unwrap() < inner.unwrap());
+
+    // A tagged value as the context gets a frame of its own.
+    let tagged = anyhow_located!("tagged");
+    let tagged_line = line!() - 1;
+    let error = Err::<(), _>(error).map_err(anyhow_context!(tagged));
+    let line = line!() - 1;
+    assert_eq!(
+        error.unwrap_err().to_string(),
+        format!(
+            "[{0}:{1}:45, emsg()]{2}[{0}:{3}:18 emsg(tagged)]",
+            site!(),
+            line,
+            END_OF_LINE,
+            tagged_line,
+        ),
+    );
+}
+
+#[cfg(not(any(feature = "compat", feature = "deny-empty-messages")))]
+mod handlers {
+    pub fn fail() -> anyhow::Error {
+        anyhow::anyhow!()
+    }
+}
+
+#[cfg(not(any(feature = "compat", feature = "deny-empty-messages")))]
+#[test]
+fn test_empty_message_module_path() {
+    let error = handlers::fail();
+    assert_eq!(error.emsg(), Some("test_location::handlers"));
+}
+
+#[test]
+fn test_prepend_location() {
+    let error = Error::msg("disk full")
+        .prepend_location("src/log.rs", 12, "writing log")
+        .prepend_location("src/main.rs", 3, format_args!("shutting down {}", 1));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[src/main.rs:3, emsg(shutting down 1)]{0}[src/log.rs:12, emsg(writing log)]{0}disk full",
+            END_OF_LINE,
+        ),
+    );
+    let lines: Vec<_> = error.locations().map(|l| l.map(|l| l.line())).collect();
+    assert_eq!(lines, [Some(3)]);
+    let frames: Vec<_> = error
+        .parsed_frames()
+        .map(|frame| (frame.file, frame.line, frame.msg))
+        .collect();
+    assert_eq!(
+        frames[..3],
+        [
+            ("src/main.rs".to_owned(), 3, "shutting down 1".to_owned()),
+            ("src/log.rs".to_owned(), 12, "writing log".to_owned()),
+            (String::new(), 0, "disk full".to_owned()),
+        ],
+    );
+}
+
+#[test]
+fn test_code() {
+    let error = anyhow_code!("E1042", "no user {}", 7);
+    let line = line!() - 1;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17 code(E1042) emsg(no user 7)]", site!(), line),
+    );
+    assert_eq!(error.code(), Some("E1042"));
+    let location = error.location().unwrap();
+    assert_eq!(
+        (location.file(), location.line(), location.column()),
+        (file!(), line, Some(17)),
+    );
+    assert_eq!(error.emsg(), Some("no user 7"));
+    assert_eq!(
+        anyhow::extract_all(&error.to_string()),
+        [(file!().to_owned(), line, "no user 7".to_owned())],
+    );
+
+    // The code survives context as an attachment, and the tag as a frame of
+    // anyhow_error!.
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("loading"))
+        .unwrap_err();
+    assert_eq!(error.code(), Some("E1042"));
+    let error = Err::<(), _>(anyhow_code!("E7", "timed out"))
+        .map_err(anyhow_error!("fetching"))
+        .unwrap_err();
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!(frames[1].msg, "timed out");
+    assert_eq!(frames[1].file, file!());
+    assert!(error.to_string().contains(" code(E7) emsg(timed out)]"));
+}
+
+#[test]
+fn test_new_tagged() {
+    let a = Error::new_tagged(site!(), line!() + 1, "x");
+    let b = anyhow_located!("x");
+    assert_eq!(a.to_string(), b.to_string());
+    assert_eq!(a.location(), b.location());
+
+    let a = Error::new_tagged("src/db.rs", 7, format_args!("{} rows", 3));
+    assert!(a.to_string().starts_with("[src/db.rs:7:"), "{}", a);
+    assert!(a.to_string().ends_with(" emsg(3 rows)]"), "{}", a);
+}
+
+#[test]
+fn test_map_err_at() {
+    let write =
+        || -> Result<(), io::Error> { Err(io::Error::new(io::ErrorKind::Other, "disk full")) };
+    let writing = anyhow::map_err_at::<io::Error, _>("src/log.rs", 40, "writing log");
+
+    let error = write().map_err(&writing).unwrap_err();
+    let expected = format!("[src/log.rs:40, emsg(writing log)]{}Custom", END_OF_LINE);
+    assert!(error.to_string().starts_with(&expected), "{}", error);
+    let location = error.location().unwrap();
+    assert_eq!((location.file(), location.line()), ("src/log.rs", 40));
+
+    // The same closure can be applied again later.
+    let again = write().map_err(writing).unwrap_err();
+    assert_eq!(again.to_string(), error.to_string());
+
+    let loading = anyhow::context_at("src/config.rs", 3, format!("loading {}", "config"));
+    let error = loading(anyhow!("no such file"));
+    assert_eq!(error.to_string(), "[src/config.rs:3, emsg(loading config)]");
+    let error = loading(error);
+    assert_eq!(error.chain().count(), 3);
+}
+
+#[test]
+fn test_anyhow_error_across_threads() {
+    fn send_sync<F>(f: F) -> F
+    where
+        F: FnOnce(io::Error) -> Error + Send + Sync,
+    {
+        f
+    }
+    fn read() -> Result<(), io::Error> {
+        Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+    }
+
+    let name = String::from("config");
+    let error = read().map_err(send_sync(anyhow_error!(name))).unwrap_err();
+    assert!(error.to_string().contains(", emsg(config)]"));
+
+    // The literal form borrows nothing, so it can be moved to another thread.
+    let tagger = send_sync(anyhow_error!("from main"));
+    let worker = std::thread::spawn(move || read().map_err(tagger));
+    let error = worker.join().unwrap().unwrap_err();
+    assert!(error.to_string().contains(", emsg(from main)]"));
+
+    let worker = std::thread::spawn(|| {
+        let id = 7;
+        read().map_err(anyhow_error!("worker {}", id))
+    });
+    let error = worker.join().unwrap().unwrap_err();
+    assert!(error.to_string().contains(", emsg(worker 7)]"));
+}
+
+#[test]
+fn test_tagged() {
+    let received = vec![("svc/src/db.rs".to_owned(), 42_u32, "timed out")];
+    for (file, line, msg) in &received {
+        let error = anyhow::tagged(file, *line, msg);
+        assert_eq!(error.to_string(), "[svc/src/db.rs:42 emsg(timed out)]");
+        let location = error.location().unwrap();
+        assert_eq!((location.file(), location.line()), ("svc/src/db.rs", 42));
+        assert_eq!(location.column(), None);
+        assert_eq!(error.outermost_message(), Some("timed out"));
+    }
+
+    let error = anyhow::tagged("a.rs", 1, format_args!("{} of {}", 3, 4));
+    assert_eq!(error.to_string(), "[a.rs:1 emsg(3 of 4)]");
+}
+
+#[track_caller]
+#[allow(clippy::incompatible_msrv)]
+fn lookup_caller(key: &str) -> Error {
+    let caller = std::panic::Location::caller();
+    match key {
+        "port" => anyhow_located!("missing key" @ caller),
+        _ => anyhow_located!("missing key {}", key @ caller),
+    }
+}
+
+#[track_caller]
+#[allow(clippy::incompatible_msrv)]
+fn bail_caller(limit: usize, len: usize) -> Result<()> {
+    anyhow::ensure_located!(len <= limit, "too long" @ std::panic::Location::caller());
+    anyhow::bail_located!("len {} of {}", len, limit @ std::panic::Location::caller());
+}
+
+#[test]
+fn test_location_override() {
+    let (error, line) = (lookup_caller("port"), line!());
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:26 emsg(missing key)]", file!(), line),
+    );
+    let (error, line) = (lookup_caller("host"), line!());
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:26 emsg(missing key host)]", file!(), line),
+    );
+    assert_eq!(error.location().unwrap().line(), line);
+
+    let (error, line) = (bail_caller(1, 2).unwrap_err(), line!());
+    assert!(error
+        .to_string()
+        .ends_with(&format!(":{}:26 emsg(too long)]", line)));
+    let (error, line) = (bail_caller(3, 2).unwrap_err(), line!());
+    assert!(error
+        .to_string()
+        .ends_with(&format!(":{}:26 emsg(len 2 of 3)]", line)));
+
+    // Without an override, the arguments are formatted as ever.
+    let error = anyhow_located!("{} {}", 1, "two");
+    assert!(error.to_string().ends_with(" emsg(1 two)]"));
+}
+
+#[test]
+fn test_is_tagged() {
+    let untagged = cfg!(any(feature = "compat", feature = "structured-location"));
+    assert!(anyhow!("x").is_tagged() != untagged);
+    assert!(anyhow_located!("x").is_tagged());
+    assert!(!Error::msg("x").is_tagged());
+    assert!(!Error::msg("[x").is_tagged());
+    assert!(!Error::new(io::Error::new(io::ErrorKind::Other, "x")).is_tagged());
+
+    let error = Err::<(), _>(Error::msg("x"))
+        .map_err(anyhow_context!("loading"))
+        .unwrap_err();
+    assert!(error.is_tagged());
+    assert!(!error.context("untagged").is_tagged());
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "x"))
+        .map_err(anyhow::anyhow_wrap!("reading"))
+        .unwrap_err();
+    assert!(error.is_tagged());
+}
+
+#[test]
+fn test_anyhow_error_nested() {
+    use std::fmt::{self, Debug, Display};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counting;
+
+    impl Display for Counting {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("disk full")
+        }
+    }
+
+    impl Debug for Counting {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            FORMATTED.fetch_add(1, Ordering::SeqCst);
+            f.write_str("Counting")
+        }
+    }
+
+    impl std::error::Error for Counting {}
+
+    fn level(error: impl Debug, n: usize) -> Error {
+        Err::<(), _>(error)
+            .map_err(anyhow_error!("level {}", n))
+            .unwrap_err()
+    }
+
+    // A foreign error is rendered into the message once; the anyhow::Error
+    // made from it is kept under each new tag as it is.
+    let mut error = level(Counting, 0);
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), 1);
+    let mut lengths = Vec::new();
+    let mut frames = anyhow::parse_frames(&error);
+    for n in 1..6 {
+        error = level(error, n);
+        assert_eq!(FORMATTED.load(Ordering::SeqCst), 1);
+
+        let nested = anyhow::parse_frames(&error);
+        assert_eq!(nested.len(), frames.len() + 1);
+        assert_eq!(nested[0].msg, format!("level {}", n));
+        assert_eq!(nested[1..], frames[..]);
+        frames = nested;
+        lengths.push(format!("{:?}", error).len());
+    }
+    assert_eq!(error.emsg(), Some("level 5"));
+    assert_eq!(error.location().unwrap().file(), file!());
+    assert_eq!(error.chain().count(), 1);
+
+    // Each level adds only its own frame, the same as wrapping a foreign
+    // error does.
+    let foreign = level(io::Error::new(io::ErrorKind::Other, "disk full"), 1);
+    let growth = format!("{:?}", foreign).len()
+        - format!("{:?}", io::Error::new(io::ErrorKind::Other, "disk full")).len();
+    for pair in lengths.windows(2) {
+        assert_eq!(pair[1] - pair[0], growth);
+    }
+}
+
+#[test]
+fn test_downcast_location() {
+    use anyhow::Location;
+
+    let error = anyhow_located!("disk full");
+    let line = line!() - 1;
+    let location = error.downcast_ref::<Location>().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+    assert_eq!(Some(location.clone()), error.location());
+    assert!(error.is::<Location>());
+
+    // The underlying error and the location of the tag over it.
+    let error = anyhow_located!(io::Error::new(io::ErrorKind::NotFound, "no config"));
+    let line = line!() - 1;
+    assert_eq!(
+        error.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert_eq!(error.downcast_ref::<Location>().unwrap().line(), line);
+
+    // The outermost tag is the one found.
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_error!("loading"))
+        .unwrap_err();
+    let line = line!() - 2;
+    assert_eq!(error.downcast_ref::<Location>().unwrap().line(), line);
+
+    // A Location in the chain is found as it is.
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .context(Location::new("src/config.rs", 12));
+    assert_eq!(error.downcast_ref::<Location>().unwrap().line(), 12);
+
+    assert!(Error::msg("no location")
+        .downcast_ref::<Location>()
+        .is_none());
+    assert!(
+        Error::new(io::Error::new(io::ErrorKind::Other, "disk full"))
+            .downcast_ref::<Location>()
+            .is_none()
+    );
+}
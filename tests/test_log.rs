@@ -0,0 +1,85 @@
+#![cfg(feature = "log-integration")]
+
+use anyhow::{anyhow_error, fail_metric, Error, Result};
+use log::kv::Key;
+use log::{Level, Log, Metadata, Record};
+use std::sync::Mutex;
+
+#[derive(Debug, PartialEq)]
+struct Logged {
+    level: Level,
+    target: String,
+    message: String,
+    file: String,
+    line: u64,
+}
+
+struct Capture(Mutex<Vec<Logged>>);
+
+impl Log for Capture {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let kv = record.key_values();
+        let file = kv.get(Key::from("file")).unwrap().to_string();
+        let line = kv.get(Key::from("line")).unwrap().to_u64().unwrap();
+        self.0.lock().unwrap().push(Logged {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            file,
+            line,
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+fn parse_port(s: &str, failures: &mut u32) -> Result<u16> {
+    match s.parse() {
+        Ok(port) => Ok(port),
+        Err(_) => fail_metric!(|| *failures += 1, "invalid port {:?}", s),
+    }
+}
+
+// The logger is process-wide, so everything that logs lives in this single
+// test.
+#[test]
+fn test_log_integration() {
+    let capture: &'static Capture = Box::leak(Box::new(Capture(Mutex::new(Vec::new()))));
+    log::set_logger(capture).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let _ = Err::<(), _>(Error::msg("disk full"))
+        .map_err(anyhow_error!("writing {}", "log"))
+        .unwrap_err();
+    let line = line!() - 2;
+
+    let mut failures = 0;
+    let error = parse_port("eighty", &mut failures).unwrap_err();
+    assert_eq!(failures, 1);
+    let fail_line = error.location().unwrap().line();
+
+    let logged = capture.0.lock().unwrap();
+    assert_eq!(
+        *logged,
+        [
+            Logged {
+                level: Level::Error,
+                target: module_path!().to_owned(),
+                message: "writing log".to_owned(),
+                file: file!().to_owned(),
+                line: u64::from(line),
+            },
+            Logged {
+                level: Level::Error,
+                target: module_path!().to_owned(),
+                message: "invalid port \"eighty\"".to_owned(),
+                file: file!().to_owned(),
+                line: u64::from(fail_line),
+            },
+        ],
+    );
+}
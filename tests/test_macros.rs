@@ -3,7 +3,11 @@
 mod common;
 
 use self::common::*;
-use anyhow::ensure;
+use anyhow::{
+    anyhow_located, categorize, ensure, ensure_ctx, ensure_eq, ensure_ne, ensure_with, fail_metric,
+    must, steps, Counter, Result,
+};
+use std::io;
 
 #[test]
 fn test_messages() {
@@ -37,8 +41,387 @@ fn test_ensure() {
         ensure!(v + v == 1);
         Ok(())
     };
+    let error = f().unwrap_err().to_string();
+    if cfg!(feature = "compat") {
+        assert_eq!(error, "Condition failed: `v + v == 1`");
+    } else {
+        assert!(error.ends_with(" emsg(Condition failed: `v + v == 1` (left: `2`, right: `1`))]"));
+    }
+}
+
+#[test]
+fn test_ensure_operands() {
+    let a = 1;
+    let b = 2;
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(a + 1 == b + 1);
+        Ok(())
+    };
+    assert_eq!(
+        f().unwrap_err().to_string(),
+        format!(
+            "[{}:{}:9 emsg(Condition failed: `a + 1 == b + 1` (left: `2`, right: `3`))]",
+            site!(),
+            line,
+        ),
+    );
+
+    let name = String::from("x");
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(name != "x",);
+        Ok(())
+    };
+    assert!(f()
+        .unwrap_err()
+        .to_string()
+        .ends_with(r#" emsg(Condition failed: `name != "x"` (left: `"x"`, right: `"x"`))]"#));
+
+    // `&&` binds more loosely than `==`, so the condition is not split.
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(a == 1 && b == 1);
+        Ok(())
+    };
+    assert!(f()
+        .unwrap_err()
+        .to_string()
+        .ends_with(" emsg(Condition failed: `a == 1 && b == 1`)]"));
+
+    #[derive(PartialEq)]
+    struct NoDebug(i32);
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(NoDebug(a) == NoDebug(b));
+        Ok(())
+    };
+    assert!(f()
+        .unwrap_err()
+        .to_string()
+        .ends_with(" emsg(Condition failed: `NoDebug(a) == NoDebug(b)`)]"));
+}
+
+#[test]
+fn test_ensure_location() {
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(false);
+        Ok(())
+    };
+    let expected = format!("[{}:{}:9 emsg(Condition failed: `false`)]", site!(), line);
+    assert!(format!("{:?}", f().unwrap_err()).starts_with(&expected));
+
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        anyhow::ensure_located!(false, "msg");
+        Ok(())
+    };
+    let expected = format!("[{}:{}:9 emsg(msg)]", site!(), line);
+    assert!(format!("{:?}", f().unwrap_err()).starts_with(&expected));
+}
+
+#[test]
+fn test_ensure_eq() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let next = || {
+        calls.set(calls.get() + 1);
+        calls.get()
+    };
+
+    let f = || -> Result<()> {
+        ensure_eq!(next(), 1);
+        Ok(())
+    };
+    assert!(f().is_ok());
+    assert_eq!(calls.get(), 1);
+
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        ensure_eq!(next(), 1);
+        Ok(())
+    };
+    assert_eq!(
+        f().unwrap_err().to_string(),
+        format!(
+            "[{}:{}:9 emsg(Condition failed: `next() == 1` (left: `2`, right: `1`))]",
+            site!(),
+            line,
+        ),
+    );
+    assert_eq!(calls.get(), 2);
+
+    let v = "one";
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        ensure_eq!(v, "two", "wrong value for {}", "v");
+        Ok(())
+    };
     assert_eq!(
         f().unwrap_err().to_string(),
-        "Condition failed: `v + v == 1`",
+        format!(
+            "[{}:{}:9 emsg(wrong value for v (left: `\"one\"`, right: `\"two\"`))]",
+            site!(),
+            line,
+        ),
+    );
+}
+
+#[test]
+fn test_ensure_ne() {
+    let f = || -> Result<()> {
+        ensure_ne!(1 + 1, 3);
+        Ok(())
+    };
+    assert!(f().is_ok());
+
+    let f = || -> Result<()> {
+        ensure_ne!(1 + 1, 2);
+        Ok(())
+    };
+    assert!(f()
+        .unwrap_err()
+        .to_string()
+        .ends_with(" emsg(Condition failed: `1 + 1 != 2` (left: `2`, right: `2`))]"));
+
+    let f = || -> Result<()> {
+        ensure_ne!(1 + 1, 2, "values must differ");
+        Ok(())
+    };
+    assert!(f()
+        .unwrap_err()
+        .to_string()
+        .ends_with(" emsg(values must differ (left: `2`, right: `2`))]"));
+}
+
+#[test]
+fn test_ensure_ctx() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let detail = || {
+        calls.set(calls.get() + 1);
+        format!("calls so far: {}", calls.get())
+    };
+
+    let v = 1;
+    let f = || -> Result<()> {
+        ensure_ctx!(v + v == 2, detail);
+        Ok(())
+    };
+    assert!(f().is_ok());
+    assert_eq!(calls.get(), 0);
+
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        ensure_ctx!(v + v == 1, detail);
+        Ok(())
+    };
+    let error = f().unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "[{}:{}:9 emsg(Condition failed: `v + v == 1`: calls so far: 1)]",
+            site!(),
+            line,
+        ),
+    );
+}
+
+#[test]
+fn test_ensure_with() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let message = || {
+        calls.set(calls.get() + 1);
+        format!("calls so far: {}", calls.get())
+    };
+
+    let v = 1;
+    let f = || -> Result<()> {
+        ensure_with!(v + v == 2, message);
+        Ok(())
+    };
+    assert!(f().is_ok());
+    assert_eq!(calls.get(), 0);
+
+    let line = line!() + 2;
+    let f = || -> Result<()> {
+        ensure_with!(v + v == 1, message);
+        Ok(())
+    };
+    let error = f().unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:9 emsg(calls so far: 1)]", site!(), line),
+    );
+}
+
+#[test]
+fn test_fail_metric() {
+    struct MockCounter(usize);
+
+    impl Counter for MockCounter {
+        fn increment(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    let mut counter = MockCounter(0);
+    let mut f = |fail: bool| -> Result<()> {
+        if fail {
+            fail_metric!(counter, "failed with {}", 1);
+        }
+        Ok(())
+    };
+    assert!(f(false).is_ok());
+    let error = f(true).unwrap_err();
+    assert!(error.to_string().ends_with(" emsg(failed with 1)]"));
+    assert!(f(true).is_err());
+    assert_eq!(counter.0, 2);
+
+    let mut calls = 0;
+    let mut f = || -> Result<()> {
+        fail_metric!(|| calls += 1, "oh no!");
+    };
+    assert!(f().is_err());
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_steps() {
+    fn connect() -> Result<()> {
+        Ok(())
+    }
+    fn authenticate() -> Result<(), io::Error> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+    }
+    fn fetch() -> Result<()> {
+        unreachable!()
+    }
+
+    let line = line!() + 4;
+    let f = || -> Result<()> {
+        steps! {
+            "connect" => connect()?;
+            "authenticate" => authenticate()?;
+            "fetch" => fetch()?;
+        }
+        Ok(())
+    };
+    let error = f().unwrap_err();
+    let expected = format!("[{}:{}, emsg(authenticate)]", file!(), line);
+    assert_eq!(error.to_string(), expected);
+    assert_eq!(error.root_cause().to_string(), "denied");
+
+    let f = || -> Result<()> {
+        steps! {
+            "connect" => connect()?;
+            "fetch" => Err(anyhow::Error::msg("timed out"))?
+        }
+        Ok(())
+    };
+    let expected = format!("[{}:{}, emsg(fetch)]: timed out", file!(), line!() - 4);
+    assert_eq!(format!("{:#}", f().unwrap_err()), expected);
+}
+
+#[test]
+fn test_must() {
+    let n: u32 = must!("7".parse());
+    assert_eq!(n, 7);
+
+    let result = std::panic::catch_unwind(|| {
+        let failing = || -> Result<()> { Err(anyhow_located!("oh no!")) };
+        must!(failing().map_err(|e| e.context("setting up")))
+    });
+    let payload = result.unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    let line = line!() - 4;
+    let prefix = format!(
+        "must! failed at {}:{}: setting up\n\nCaused by:\n",
+        file!(),
+        line
     );
+    assert!(message.starts_with(&prefix), "{}", message);
+    let origin = format!("[{}:{}:46 emsg(oh no!)]", site!(), line - 1);
+    assert!(message.contains(&origin), "{}", message);
+}
+
+#[test]
+fn test_categorize() {
+    #[derive(Debug, PartialEq)]
+    enum Category {
+        Network,
+        Storage,
+    }
+    use Category::*;
+
+    fn connect(fail: bool) -> Result<u16> {
+        if fail {
+            Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))?;
+        }
+        Ok(443)
+    }
+
+    let ok = categorize!(Network, { connect(false)? + 1 });
+    assert_eq!(ok.unwrap(), 444);
+
+    let error = categorize!(Network, {
+        let port = connect(true)?;
+        port + 1
+    })
+    .unwrap_err();
+    assert_eq!(error.category::<Category>(), Some(&Network));
+    assert!(error.root_cause().is::<io::Error>());
+
+    let error = error.context("syncing").with_category(Storage);
+    assert_eq!(error.category::<Category>(), Some(&Storage));
+    assert_eq!(error.attachment::<Category>(), None);
+}
+
+#[test]
+fn test_captured_identifiers() {
+    let id = 7;
+    let fail = || Err::<(), _>(io::Error::new(io::ErrorKind::Other, "refused"));
+
+    let error = anyhow_located!("no user {id} in {{users}}");
+    assert!(error.to_string().ends_with(" emsg(no user 7 in {users})]"));
+    let error = anyhow::anyhow_basic!("no user {id} in {{users}}");
+    assert_eq!(error.to_string(), "no user 7 in {users}");
+    let error = anyhow::anyhow_basic!("no {{user}}");
+    assert_eq!(error.downcast_ref::<&str>(), Some(&"no {user}"));
+    let error = anyhow_located!("no user {id} in {}", "users");
+    assert!(error.to_string().ends_with(" emsg(no user 7 in users)]"));
+
+    let error = fail()
+        .map_err(anyhow::anyhow_error!("connecting {id} {{once}}"))
+        .unwrap_err();
+    assert!(error.to_string().contains(", emsg(connecting 7 {once})]"));
+    let error = fail()
+        .map_err(anyhow::anyhow_error_brief!("connecting {id} {{once}}"))
+        .unwrap_err();
+    assert!(error.to_string().contains(", emsg(connecting 7 {once})]"));
+    let error = fail()
+        .map_err(anyhow::anyhow_wrap!("connecting {id} {{once}}"))
+        .unwrap_err();
+    assert!(error.to_string().ends_with(", emsg(connecting 7 {once})]"));
+    let error = Err::<(), _>(error)
+        .map_err(anyhow::anyhow_context!("syncing {id} {{once}}"))
+        .unwrap_err();
+    assert!(error.to_string().ends_with(", emsg(syncing 7 {once})]"));
+    let error = Err::<(), _>(error)
+        .map_err(anyhow::anyhow_context!("syncing {id} {}", "again"))
+        .unwrap_err();
+    assert!(error.to_string().ends_with(", emsg(syncing 7 again)]"));
+
+    let result: Result<()> = (|| {
+        anyhow::ensure_located!(id == 8, "expected {id} to be 8, {{not}} {}", id);
+        Ok(())
+    })();
+    let error = result.unwrap_err();
+    assert!(error
+        .to_string()
+        .ends_with(" emsg(expected 7 to be 8, {not} 7)]"));
 }
@@ -0,0 +1,48 @@
+use anyhow::{anyhow_context, anyhow_located, Context};
+use std::io;
+
+#[test]
+fn test_markdown() {
+    // Must be set before the first backtrace is captured in this process.
+    #[cfg(feature = "backtrace")]
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let io = io::Error::new(io::ErrorKind::Other, "disk full");
+    let error = Err::<(), _>(io)
+        .context("write failed")
+        .map_err(anyhow_context!("saving report"))
+        .unwrap_err();
+    let line = line!() - 2;
+
+    let markdown = error.to_markdown();
+    let expected = if cfg!(feature = "with-location") {
+        format!(
+            "**saving report** `{0}:{1}:18`\n\n- write failed `{0}:{2}:10`\n  - write failed\n    - disk full\n",
+            file!(),
+            line,
+            line - 1,
+        )
+    } else {
+        format!(
+            "**saving report** `{}:{}:18`\n\n- write failed\n  - disk full\n",
+            file!(),
+            line,
+        )
+    };
+    assert!(markdown.starts_with(&expected), "{}", markdown);
+
+    let error = anyhow_located!("oh no!").context("outer");
+    let markdown = error.to_markdown();
+    assert!(
+        markdown.starts_with("**outer**\n\n- oh no! `"),
+        "{}",
+        markdown
+    );
+
+    #[cfg(feature = "backtrace")]
+    {
+        let backtrace = &markdown[markdown.find("\n\n```text\n").unwrap()..];
+        assert!(backtrace.ends_with("\n```\n"));
+        assert_eq!(backtrace.matches("```").count(), 2);
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(debug_assertions)]
+
+use anyhow::{anyhow, Error};
+use std::cell::RefCell;
+
+thread_local! {
+    // The hook runs on the thread that drops the error.
+    static DROPPED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+fn record(error: &Error) {
+    DROPPED.with(|dropped| dropped.borrow_mut().push(error.to_string()));
+}
+
+fn dropped() -> Vec<String> {
+    DROPPED.with(|dropped| dropped.borrow_mut().drain(..).collect())
+}
+
+// The hook is process-wide, so everything that depends on it lives in this
+// single test.
+#[test]
+fn test_must_use() {
+    anyhow::set_unobserved_hook(record);
+
+    drop(Error::msg("ignored").must_use());
+    assert_eq!(dropped(), ["ignored"]);
+
+    let error = Error::msg("displayed").must_use();
+    let _ = error.to_string();
+    drop(error);
+
+    let error = Error::msg("debugged").must_use();
+    let _ = format!("{:?}", error);
+    drop(error);
+
+    let error = anyhow!("downcast").must_use();
+    assert!(error.downcast_ref::<std::io::Error>().is_none());
+    drop(error);
+
+    let error = Error::msg("unwrapped").must_use().into_inner();
+    drop(error);
+
+    assert!(dropped().is_empty());
+}
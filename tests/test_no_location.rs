@@ -0,0 +1,39 @@
+#![cfg(feature = "no-location")]
+
+use anyhow::{anyhow, anyhow_context, anyhow_error, bail, ensure, Result};
+use std::io;
+
+#[test]
+fn test_plain_output() {
+    assert_eq!(anyhow!("foo").to_string(), "foo");
+    assert_eq!(anyhow!("foo {}", 1).to_string(), "foo 1");
+
+    let f = || -> Result<()> { bail!("bar") };
+    assert_eq!(f().unwrap_err().to_string(), "bar");
+
+    let f = || -> Result<()> {
+        ensure!(1 + 1 == 3);
+        Ok(())
+    };
+    assert_eq!(
+        f().unwrap_err().to_string(),
+        "Condition failed: `1 + 1 == 3`"
+    );
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing"))
+        .unwrap_err();
+    let line = line!() - 2;
+    assert!(error
+        .to_string()
+        .starts_with(&format!("[{}:{}:18, emsg(writing)]", file!(), line)));
+
+    let error = Err::<(), _>(anyhow!("foo"))
+        .map_err(anyhow_context!("bar"))
+        .unwrap_err();
+    let line = line!() - 2;
+    assert_eq!(
+        format!("{:#}", error),
+        format!("[{}:{}:18, emsg(bar)]: foo", file!(), line),
+    );
+}
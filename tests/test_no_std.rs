@@ -0,0 +1,43 @@
+// The location macros must expand in a crate without the standard prelude,
+// where `format!` is not in scope.
+#![no_std]
+
+extern crate alloc;
+extern crate std;
+
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use anyhow::{anyhow_context, anyhow_error, anyhow_located, Error};
+
+#[derive(Debug)]
+struct Io;
+
+#[test]
+fn test_macros_without_prelude() {
+    let error = Err::<(), _>(Io)
+        .map_err(anyhow_error!("writing {}", "log"))
+        .unwrap_err();
+    assert!(error.to_string().ends_with("Io"));
+
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("flushing {}", "log"))
+        .unwrap_err();
+    assert!(error.to_string().ends_with(", emsg(flushing log)]"));
+
+    let error: Error = anyhow_located!("closing {}", "log");
+    assert!(error.to_string().ends_with(" emsg(closing log)]"));
+}
+
+// Without std, anyhow::Error derefs to core::error::Error on toolchains that
+// have it, as it does to std::error::Error (the same trait) with std.
+#[test]
+#[allow(clippy::incompatible_msrv)]
+fn test_core_error() {
+    let error = anyhow_located!("closing {}", "log");
+    let core_error: &dyn core::error::Error = error.as_ref();
+    assert!(core_error.to_string().ends_with(" emsg(closing log)]"));
+    assert!(core_error.source().is_none());
+
+    let boxed: Box<dyn core::error::Error + Send + Sync> = error.into();
+    assert!(boxed.to_string().ends_with(" emsg(closing log)]"));
+}
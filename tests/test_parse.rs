@@ -0,0 +1,654 @@
+#![cfg(not(feature = "deny-empty-messages"))]
+use anyhow::{anyhow_context, anyhow_error, anyhow_located, parse::ParsedFrame, Error, Result};
+use std::io;
+
+// The chain of examples/result_error.rs.
+fn err1_3() -> Result<()> {
+    let err = anyhow_located!("1_3");
+    Err(anyhow_located!(err))
+}
+
+fn err4() -> Result<()> {
+    err1_3().map_err(anyhow_error!("4_1"))?;
+    Ok(())
+}
+
+fn err4_2() -> Result<()> {
+    err4().map_err(anyhow_error!("4_2:{}", "4_2"))?;
+    Ok(())
+}
+
+fn err4_3() -> Result<()> {
+    let err = anyhow_located!("4_3");
+    err4_2().map_err(anyhow_error!(err))?;
+    Ok(())
+}
+
+fn err4_4() -> Result<()> {
+    err4_3().map_err(anyhow_error!())?;
+    Ok(())
+}
+
+#[test]
+fn test_parse_frames() {
+    let error = err4_4().unwrap_err();
+    let frames = anyhow::parse_frames(&error);
+    let messages: Vec<_> = frames.iter().map(|frame| frame.msg.as_str()).collect();
+    let nested = |line| format!("[{}:{}:15 emsg({})]", site!(), line, "4_3");
+    // anyhow_located!(err) puts its own frame in front of the tagged err
+    // rather than wrapping it.
+    assert_eq!(
+        messages[..6],
+        ["", &nested(22), "4_2:4_2", "4_1", "", "1_3"],
+    );
+    assert!(frames[..6].iter().all(|frame| frame.file == file!()));
+    let lines: Vec<_> = frames[..6].iter().map(|frame| frame.line).collect();
+    assert_eq!(lines, [28, 23, 17, 12, 8, 7]);
+
+    // With RUST_BACKTRACE set, the backtrace follows as one more frame.
+    for frame in &frames[6..] {
+        assert_eq!((frame.file.as_str(), frame.line), ("", 0));
+    }
+}
+
+#[test]
+fn test_root_frame() {
+    let root = err4_4().unwrap_err().root_frame().unwrap();
+    assert_eq!(
+        root,
+        ParsedFrame {
+            file: file!().to_owned(),
+            line: 7,
+            msg: "1_3".to_owned(),
+        },
+    );
+
+    assert_eq!(Error::msg("untagged").root_frame(), None);
+}
+
+#[test]
+fn test_depth() {
+    let err1 = || -> Result<()> { Err(anyhow_located!("1_1")) };
+    assert_eq!(err1().unwrap_err().depth(), 1);
+    assert_eq!(err4_4().unwrap_err().depth(), 6);
+    assert_eq!(Error::msg("untagged").depth(), 0);
+}
+
+#[test]
+fn test_debug_multiline() {
+    let error = err4_4().unwrap_err();
+    let debug = format!("{:?}", error);
+    let frames = debug.split("\n\nStack backtrace:").next().unwrap();
+    assert_eq!(frames.matches('\n').count(), 5, "{}", debug);
+    assert!(!debug.contains(anyhow::line_separator()), "{}", debug);
+    assert!(frames.lines().next().unwrap().ends_with(":28:22, emsg()]"));
+
+    // `{:-?}` keeps the separators, and `{}` stays on one line.
+    let raw = format!("{:-?}", error);
+    assert_eq!(raw.replace(anyhow::line_separator(), "\n"), debug);
+    assert!(!error.to_string().contains('\n'));
+    assert_eq!(
+        error.to_string().matches(anyhow::line_separator()).count(),
+        5
+    );
+}
+
+#[test]
+fn test_parsed_frames() {
+    let error = err4_4().unwrap_err();
+    let frames = error.parsed_frames();
+    let lines: Vec<_> = frames.clone().take(6).map(|frame| frame.line).collect();
+    assert_eq!(lines, [28, 23, 17, 12, 8, 7]);
+    assert_eq!(frames.collect::<Vec<_>>(), anyhow::parse_frames(&error));
+}
+
+#[test]
+fn test_parse_foreign() {
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing"))
+        .unwrap_err();
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!(frames[0].msg, "writing");
+    assert_eq!(
+        frames[1],
+        ParsedFrame {
+            file: String::new(),
+            line: 0,
+            msg: format!("{:?}", io::Error::new(io::ErrorKind::Other, "disk full")),
+        },
+    );
+
+    let frames = anyhow::parse_frames(&Error::msg("plain"));
+    assert_eq!(frames[0].msg, "plain");
+    assert_eq!(frames[0].file, "");
+}
+
+#[test]
+fn test_parse_rewrapped() {
+    let error = anyhow_located!("inner");
+    let line = line!() - 1;
+    let error = anyhow_located!(error);
+    let expected = format!(
+        "[{0}:{1}:17 emsg()]{2}[{0}:{3}:17 emsg(inner)]",
+        site!(),
+        line + 2,
+        anyhow::line_separator(),
+        line,
+    );
+    assert_eq!(error.to_string(), expected);
+
+    // A plain message, even one that mentions a tag, is wrapped as usual.
+    let error = anyhow_located!(String::from("not a tag: [a.rs:1 emsg(x)]"));
+    let line = line!() - 1;
+    let expected = format!(
+        "[{}:{}:17 emsg(not a tag: [a.rs:1 emsg(x)])]",
+        site!(),
+        line
+    );
+    assert_eq!(error.to_string(), expected);
+}
+
+#[test]
+fn test_render_tree() {
+    let tree = err4_4().unwrap_err().render_tree();
+    let golden = format!(
+        "\
+(no message)
+  at {0}:28
+  [{1}:22:15 emsg(4_3)]
+    at {0}:23
+    4_2:4_2
+      at {0}:17
+      4_1
+        at {0}:12
+        (no message)
+          at {0}:8
+          1_3
+            at {0}:7
+",
+        file!(),
+        site!(),
+    );
+    assert!(tree.starts_with(&golden), "{}", tree);
+}
+
+#[test]
+fn test_write_tree() {
+    let error = err4_4().unwrap_err();
+    let mut tree = String::new();
+    error.write_tree(&mut tree).unwrap();
+    assert_eq!(tree, error.render_tree());
+
+    let mut bytes = Vec::new();
+    error.write_tree_io(&mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), tree);
+
+    let mut full = [0; 16];
+    let error = error.write_tree_io(&mut &mut full[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+    assert_eq!(&full, b"(no message)\n  a");
+}
+
+#[test]
+fn test_frame_search() {
+    let error = err4_4().unwrap_err();
+    assert!(error.frame_contains("1_3"));
+    assert!(!error.frame_contains("1_4"));
+
+    // The outermost match wins: 4_3 is nested in the message of the frame
+    // that anyhow_error!(err) made.
+    let frame = error.first_frame_with("4_").unwrap();
+    assert_eq!(frame.line, 23);
+    assert_eq!(frame.msg, format!("[{}:22:15 emsg(4_3)]", site!()));
+
+    let frame = error.first_frame_with("1_3").unwrap();
+    assert_eq!(
+        frame,
+        ParsedFrame {
+            file: file!().to_owned(),
+            line: 7,
+            msg: "1_3".to_owned(),
+        },
+    );
+    assert!(error.first_frame_with("1_4").is_none());
+}
+
+#[test]
+fn test_from_frames() {
+    let error = err4_4().unwrap_err();
+    let frames = anyhow::parse_frames(&error);
+    let rebuilt = Error::from_frames(frames.clone());
+    assert_eq!(anyhow::parse_frames(&rebuilt), frames);
+    #[cfg(feature = "serde")]
+    assert_eq!(rebuilt.to_json(), error.to_json());
+
+    // Without columns, which frames do not keep, the rendering is the same.
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(|error| Error::new(error).prepend_location("src/log.rs", 12, "writing log"))
+        .unwrap_err()
+        .prepend_location("src/main.rs", 3, "starting");
+    let rebuilt = Error::from_frames(anyhow::parse_frames(&error));
+    assert_eq!(format!("{:?}", rebuilt), format!("{:?}", error));
+}
+
+#[test]
+fn test_parse_escaped_separator() {
+    let error = Err::<(), _>(anyhow_located!("inner <<EOL>> message"))
+        .map_err(anyhow_error!("outer {} message", "<<EOL>>"))
+        .unwrap_err();
+    let message = error.to_string();
+    assert_eq!(message.matches("<<EOL>>").count(), 1, "{}", message);
+    assert!(message.contains("emsg(outer <<EOL>\\> message)]<<EOL>>"));
+
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!(frames[0].msg, "outer <<EOL>> message");
+    assert_eq!(frames[1].msg, "inner <<EOL>> message");
+    let messages: Vec<_> = anyhow::extract_all(&message)
+        .into_iter()
+        .map(|(_, _, message)| message)
+        .collect();
+    assert_eq!(messages, ["outer <<EOL>> message", "inner <<EOL>> message"]);
+
+    let rebuilt = Error::from_frames(frames.clone());
+    assert_eq!(anyhow::parse_frames(&rebuilt), frames);
+}
+
+#[test]
+fn test_strip_locations() {
+    let error = err4_4().unwrap_err();
+    let stripped = error.strip_locations();
+    let debug = format!("{:?}", stripped);
+    assert!(!debug.contains("src/"), "{}", debug);
+    assert!(!debug.contains(file!()), "{}", debug);
+    assert!(anyhow::parse_frames(&stripped)
+        .iter()
+        .all(|frame| frame.file.is_empty()));
+    assert!(
+        stripped.to_string().starts_with("4_3: 4_2:4_2: 4_1: 1_3"),
+        "{}",
+        stripped
+    );
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing"))
+        .unwrap_err();
+    assert_eq!(
+        error.strip_locations().to_string(),
+        r#"writing: Custom { kind: Other, error: "disk full" }"#,
+    );
+}
+
+#[test]
+fn test_frame_from_str() {
+    let error = err4_4().unwrap_err();
+    for frame in anyhow::parse_frames(&error).into_iter().take(6) {
+        let rendered = frame.to_string();
+        assert_eq!(rendered.parse::<ParsedFrame>(), Ok(frame), "{}", rendered);
+    }
+
+    let frame = ParsedFrame {
+        file: "src/db.rs".to_owned(),
+        line: 7,
+        msg: "no <<EOL>> here".to_owned(),
+    };
+    assert_eq!(frame.to_string(), "[src/db.rs:7 emsg(no <<EOL>\\> here)]");
+    assert_eq!(frame.to_string().parse::<ParsedFrame>(), Ok(frame));
+
+    for malformed in &[
+        "",
+        "timed out",
+        "[src/db.rs:7]",
+        "[src/db.rs:7 timed out]",
+        "[src/db.rs emsg(timed out)]",
+        "[src/db.rs:7 emsg(timed out)] and more",
+    ] {
+        assert!(malformed.parse::<ParsedFrame>().is_err(), "{}", malformed);
+    }
+}
+
+#[test]
+fn test_outermost_message() {
+    let error = err4_4().unwrap_err();
+    assert_eq!(error.outermost_message(), Some(""));
+    let error = err4_2().unwrap_err();
+    assert_eq!(error.outermost_message(), Some("4_2:4_2"));
+    assert_eq!(err1_3().unwrap_err().outermost_message(), Some(""));
+
+    let error = Error::msg("untagged");
+    assert_eq!(error.outermost_message(), Some("untagged"));
+    assert_eq!(error.emsg(), None);
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    assert_eq!(error.outermost_message(), None);
+}
+
+#[test]
+fn test_retag() {
+    let error =
+        Err::<(), _>(Error::msg("bad token").prepend_location("out/lexer.gen.rs", 90, "lexing"))
+            .map_err(anyhow_error!("loading grammar"))
+            .unwrap_err()
+            .prepend_location("out/parser.gen.rs", 812, "parsing");
+
+    let error = error.retag(|mut frame| {
+        if frame.file.ends_with(".gen.rs") {
+            frame.file = frame.file.replace(".gen.rs", ".template");
+        }
+        frame
+    });
+
+    let debug = format!("{:?}", error);
+    assert!(!debug.contains(".gen.rs"), "{}", debug);
+    assert!(
+        debug.contains("[out/parser.template:812, emsg(parsing)]"),
+        "{}",
+        debug
+    );
+    assert!(
+        debug.contains("[out/lexer.template:90, emsg(lexing)]"),
+        "{}",
+        debug
+    );
+    let files: Vec<_> = error
+        .parsed_frames()
+        .filter(|frame| !frame.file.is_empty())
+        .map(|frame| frame.file)
+        .collect();
+    assert_eq!(
+        files,
+        ["out/parser.template", file!(), "out/lexer.template"],
+    );
+}
+
+#[test]
+fn test_frames_eq() {
+    let build = |root: &str| {
+        Err::<(), _>(anyhow::tagged(
+            &format!("{}/src/db.rs", root),
+            42,
+            "timed out",
+        ))
+        .map_err(anyhow_error!("loading users"))
+        .unwrap_err()
+        .prepend_location(&format!("{}/src/main.rs", root), 7, "starting up")
+    };
+    let local = build("/home/dev/app");
+    let ci = build("C:\\build\\1234");
+    assert!(local.frames_eq(&ci));
+    assert!(
+        format!("{:?}", local) != format!("{:?}", ci),
+        "the paths differ",
+    );
+
+    let error = err4_4().unwrap_err();
+    assert!(error.frames_eq(&err4_4().unwrap_err()));
+    assert!(!error.frames_eq(&local));
+
+    let other_line = anyhow::tagged("/home/dev/app/src/db.rs", 43, "timed out");
+    let other_file = anyhow::tagged("/home/dev/app/src/dbs.rs", 42, "timed out");
+    let other_msg = anyhow::tagged("/home/dev/app/src/db.rs", 42, "timed  out");
+    let same = anyhow::tagged("db.rs", 42, "timed out");
+    let base = anyhow::tagged("/home/dev/app/src/db.rs", 42, "timed out");
+    assert!(base.frames_eq(&same));
+    assert!(!base.frames_eq(&other_line));
+    assert!(!base.frames_eq(&other_file));
+    assert!(!base.frames_eq(&other_msg));
+}
+
+#[test]
+fn test_frames_cached() {
+    use std::fmt::{self, Debug, Display};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counting(&'static str);
+
+    impl Display for Counting {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            FORMATTED.fetch_add(1, Ordering::SeqCst);
+            f.write_str(self.0)
+        }
+    }
+
+    impl Debug for Counting {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            Display::fmt(self, f)
+        }
+    }
+
+    impl std::error::Error for Counting {}
+
+    let mut error = Err::<(), _>(Error::new(Counting("disk full")))
+        .map_err(anyhow_context!("writing log"))
+        .unwrap_err();
+    let created = FORMATTED.load(Ordering::SeqCst);
+
+    let frames: Vec<ParsedFrame> = error.parsed_frames().collect();
+    let parsed = FORMATTED.load(Ordering::SeqCst);
+    assert!(parsed > created);
+    assert_eq!(error.root_frame().unwrap().msg, "writing log");
+    assert_eq!(anyhow::parse_frames(&error), frames);
+    let mut tree = String::new();
+    error.write_tree(&mut tree).unwrap();
+    assert!(error.first_frame_with("disk full").is_some());
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), parsed);
+
+    // So are the frames of the levels, and the location.
+    assert_eq!(error.depth(), 1);
+    let levels = FORMATTED.load(Ordering::SeqCst);
+    assert!(levels > parsed);
+    assert_eq!(error.all_frames().count(), 2);
+    assert_eq!(error.depth(), 1);
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), levels);
+
+    let tagged = Error::msg(Counting("[a.rs:3 emsg(x)]"));
+    assert_eq!(tagged.location().unwrap().line(), 3);
+    let located = FORMATTED.load(Ordering::SeqCst);
+    assert_eq!(tagged.location().unwrap().line(), 3);
+    assert!(tagged.downcast_ref::<anyhow::Location>().is_some());
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), located);
+
+    // Changing the error drops the cached frames.
+    error.downcast_mut::<Counting>().unwrap().0 = "disk on fire";
+    assert!(error.first_frame_with("disk on fire").is_some());
+    assert!(FORMATTED.load(Ordering::SeqCst) > parsed);
+}
+
+#[test]
+fn test_truncate_frames() {
+    let frames: Vec<ParsedFrame> = (1..=20)
+        .map(|line| ParsedFrame {
+            file: "src/retry.rs".to_owned(),
+            line,
+            msg: format!("attempt {}", line),
+        })
+        .collect();
+    let error = Error::from_frames(frames.clone());
+
+    let truncated = anyhow::parse_frames(&error.truncate_frames(6));
+    assert_eq!(truncated.len(), 7);
+    assert_eq!(truncated[..3], frames[..3]);
+    assert_eq!(truncated[4..], frames[17..]);
+    assert_eq!(
+        truncated[3],
+        ParsedFrame {
+            file: String::new(),
+            line: 0,
+            msg: "... 14 frames elided ...".to_owned(),
+        },
+    );
+
+    // An odd maximum keeps the extra frame on the outer side.
+    let truncated = anyhow::parse_frames(&error.truncate_frames(5));
+    assert_eq!(truncated.len(), 6);
+    assert_eq!(truncated[..3], frames[..3]);
+    assert_eq!(truncated[3].msg, "... 15 frames elided ...");
+    assert_eq!(truncated[4..], frames[18..]);
+
+    assert_eq!(anyhow::parse_frames(&error.truncate_frames(20)), frames);
+    let truncated = anyhow::parse_frames(&error.truncate_frames(0));
+    assert_eq!(truncated.len(), 1);
+    assert_eq!(truncated[0].msg, "... 20 frames elided ...");
+}
+
+#[test]
+fn test_matches_location() {
+    let error = err1_3().unwrap_err();
+    assert!(error.matches_location(file!(), 7));
+    assert!(error.matches_location(file!(), 8));
+    assert!(!error.matches_location(file!(), 9));
+
+    let absolute = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), file!());
+    assert!(error.matches_location(&absolute, 7));
+    assert!(error.matches_location("test_parse.rs", 7));
+    assert!(!error.matches_location("parse.rs", 7));
+    assert!(!error.matches_location("tests/other.rs", 7));
+
+    let error = anyhow::tagged("/home/dev/app/src/db.rs", 42, "timed out");
+    assert!(error.matches_location("src/db.rs", 42));
+    assert!(!error.matches_location("src/db.rs", 41));
+}
+
+#[test]
+fn test_parenthesized_messages() {
+    let messages = [
+        "No such file or directory (os error 2)",
+        "f(g(h(i(j(k))))) failed [see (x(1))] here",
+        "unclosed ( in (a(b)",
+        "stray ) and )] and \\) after (c)",
+        "backslashes \\\\( and \\\\\\) and a trailing \\",
+    ];
+    for message in &messages {
+        let error = Err::<(), _>(anyhow_located!("{}", message))
+            .map_err(anyhow_error!("parsing ({})", message))
+            .unwrap_err();
+        let frames = anyhow::parse_frames(&error);
+        assert_eq!(frames[0].msg, format!("parsing ({})", message));
+        assert_eq!(frames[1].msg, *message);
+        assert_eq!(error.root_frame().unwrap().msg, *message);
+
+        let rebuilt = Error::from_frames(frames.clone());
+        assert_eq!(anyhow::parse_frames(&rebuilt), frames);
+        let frame: ParsedFrame = frames[1].to_string().parse().unwrap();
+        assert_eq!(frame, frames[1]);
+
+        let found = anyhow::extract_all(&format!("log: {} end", frames[1]));
+        assert_eq!(found[0].2, *message);
+        assert!(error.strip_locations().to_string().ends_with(*message));
+    }
+}
+
+#[test]
+fn test_file_histogram() {
+    let errors = vec![
+        err4_4().unwrap_err(),
+        err1_3().unwrap_err(),
+        anyhow::tagged("src/db.rs", 42, "timed out"),
+        Err::<(), _>(anyhow::tagged("src/db.rs", 7, "connection reset"))
+            .map_err(anyhow_error!("loading users"))
+            .unwrap_err(),
+        anyhow::tagged("src/api.rs", 3, "bad request"),
+        Error::msg("no location"),
+    ];
+
+    let histogram = anyhow::file_histogram(&errors);
+    assert_eq!(histogram.len(), 3);
+    assert_eq!(histogram[file!()], 2);
+    assert_eq!(histogram["src/db.rs"], 2);
+    assert_eq!(histogram["src/api.rs"], 1);
+
+    assert!(anyhow::file_histogram(&errors[5..]).is_empty());
+}
+
+#[test]
+fn test_try_clone() {
+    use anyhow::Context;
+
+    // The copy has no backtrace of its own.
+    fn without_backtrace(error: &Error) -> String {
+        let debug = format!("{:?}", error);
+        match debug.find("\n\nStack backtrace:") {
+            Some(end) => debug[..end].to_owned(),
+            None => debug,
+        }
+    }
+
+    let error = err4_4()
+        .map_err(anyhow_context!("serving request"))
+        .context("handler failed")
+        .unwrap_err();
+    let copy = error.try_clone().unwrap();
+    assert_eq!(without_backtrace(&copy), without_backtrace(&error));
+    assert_eq!(format!("{:#}", copy), format!("{:#}", error));
+    assert_eq!(copy.chain().count(), error.chain().count());
+    assert_eq!(copy.location(), error.location());
+
+    // A foreign error rendered into the message by anyhow_error! is text too.
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing log"))
+        .unwrap_err();
+    let copy = error.try_clone().unwrap();
+    assert_eq!(without_backtrace(&copy), without_backtrace(&error));
+
+    #[derive(Debug)]
+    struct Custom;
+
+    impl std::fmt::Display for Custom {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("custom failure")
+        }
+    }
+
+    impl std::error::Error for Custom {}
+
+    let error = Error::new(Custom).context("wrapping");
+    assert!(error.try_clone().is_none());
+    assert!(Error::new(Custom).try_clone().is_none());
+}
+
+#[test]
+fn test_with_frame_filter() {
+    let error = err4_4().unwrap_err();
+    let frames = anyhow::parse_frames(&error);
+
+    // The tagged frames are all in this file; anything after them is the
+    // untagged backtrace.
+    let filtered = anyhow::parse_frames(&error.with_frame_filter(|frame| frame.file == file!()));
+    assert_eq!(filtered[..], frames[..6]);
+
+    let filtered = anyhow::parse_frames(&error.with_frame_filter(|frame| frame.line != 17));
+    let lines: Vec<_> = filtered[..5].iter().map(|frame| frame.line).collect();
+    assert_eq!(lines, [28, 23, 12, 8, 7]);
+    assert_eq!(filtered[5..], frames[6..]);
+
+    // Frames moved to vendored files by retag are dropped by file.
+    let retagged = err4_4().unwrap_err().retag(|mut frame| {
+        if frame.line == 12 || frame.line == 8 {
+            frame.file = format!("vendor/glue/{}", frame.file);
+        }
+        frame
+    });
+    let filtered = retagged.with_frame_filter(|frame| !frame.file.contains("vendor/"));
+    let messages: Vec<_> = anyhow::parse_frames(&filtered)
+        .into_iter()
+        .take(4)
+        .map(|frame| (frame.line, frame.msg))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (28, String::new()),
+            (23, format!("[{}:22:15 emsg(4_3)]", site!())),
+            (17, "4_2:4_2".to_owned()),
+            (7, "1_3".to_owned()),
+        ],
+    );
+    assert!(anyhow::parse_frames(&filtered)
+        .iter()
+        .all(|frame| !frame.file.contains("vendor/")));
+
+    assert_eq!(error.with_frame_filter(|_| false).to_string(), "");
+}
+
+// Declared last so as not to move the lines that the tests above refer to.
+mod common;
@@ -0,0 +1,85 @@
+use anyhow::{anyhow_context, anyhow_located, Context, FromError, Location, ReportParts, ToReport};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+struct Report {
+    summary: String,
+    causes: Vec<String>,
+    site: Option<(String, u32)>,
+}
+
+impl FromError for Report {
+    fn from_parts(parts: ReportParts) -> Self {
+        Report {
+            summary: parts.message,
+            causes: parts.causes,
+            site: parts
+                .location
+                .map(|location| (location.file().to_owned(), location.line())),
+        }
+    }
+
+    fn into_parts(self) -> ReportParts {
+        ReportParts {
+            message: self.summary,
+            causes: self.causes,
+            location: self.site.map(|(file, line)| Location::new(file, line)),
+        }
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let line = line!() + 2;
+    let error = Err::<(), _>(anyhow_located!("no such user"))
+        .map_err(anyhow_context!("loading profile"))
+        .unwrap_err();
+    let report: Report = error.to_report();
+    assert_eq!(
+        report,
+        Report {
+            summary: "loading profile".to_owned(),
+            causes: vec!["no such user".to_owned()],
+            site: Some((file!().to_owned(), line)),
+        },
+    );
+
+    let error = report.into_error();
+    assert_eq!(error.chain().count(), 2);
+    assert_eq!(error.root_cause().to_string(), "no such user");
+    let location = error.locations().next().unwrap().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+
+    let again: Report = error.to_report();
+    assert_eq!(again.summary, "loading profile");
+    assert_eq!(again.causes, ["no such user"]);
+    assert_eq!(again.site, Some((file!().to_owned(), line)));
+}
+
+#[test]
+fn test_foreign_and_single() {
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .context("write failed")
+        .unwrap_err();
+    let report = Report::from_error(&error);
+    assert_eq!(report.summary, "write failed");
+    // With "with-location" the context is located, and kept typed under it.
+    #[cfg(not(feature = "with-location"))]
+    {
+        assert_eq!(report.causes, ["disk full"]);
+        assert_eq!(report.site, None);
+        assert_eq!(
+            format!("{:#}", report.into_error()),
+            "write failed: disk full"
+        );
+    }
+
+    let report = Report {
+        summary: "oh no!".to_owned(),
+        causes: Vec::new(),
+        site: None,
+    };
+    let error = report.into_error();
+    assert_eq!(format!("{:#}", error), "oh no!");
+    assert_eq!(error.chain().count(), 1);
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "prost")]
+
+use anyhow::proto::{ErrorRecord, Location};
+use anyhow::{anyhow_context, anyhow_located, Context, Error};
+use prost::Message;
+use std::io;
+
+#[test]
+fn test_round_trip() {
+    let line = line!() + 2;
+    let error = Err::<(), _>(anyhow_located!("no such user"))
+        .map_err(anyhow_context!("loading profile"))
+        .context_coded("user_not_found", "handling request")
+        .unwrap_err();
+
+    let record = error.to_proto();
+    let bytes = record.encode_to_vec();
+    let decoded = ErrorRecord::decode(&bytes[..]).unwrap();
+    assert_eq!(decoded, record);
+    assert_eq!(
+        decoded,
+        ErrorRecord {
+            message: "handling request".to_owned(),
+            location: None,
+            chain: vec![
+                "handling request".to_owned(),
+                "loading profile".to_owned(),
+                "no such user".to_owned(),
+            ],
+            code: "user_not_found".to_owned(),
+        },
+    );
+
+    let inner = anyhow_located!("no such user");
+    let line_inner = line!() - 1;
+    let decoded = ErrorRecord::decode(&inner.to_proto().encode_to_vec()[..]).unwrap();
+    assert_eq!(
+        decoded.location,
+        Some(Location {
+            file: file!().to_owned(),
+            line: line_inner,
+        }),
+    );
+    assert_ne!(line, line_inner);
+}
+
+#[test]
+fn test_foreign() {
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    let record = error.to_proto();
+    assert_eq!(record.message, "disk full");
+    assert_eq!(record.chain, ["disk full"]);
+    assert_eq!(record.location, None);
+    assert_eq!(record.code, "");
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use anyhow::{anyhow_context, anyhow_located, Context, Error, Result};
+use std::env;
+use std::io;
+
+fn lookup(id: u32) -> Result<()> {
+    Err(anyhow_located!("no session {}", id))
+}
+
+fn first_run() -> Error {
+    lookup(17)
+        .map_err(anyhow_context!(
+            "resuming request {}",
+            "550e8400-e29b-41d4-a716-446655440000"
+        ))
+        .unwrap_err()
+}
+
+fn second_run() -> Error {
+    let _shifted = ();
+    lookup(90210)
+        .map_err(anyhow_context!(
+            "resuming request {}",
+            "6ba7b810-9dad-11d1-80b4-00c04fd430c8"
+        ))
+        .unwrap_err()
+}
+
+#[test]
+fn test_redacted_is_stable() {
+    let first = first_run().context("handling");
+    let second = second_run().context("handling");
+    assert_ne!(format!("{:#}", first), format!("{:#}", second));
+    assert_eq!(first.redacted(), second.redacted());
+
+    let expected = format!(
+        "handling\n\
+         \n\
+         Caused by:\n    \
+         0: [{0}:LINE, emsg(resuming request #)]\n    \
+         1: [{0}:LINE emsg(no session #)]",
+        site!(),
+    );
+    assert_eq!(first.redacted(), expected);
+}
+
+#[test]
+fn test_redacted_paths() {
+    let cwd = env::current_dir().unwrap();
+    let path = cwd.join("data").join("9.json");
+    let io = io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} missing", path.display()),
+    );
+    let error = Err::<(), _>(io)
+        .context(format!("reading {}", path.display()))
+        .unwrap_err();
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(
+        error.redacted(),
+        "reading data/#.json\n\nCaused by:\n    data/#.json missing",
+    );
+    #[cfg(feature = "with-location")]
+    assert_eq!(
+        error.redacted(),
+        format!(
+            "[{}:LINE, emsg(reading data/#.json)]\n\nCaused by:\n    0: reading data/#.json\n    1: data/#.json missing",
+            file!(),
+        ),
+    );
+
+    assert_eq!(Error::msg("port 8080 busy").redacted(), "port # busy");
+}
+
+#[test]
+fn test_redact() {
+    let secret = "postgres://admin:s3cr3t@db";
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, secret))
+        .map_err(anyhow::anyhow_error!("connecting to {}", secret))
+        .map_err(anyhow_context!("starting up"))
+        .unwrap_err();
+    assert!(format!("{:?}", error).contains("s3cr3t"));
+
+    let redacted = error.redact(&["s3cr3t", ""]);
+    let debug = format!("{:?}", redacted);
+    assert!(!debug.contains("s3cr3t"), "{}", debug);
+    assert!(
+        debug.contains("connecting to postgres://admin:***@db"),
+        "{}",
+        debug
+    );
+    assert!(
+        debug.contains("error: \"postgres://admin:***@db\""),
+        "{}",
+        debug
+    );
+
+    let lines =
+        |error: &Error| -> Vec<u32> { error.parsed_frames().map(|frame| frame.line).collect() };
+    let files =
+        |error: &Error| -> Vec<String> { error.parsed_frames().map(|frame| frame.file).collect() };
+    assert_eq!(lines(&redacted)[..2], lines(&error)[..2]);
+    assert_eq!(files(&redacted)[..2], files(&error)[..2]);
+    let location = redacted.location().unwrap();
+    assert_eq!(location.line(), error.location().unwrap().line());
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "relative-paths")]
+
+include!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/relative/tagged.rs"
+));
+
+#[test]
+fn test_relative_paths() {
+    let error = tagged();
+    let message = error.to_string();
+    assert!(message.ends_with("tests/relative/tagged.rs:4:5 emsg(oh no!)]"));
+    assert!(!message.contains(env!("CARGO_MANIFEST_DIR")), "{}", message);
+    assert_eq!(error.location().unwrap().file(), "tests/relative/tagged.rs");
+
+    // A file that is already relative is kept as it is.
+    let error = anyhow::anyhow_located!("oh no!");
+    assert_eq!(error.location().unwrap().file(), file!());
+}
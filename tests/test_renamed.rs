@@ -0,0 +1,22 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+// Builds tests/renamed, which depends on this crate under another name, so
+// that a macro expanding to `::anyhow::...` instead of `$crate::...` fails to
+// compile.
+#[cfg_attr(miri, ignore)]
+#[test]
+fn test_renamed_dependency() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .arg("build")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(manifest_dir.join("tests/renamed/Cargo.toml"))
+        .env("CARGO_TARGET_DIR", manifest_dir.join("target/renamed"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
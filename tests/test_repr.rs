@@ -5,6 +5,12 @@ use anyhow::Error;
 use std::marker::Unpin;
 use std::mem;
 
+// Compile-time versions of the two tests below, so that any growth of Error
+// fails the build instead of only a test run.
+const _: [(); mem::size_of::<usize>()] = [(); mem::size_of::<Error>()];
+const _: [(); mem::size_of::<usize>()] = [(); mem::size_of::<Result<(), Error>>()];
+const _: [(); mem::size_of::<usize>()] = [(); mem::size_of::<Option<Error>>()];
+
 #[test]
 fn test_error_size() {
     assert_eq!(mem::size_of::<Error>(), mem::size_of::<usize>());
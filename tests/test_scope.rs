@@ -0,0 +1,95 @@
+mod common;
+
+use anyhow::{anyhow, Result};
+use futures::FutureExt;
+
+async fn fetch(ok: bool) -> Result<u32> {
+    if ok {
+        Ok(7)
+    } else {
+        Err(anyhow!("connection reset"))
+    }
+}
+
+#[test]
+fn test_scoped_ok() {
+    let output = anyhow::scoped("fetching", fetch(true)).now_or_never();
+    assert_eq!(output.unwrap().unwrap(), 7);
+}
+
+#[test]
+fn test_scoped_err() {
+    let line = line!() + 1;
+    let scoped = anyhow::scoped(format!("fetching {}", "manifest"), fetch(false));
+    let error = scoped.now_or_never().unwrap().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:18, emsg(fetching manifest)]", file!(), line),
+    );
+    assert_eq!(error.chain().count(), 2);
+
+    let location = error.locations().next().unwrap().unwrap();
+    assert_eq!(location.line(), line);
+}
+
+#[test]
+fn test_scoped_nested() {
+    let error = anyhow::scoped("deploying", async {
+        let n = anyhow::scoped("fetching", fetch(false)).await?;
+        Ok(n + 1)
+    })
+    .now_or_never()
+    .unwrap()
+    .unwrap_err();
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    assert!(chain[0].ends_with(" emsg(deploying)]"));
+    assert!(chain[1].ends_with(" emsg(fetching)]"));
+    assert_eq!(chain.len(), 3);
+}
+
+async fn fetch_io(ok: bool) -> std::io::Result<u32> {
+    if ok {
+        Ok(7)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "connection reset",
+        ))
+    }
+}
+
+#[test]
+fn test_try_async() {
+    let deploy = |ok| async move {
+        let n = anyhow::try_async!(fetch_io(ok), "fetching {}", "manifest");
+        Ok::<_, anyhow::Error>(n + 1)
+    };
+    let line = line!() - 3;
+    assert_eq!(deploy(true).now_or_never().unwrap().unwrap(), 8);
+
+    let error = deploy(false).now_or_never().unwrap().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:17, emsg(fetching manifest)]", site!(), line),
+    );
+    assert_eq!(error.root_cause().to_string(), "connection reset");
+    assert!(error.downcast_ref::<std::io::Error>().is_some());
+}
+
+#[cfg(not(feature = "deny-empty-messages"))]
+#[test]
+fn test_try_async_no_message() {
+    let error = async {
+        anyhow::try_async!(fetch(false));
+        Ok(())
+    }
+    .now_or_never()
+    .unwrap()
+    .unwrap_err();
+    let line = line!() - 6;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:9, emsg()]", site!(), line),
+    );
+    assert_eq!(error.chain().count(), 2);
+}
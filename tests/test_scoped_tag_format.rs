@@ -0,0 +1,44 @@
+mod common;
+
+use anyhow::{anyhow_located, Template};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+fn builtin(error: anyhow::Error) -> bool {
+    error.to_string().starts_with(&format!("[{}:", site!()))
+}
+
+#[test]
+fn test_nested_scopes() {
+    let outer = Template::new("outer: {msg}");
+    let inner = Template::new("inner: {msg}");
+    let message = anyhow::with_tag_format(outer, || {
+        let before = anyhow_located!("a").to_string();
+        let nested = anyhow::with_tag_format(inner, || anyhow_located!("b").to_string());
+        let after = anyhow_located!("c").to_string();
+        [before, nested, after]
+    });
+    assert_eq!(message, ["outer: a", "inner: b", "outer: c"]);
+    assert!(builtin(anyhow_located!("d")));
+}
+
+#[test]
+fn test_panic_restores() {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        anyhow::with_tag_format(Template::new("{msg}"), || -> () {
+            assert_eq!(anyhow_located!("a").to_string(), "a");
+            panic!("fails inside the scope");
+        })
+    }));
+    assert!(result.is_err());
+    assert!(builtin(anyhow_located!("b")));
+}
+
+#[test]
+fn test_other_threads() {
+    anyhow::with_tag_format(Template::new("{msg}"), || {
+        let other = thread::spawn(|| builtin(anyhow_located!("a"))).join();
+        assert!(other.unwrap());
+        assert_eq!(anyhow_located!("b").to_string(), "b");
+    });
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use anyhow::{anyhow_error, anyhow_located, Error, END_OF_LINE};
+use std::fmt::{self, Display};
+
+// The separator is process-wide, so everything that sets it lives in this
+// single test.
+#[test]
+fn test_line_separator() {
+    assert_eq!(anyhow::line_separator(), END_OF_LINE);
+    let joined = Error::msg("[a.rs:1 emsg(x)] ~~ [b.rs:2 emsg(y)]");
+    assert_eq!(lines(&joined), [1, 0]);
+    assert_eq!(joined.depth(), 1);
+
+    anyhow::set_line_separator(" ~~ ");
+    assert_eq!(anyhow::line_separator(), " ~~ ");
+
+    // What was parsed under the old separator is parsed again.
+    assert_eq!(lines(&joined), [1, 2]);
+    assert_eq!(joined.depth(), 2);
+
+    let line = line!() + 2;
+    let error = Err::<(), Error>(anyhow_located!("no <<EOL>> here"))
+        .map_err(anyhow_error!("loading"))
+        .unwrap_err();
+    let message = error.to_string();
+    let head = format!("[{}:{}:18, emsg(loading)] ~~ ", site!(), line);
+    assert!(message.starts_with(&head), "{}", message);
+    assert!(!message[head.len()..].contains(" ~~ "));
+
+    let location = error.location().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+    let messages: Vec<_> = anyhow::extract_all(&message)
+        .into_iter()
+        .map(|(_, _, message)| message)
+        .collect();
+    assert_eq!(messages, ["loading", "no <<EOL>> here"]);
+
+    // `{:?}` shows each separator as a line break, even one split across
+    // writes.
+    let debug = format!("{:?}", error);
+    assert_eq!(debug, format!("{:-?}", error).replace(" ~~ ", "\n"));
+    assert_eq!(format!("{:?}", Error::msg(Split)), "a\nb ~ c ~");
+
+    // An empty separator is ignored.
+    anyhow::set_line_separator("");
+    assert_eq!(anyhow::line_separator(), " ~~ ");
+    assert_eq!(error.parsed_frames().count(), 2);
+}
+
+fn lines(error: &Error) -> Vec<u32> {
+    error.parsed_frames().map(|frame| frame.line).collect()
+}
+
+#[derive(Debug)]
+struct Split;
+
+impl Display for Split {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a ~")?;
+        f.write_str("~ b ~")?;
+        f.write_str(" c ~")
+    }
+}
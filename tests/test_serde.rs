@@ -0,0 +1,120 @@
+#![cfg(all(feature = "serde", not(feature = "deny-empty-messages")))]
+
+use anyhow::{
+    anyhow_context, anyhow_error, anyhow_located, DeserializedError, Error, Location, Result,
+};
+use serde_json::json;
+use std::io;
+
+#[test]
+fn test_round_trip() {
+    let line = line!() + 1;
+    let error = Err::<(), _>(anyhow_located!("no such user"))
+        .map_err(anyhow_context!("loading profile"))
+        .unwrap_err();
+
+    let json = serde_json::to_value(&error).unwrap();
+    let outer = format!("[{}:{}:18, emsg(loading profile)]", file!(), line + 1);
+    let inner = format!("[{}:{}:30 emsg(no such user)]", file!(), line);
+    assert_eq!(
+        json,
+        json!({
+            "message": "loading profile",
+            "location": { "file": file!(), "line": line + 1, "column": 18 },
+            "chain": [outer, inner],
+        }),
+    );
+
+    let received: DeserializedError = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(received.message(), "loading profile");
+    assert_eq!(
+        received.location(),
+        Some(&Location::new(file!(), line + 1).with_column(18))
+    );
+    assert_eq!(serde_json::to_value(&received).unwrap(), json);
+
+    let rebuilt = Error::from(received);
+    assert_eq!(rebuilt.to_string(), error.to_string());
+    assert_eq!(format!("{:#}", rebuilt), format!("{:#}", error));
+    let chain: Vec<String> = rebuilt.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, [outer, inner]);
+}
+
+#[test]
+fn test_foreign() {
+    let error = Error::new(io::Error::new(io::ErrorKind::Other, "disk full"));
+    let json = serde_json::to_string(&error).unwrap();
+    assert_eq!(
+        json,
+        r#"{"message":"disk full","location":null,"chain":["disk full"]}"#,
+    );
+
+    let received: DeserializedError = serde_json::from_str(&json).unwrap();
+    assert_eq!(received.location(), None);
+    assert_eq!(Error::from(received).to_string(), "disk full");
+}
+
+// The chain of examples/result_error.rs.
+fn err1_3() -> Result<()> {
+    let err = anyhow_located!("1_3");
+    Err(anyhow_located!(err))
+}
+
+fn err4() -> Result<()> {
+    err1_3().map_err(anyhow_error!("4_1"))?;
+    Ok(())
+}
+
+fn err4_2() -> Result<()> {
+    err4().map_err(anyhow_error!("4_2:{}", "4_2"))?;
+    Ok(())
+}
+
+fn err4_3() -> Result<()> {
+    let err = anyhow_located!("4_3");
+    err4_2().map_err(anyhow_error!(err))?;
+    Ok(())
+}
+
+fn err4_4() -> Result<()> {
+    err4_3().map_err(anyhow_error!())?;
+    Ok(())
+}
+
+#[test]
+fn test_to_json() {
+    let error = err4_4().unwrap_err();
+    let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+    let frames = json.as_array().unwrap();
+    let frame = |line, msg: &str| json!({ "file": file!(), "line": line, "msg": msg });
+    let nested = format!("[{}:74:15 emsg(4_3)]", file!());
+    assert_eq!(
+        frames[..6],
+        [
+            frame(80, ""),
+            frame(75, &nested),
+            frame(69, "4_2:4_2"),
+            frame(64, "4_1"),
+            frame(60, ""),
+            frame(59, "1_3"),
+        ],
+    );
+
+    // With RUST_BACKTRACE set, the backtrace follows as an untagged frame.
+    for frame in &frames[6..] {
+        assert_eq!(
+            (&frame["file"], &frame["line"]),
+            (&json!(null), &json!(null))
+        );
+    }
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .map_err(anyhow_error!("writing"))
+        .unwrap_err();
+    let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+    assert_eq!(
+        json[1]["msg"],
+        "Custom { kind: Other, error: \"disk full\" }"
+    );
+    assert_eq!(json[1]["file"], json!(null));
+}
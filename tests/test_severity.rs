@@ -0,0 +1,44 @@
+use anyhow::{Context, Error, Severity};
+use std::io;
+
+#[test]
+fn test_severity() {
+    let error = Error::msg("oh no!");
+    assert_eq!(error.severity(), Severity::Error);
+
+    let error = error.with_severity(Severity::Fatal).context("outer");
+    assert_eq!(error.severity(), Severity::Fatal);
+    let error = error.with_severity(Severity::Info);
+    assert_eq!(error.severity(), Severity::Info);
+    assert!(error.attachment::<Severity>().is_none());
+}
+
+#[test]
+fn test_syslog() {
+    let cases = [
+        (Severity::Fatal, 2),
+        (Severity::Error, 3),
+        (Severity::Warning, 4),
+        (Severity::Info, 6),
+        (Severity::Debug, 7),
+    ];
+    for &(severity, priority) in &cases {
+        assert_eq!(severity.syslog_priority(), priority);
+
+        let io = io::Error::new(io::ErrorKind::Other, "disk\nfull");
+        let error = Err::<(), _>(io)
+            .context("write failed")
+            .context("saving report")
+            .unwrap_err()
+            .with_severity(severity);
+        #[cfg(not(feature = "with-location"))]
+        {
+            let expected = format!("<{}>saving report: write failed: disk full", priority);
+            assert_eq!(error.to_syslog(), expected);
+        }
+        assert!(error.to_syslog().starts_with(&format!("<{}>", priority)));
+        assert!(!error.to_syslog().contains('\n'));
+    }
+
+    assert_eq!(Error::msg("oh no!").to_syslog(), "<3>oh no!");
+}
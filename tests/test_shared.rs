@@ -0,0 +1,62 @@
+use anyhow::{Context, Error, SharedError};
+use std::io;
+use std::thread;
+
+fn shared() -> SharedError {
+    let io = io::Error::new(io::ErrorKind::TimedOut, "upstream timed out");
+    Err::<(), _>(io)
+        .context("fetching profile")
+        .unwrap_err()
+        .into()
+}
+
+#[test]
+fn test_shared_across_threads() {
+    let error = shared();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let error = error.clone();
+            thread::spawn(move || {
+                let io = error.downcast_ref::<io::Error>().unwrap();
+                assert_eq!(io.kind(), io::ErrorKind::TimedOut);
+                format!("{:#}", error)
+            })
+        })
+        .collect();
+    for handle in handles {
+        let rendered = handle.join().unwrap();
+        assert!(rendered.ends_with("fetching profile: upstream timed out"));
+        #[cfg(not(feature = "with-location"))]
+        assert_eq!(rendered, "fetching profile: upstream timed out");
+    }
+
+    let levels = if cfg!(feature = "with-location") {
+        3
+    } else {
+        2
+    };
+    assert_eq!(error.chain().count(), levels);
+    assert!(error.root_cause().is::<io::Error>());
+    assert_eq!(format!("{:?}", error), format!("{:?}", *error));
+
+    // Once the other handles are gone the error can be taken back.
+    let error = error.try_unwrap().unwrap();
+    assert!(error.downcast_ref::<io::Error>().is_some());
+}
+
+#[test]
+fn test_shared_as_source() {
+    let error = shared();
+    let wrapped = Error::new(error.clone()).context("rendering page");
+    let mut chain: Vec<_> = wrapped.chain().map(ToString::to_string).collect();
+    // With "with-location" the context has a tagged level of its own.
+    if cfg!(feature = "with-location") {
+        assert!(chain.remove(1).ends_with(", emsg(fetching profile)]"));
+    }
+    assert_eq!(
+        chain,
+        ["rendering page", "fetching profile", "upstream timed out"],
+    );
+    assert!(wrapped.downcast_ref::<SharedError>().is_some());
+    assert!(error.try_unwrap().is_err());
+}
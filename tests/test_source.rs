@@ -1,3 +1,5 @@
+mod common;
+
 use anyhow::anyhow;
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
@@ -60,3 +62,116 @@ fn test_anyhow_from_anyhow() {
     let error = anyhow!(error);
     assert_eq!("oh no!", error.source().unwrap().to_string());
 }
+
+#[test]
+fn test_count_of() {
+    let inner = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let outer = io::Error::new(io::ErrorKind::Other, TestError::Io(inner));
+    let error = anyhow::Error::new(outer).context("loading config");
+    assert_eq!(error.chain().count(), 3);
+    assert_eq!(error.count_of::<io::Error>(), 2);
+    assert_eq!(error.count_of::<fmt::Error>(), 0);
+
+    let inner = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let error = anyhow::Error::new(TestError::Io(inner));
+    assert_eq!(error.count_of::<TestError>(), 1);
+    assert_eq!(error.count_of::<io::Error>(), 1);
+}
+
+#[test]
+fn test_located_keeps_source() {
+    #[derive(thiserror::Error, Debug)]
+    #[error("reading config")]
+    struct ConfigError {
+        #[source]
+        cause: io::Error,
+    }
+
+    let cause = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let error = anyhow::anyhow_located!(ConfigError { cause });
+    let line = line!() - 1;
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    let tag = format!("[{}:{}:17 emsg(reading config)]", site!(), line);
+    assert_eq!(chain, [tag.as_str(), "reading config", "oh no!"]);
+    let cause = error.chain().nth(2).unwrap();
+    assert_eq!(
+        cause.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::NotFound,
+    );
+    assert!(error.downcast_ref::<ConfigError>().is_some());
+}
+
+#[test]
+fn test_all_frames() {
+    #[derive(thiserror::Error, Debug)]
+    #[error("reading config")]
+    struct ConfigError {
+        #[source]
+        cause: io::Error,
+    }
+
+    let cause = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let error = Err::<(), _>(anyhow::anyhow_located!(ConfigError { cause }))
+        .map_err(anyhow::anyhow_context!("starting up"))
+        .unwrap_err();
+    let line = line!() - 3;
+    let frames: Vec<_> = error
+        .all_frames()
+        .map(|frame| (frame.file, frame.line, frame.msg))
+        .collect();
+    let file = file!().to_owned();
+    let untagged = |msg: &str| (String::new(), 0, msg.to_owned());
+    assert_eq!(
+        frames,
+        [
+            (file.clone(), line + 1, "starting up".to_owned()),
+            (file, line, "reading config".to_owned()),
+            untagged("reading config"),
+            untagged("oh no!"),
+        ],
+    );
+}
+
+#[test]
+fn test_chain_rev() {
+    let error = anyhow::Error::new(io::Error::new(io::ErrorKind::NotFound, "oh no!"))
+        .context("reading config")
+        .context("loading config")
+        .context("starting up");
+    let reversed: Vec<_> = error.chain().rev().map(ToString::to_string).collect();
+    assert_eq!(
+        reversed,
+        ["oh no!", "reading config", "loading config", "starting up"],
+    );
+
+    let mut chain = error.chain();
+    assert_eq!(chain.next().unwrap().to_string(), "starting up");
+    assert_eq!(chain.next_back().unwrap().to_string(), "oh no!");
+    assert_eq!(chain.len(), 2);
+    assert_eq!(chain.next_back().unwrap().to_string(), "reading config");
+    assert_eq!(chain.next().unwrap().to_string(), "loading config");
+    assert!(chain.next().is_none());
+}
+
+#[test]
+fn test_chain_len() {
+    let error = anyhow!("oh no!");
+    assert_eq!(error.chain_len(), 1);
+    assert_eq!(error.chain_len(), error.chain().count());
+
+    let error = anyhow::Error::new(io::Error::new(io::ErrorKind::NotFound, "oh no!"));
+    assert_eq!(error.chain_len(), 1);
+
+    let error = error.context("reading config");
+    assert_eq!(error.chain_len(), 2);
+    assert_eq!(error.chain_len(), error.chain().count());
+
+    let inner = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let outer = io::Error::new(io::ErrorKind::Other, TestError::Io(inner));
+    let error = anyhow::Error::new(outer)
+        .context("reading config")
+        .context("starting up");
+    assert_eq!(error.chain_len(), error.chain().count());
+    assert_eq!(error.chain_len(), 4);
+    assert_eq!(error.chain().len(), 4);
+}
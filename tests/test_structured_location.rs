@@ -0,0 +1,69 @@
+#![cfg(all(feature = "structured-location", not(feature = "compat")))]
+
+use anyhow::{anyhow, bail, ensure, Error, Frame, Result};
+use std::panic::Location;
+
+#[test]
+fn test_message_kept_clean() {
+    let error = anyhow!("disk full");
+    let line = line!() - 1;
+    assert_eq!(error.to_string(), "disk full");
+    let location = error.location().unwrap();
+    assert_eq!(location.file(), file!());
+    assert_eq!((location.line(), location.column()), (line, Some(17)));
+    let tagged = format!("[{}:{}:17 emsg(disk full)]", file!(), line);
+    assert!(format!("{:?}", error).starts_with(&tagged));
+
+    let frames = anyhow::parse_frames(&error);
+    assert_eq!((frames[0].line, &*frames[0].msg), (line, "disk full"));
+    match error.frames().next().unwrap() {
+        Frame::Adhoc { message, location } => {
+            assert_eq!(message, "disk full");
+            assert_eq!(location.unwrap().line(), line);
+        }
+        frame => panic!("expected ad-hoc, got {:?}", frame),
+    }
+    let json = format!(r#""loc":"{}:{}""#, file!(), line);
+    assert!(error.to_compact_json().contains(&json));
+
+    let error = error.context("saving report");
+    assert_eq!(format!("{:#}", error), "saving report: disk full");
+    let locations: Vec<_> = error.locations().collect();
+    assert_eq!(locations[0], None);
+    assert_eq!(locations[1].as_ref().unwrap().line(), line);
+
+    // The location still tells errors apart, as a tag would.
+    let a = anyhow!("oh no!");
+    let b = anyhow!("oh no!");
+    assert_ne!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint_without_lines(), b.fingerprint_without_lines());
+}
+
+#[track_caller]
+#[allow(clippy::incompatible_msrv)]
+fn missing(key: &str) -> Error {
+    anyhow!("missing key {}", key @ Location::caller())
+}
+
+#[test]
+fn test_macros() {
+    let error = anyhow!("port {}", 80);
+    assert_eq!(error.to_string(), "port 80");
+    assert_eq!(error.location().unwrap().line(), line!() - 2);
+
+    let error = missing("port");
+    assert_eq!(error.to_string(), "missing key port");
+    assert_eq!(error.location().unwrap().line(), line!() - 2);
+
+    let error = (|| -> Result<()> { bail!("oh {}", "no") })().unwrap_err();
+    assert_eq!(error.to_string(), "oh no");
+    assert_eq!(error.location().unwrap().line(), line!() - 2);
+
+    let error = (|| -> Result<()> {
+        ensure!(1 + 1 == 3, "bad math");
+        Ok(())
+    })()
+    .unwrap_err();
+    assert_eq!(error.to_string(), "bad math");
+    assert_eq!(error.location().unwrap().line(), line!() - 5);
+}
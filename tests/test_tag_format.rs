@@ -0,0 +1,29 @@
+mod common;
+
+use anyhow::{anyhow_context, anyhow_located, Template};
+
+// The tag format is process-wide, so everything that sets it lives in this
+// single test.
+#[test]
+fn test_tag_format() {
+    let template = Template::new("{msg} ({file} line {line}, column {column}) {other}");
+    assert_eq!(anyhow::set_tag_format(template), Ok(()));
+
+    let error = anyhow_located!("oh no!");
+    let line = line!() - 1;
+    let expected = format!("oh no! ({} line {}, column 17) {{other}}", site!(), line);
+    assert!(error.to_string().ends_with(&expected), "{}", error);
+    assert!(anyhow_located!("{} failed", "write")
+        .to_string()
+        .starts_with("write failed ("));
+
+    let error = Err::<(), _>(error)
+        .map_err(anyhow_context!("loading"))
+        .unwrap_err();
+    assert!(error.to_string().starts_with("loading ("), "{}", error);
+
+    // A second template is handed back and the first stays in place.
+    let other = Template::new("[{msg}]");
+    assert_eq!(anyhow::set_tag_format(other), Err(other));
+    assert!(anyhow_located!("later").to_string().starts_with("later ("));
+}
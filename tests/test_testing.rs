@@ -0,0 +1,85 @@
+#![cfg(all(feature = "testing", not(feature = "deny-empty-messages")))]
+
+use anyhow::{anyhow_error, anyhow_located, assert_error_at, Result};
+use std::panic;
+
+// The chain of examples/result_error.rs.
+fn err1_3() -> Result<()> {
+    let err = anyhow_located!("1_3");
+    Err(anyhow_located!(err))
+}
+
+fn err4() -> Result<()> {
+    err1_3().map_err(anyhow_error!("4_1"))?;
+    Ok(())
+}
+
+fn err4_2() -> Result<()> {
+    err4().map_err(anyhow_error!("4_2:{}", "4_2"))?;
+    Ok(())
+}
+
+fn err4_3() -> Result<()> {
+    let err = anyhow_located!("4_3");
+    err4_2().map_err(anyhow_error!(err))?;
+    Ok(())
+}
+
+fn err4_4() -> Result<()> {
+    err4_3().map_err(anyhow_error!())?;
+    Ok(())
+}
+
+fn panic_message<F: FnOnce() + panic::UnwindSafe>(f: F) -> String {
+    let payload = panic::catch_unwind(f).unwrap_err();
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => (*payload.downcast::<&str>().unwrap()).to_owned(),
+    }
+}
+
+#[test]
+fn test_assert_error_at() {
+    assert_error_at!(err1_3(), 9);
+    assert_error_at!(err4(), 13);
+    assert_error_at!(err4_2(), 18);
+    assert_error_at!(err4_3(), 24);
+    assert_error_at!(err4_4(), 29);
+    assert_error_at!(err4_4(), 29,);
+}
+
+#[test]
+fn test_assert_error_at_fails() {
+    let message = panic_message(|| assert_error_at!(err4_4(), 30));
+    assert!(
+        message.starts_with(&format!(
+            "assert_error_at! failed: expected the error to come from line 30, \
+             but its outermost frame is at {}:29\nframes:\n",
+            file!(),
+        )),
+        "{}",
+        message,
+    );
+    for line in &[29, 24, 18, 13, 9, 8] {
+        assert!(
+            message.contains(&format!("\n    {}:{} ", file!(), line)),
+            "{}",
+            message,
+        );
+    }
+    assert!(message.contains(&format!("\n    {}:18 4_2:4_2\n", file!())));
+
+    let message =
+        panic_message(|| assert_error_at!(Err::<(), _>(anyhow::Error::msg("untagged")), 1));
+    assert_eq!(
+        message,
+        "assert_error_at! failed: expected the error to come from line 1, \
+         but its outermost frame has no location\nframes:\n    (no location) untagged",
+    );
+
+    let message = panic_message(|| assert_error_at!(Ok::<(), anyhow::Error>(()), 5));
+    assert_eq!(
+        message,
+        "assert_error_at! expected an error from line 5, got Ok"
+    );
+}
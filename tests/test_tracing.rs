@@ -0,0 +1,41 @@
+#![cfg(feature = "tracing-integration")]
+
+use anyhow::{anyhow, anyhow_error, Error};
+use tracing::span::Span;
+use tracing_subscriber::registry::Registry;
+
+#[test]
+fn test_tracing_integration() {
+    let subscriber = Registry::default();
+    tracing::subscriber::with_default(subscriber, || {
+        let error = anyhow!("outside");
+        assert!(!error.to_string().contains(" span("), "{}", error);
+
+        let span = tracing::info_span!("load_config");
+        let _entered = span.enter();
+        let expected = format!(" span({}::load_config) emsg(", module_path!());
+
+        let error = anyhow!("disk full");
+        assert!(error.to_string().contains(&expected), "{}", error);
+        assert_eq!(error.emsg(), Some("disk full"));
+        assert_eq!(error.location().unwrap().line(), line!() - 3);
+
+        let error = Err::<(), _>(Error::msg("disk full"))
+            .map_err(anyhow_error!("writing {}", "log"))
+            .unwrap_err();
+        let line = line!() - 2;
+        let tagged = error.to_string();
+        assert!(tagged.contains(&format!(",{}", expected)), "{}", tagged);
+        assert_eq!(error.emsg(), Some("writing log"));
+        assert_eq!(error.location().unwrap().line(), line);
+
+        let frames = anyhow::parse_frames(&error);
+        assert_eq!(frames[0].msg, "writing log");
+        assert_eq!(frames[0].line, line);
+
+        // The span is that of the thread when the error is made.
+        drop(_entered);
+        assert!(Span::current().is_none());
+        assert!(!anyhow!("later").to_string().contains(" span("));
+    });
+}
@@ -0,0 +1,36 @@
+use anyhow::{anyhow_located, Context, Error};
+use std::io;
+
+// The build version is process-wide, so everything that sets one lives in
+// this single test.
+#[test]
+fn test_build_version() {
+    let before = Error::msg("oh no!");
+    assert_eq!(before.build_version(), None);
+
+    anyhow::set_build_version("1.2.3-abcdef0");
+    let error = anyhow_located!("oh no!").context("outer");
+    assert_eq!(error.build_version(), Some("1.2.3-abcdef0"));
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "disk full"))
+        .context("write failed")
+        .unwrap_err();
+    assert_eq!(error.build_version(), Some("1.2.3-abcdef0"));
+    #[cfg(not(feature = "with-location"))]
+    assert_eq!(
+        error.to_compact_json(),
+        r#"{"msg":"write failed: disk full","depth":2,"ver":"1.2.3-abcdef0"}"#,
+    );
+    assert!(error
+        .to_compact_json()
+        .ends_with(r#","ver":"1.2.3-abcdef0"}"#));
+
+    // Errors keep the version they were created under.
+    anyhow::set_build_version("1.2.4-1234567");
+    assert_eq!(
+        error.context("later").build_version(),
+        Some("1.2.3-abcdef0")
+    );
+    assert_eq!(before.context("later").build_version(), None);
+    assert_eq!(Error::msg("new").build_version(), Some("1.2.4-1234567"));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "with-location")]
+
+use anyhow::Context;
+use std::fmt::{self, Display};
+use std::io;
+
+fn io_error() -> Result<(), io::Error> {
+    Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+}
+
+#[test]
+fn test_context_location() {
+    let error = io_error().context("writing").unwrap_err();
+    let line = line!() - 1;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:28, emsg(writing)]", file!(), line),
+    );
+    let location = error.location().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    let error = io_error()
+        .with_context(|| format!("writing {}", "log"))
+        .unwrap_err();
+    let line = line!() - 2;
+    assert_eq!(
+        error.to_string(),
+        format!("[{}:{}:10, emsg(writing log)]", file!(), line),
+    );
+}
+
+#[derive(Debug)]
+struct Loading(u64);
+
+impl Display for Loading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "loading user {}", self.0)
+    }
+}
+
+#[test]
+fn test_context_downcast() {
+    let error = io_error().context(Loading(7)).unwrap_err();
+    let line = line!() - 1;
+    assert_eq!(error.downcast_ref::<Loading>().unwrap().0, 7);
+    assert_eq!(error.emsg(), Some("loading user 7"));
+    assert_eq!(error.location().unwrap().line(), line);
+
+    let chain: Vec<_> = error.chain().map(ToString::to_string).collect();
+    let tagged = format!("[{}:{}:28, emsg(loading user 7)]", file!(), line);
+    assert_eq!(chain, [&*tagged, "loading user 7", "disk full"]);
+
+    let error = error.downcast::<Loading>().unwrap();
+    assert_eq!(error.0, 7);
+}
@@ -0,0 +1,6 @@
+use anyhow::{anyhow, anyhow_error, Error};
+
+fn main() {
+    let _ = anyhow!("x");
+    let _ = Err::<(), _>(Error::msg("a")).map_err(anyhow_error!("x"));
+}
@@ -0,0 +1,14 @@
+use anyhow::{anyhow_error, Error};
+
+fn tagger<F: FnOnce(Error) -> Error>(f: F) -> F {
+    f
+}
+
+// The closure borrows the value it formats, so the value cannot be moved
+// while the closure is alive.
+fn main() {
+    let name = String::from("config");
+    let tag = tagger(anyhow_error!(name));
+    drop(name);
+    let _ = tag(Error::msg("disk full"));
+}
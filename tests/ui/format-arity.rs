@@ -0,0 +1,12 @@
+use anyhow::{anyhow_error, bail, Result};
+
+fn parse(input: &str) -> Result<u32> {
+    if input.is_empty() {
+        bail!("empty input", input);
+    }
+    input.parse::<u32>().map_err(anyhow_error!("invalid number {}"))
+}
+
+fn main() {
+    let _ = parse("");
+}
@@ -0,0 +1,5 @@
+use anyhow::anyhow;
+
+fn main() {
+    let _ = anyhow!("{}");
+}
@@ -0,0 +1,15 @@
+use anyhow::{anyhow_error, Error};
+
+fn main() {
+    // Copy and non-Copy values alike are borrowed by the closure, so each can
+    // be used at several sites and after them.
+    let code = 7;
+    let name = String::from("config");
+
+    let _ = Err::<(), _>(Error::msg("a")).map_err(anyhow_error!(code));
+    let _ = Err::<(), _>(Error::msg("b")).map_err(anyhow_error!(code));
+    let _ = Err::<(), _>(Error::msg("c")).map_err(anyhow_error!(name));
+    let _ = Err::<(), _>(Error::msg("d")).map_err(anyhow_error!(name));
+
+    println!("{} {}", code, name);
+}